@@ -1,7 +1,9 @@
 //! Demonstrates how to use [`embassy-net`] with the [`sntpc`] library.
 //!
 //! This example fetches the current time from a NTP server using the
-//! SNTP client library and prints the result.
+//! SNTP client library and prints the result. Timestamps are taken from the
+//! `embassy-time` clock with [`sntpc::embassy::EmbassyTimestampGen`], so the
+//! reported offset is the UNIX time the clock has started at.
 //!
 //! ## Create a TUN/TAP interface
 //!
@@ -69,37 +71,21 @@ cfg_unix! {
     use embassy_net::udp::{PacketMetadata, UdpSocket};
     use embassy_net::{Config, Ipv4Address, Ipv4Cidr, StackResources};
     use embassy_net_tuntap::TunTapDevice;
-    use embassy_time::{Duration, Timer};
+    use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+    use embassy_sync::signal::Signal;
+    use embassy_time::Duration;
     use heapless::Vec;
-    use sntpc::{get_time, NtpContext, NtpTimestampGenerator};
+    use sntpc::embassy::{run_sync_loop, EmbassyTimestampGen};
+    use sntpc::{NtpContext, NtpResult};
     use static_cell::StaticCell;
 
     use core::net::{IpAddr, SocketAddr};
-    use std::time::SystemTime;
     use std::thread;
 
     const NTP_SERVER: &str = "pool.ntp.org";
 
-    #[derive(Copy, Clone, Default)]
-    struct Timestamp {
-        duration: std::time::Duration,
-    }
-
-    impl NtpTimestampGenerator for Timestamp {
-        fn init(&mut self) {
-            self.duration = SystemTime::now()
-                .duration_since(SystemTime::UNIX_EPOCH)
-                .unwrap();
-        }
-
-        fn timestamp_sec(&self) -> u64 {
-            self.duration.as_secs()
-        }
-
-        fn timestamp_subsec_micros(&self) -> u32 {
-            self.duration.subsec_micros()
-        }
-    }
+    static LATEST_TIME: Signal<CriticalSectionRawMutex, NtpResult> =
+        Signal::new();
 
     use defmt::{error, info};
 
@@ -110,6 +96,19 @@ cfg_unix! {
         runner.run().await
     }
 
+    #[embassy_executor::task]
+    async fn report_task() -> ! {
+        loop {
+            let time = LATEST_TIME.wait().await;
+            // the offset is relative to the `embassy-time` clock start
+            let now_us =
+                i64::try_from(embassy_time::Instant::now().as_micros())
+                    .unwrap_or(i64::MAX)
+                    .saturating_add(time.offset());
+            info!("Time: {:?}, UNIX time: {}us", time, now_us);
+        }
+    }
+
     #[embassy_executor::task]
     async fn main_task(spawner: Spawner) {
         static RESOURCES: StaticCell<StackResources<3>> = StaticCell::new();
@@ -154,7 +153,7 @@ cfg_unix! {
         );
         socket.bind(123).unwrap();
 
-        let context = NtpContext::new(Timestamp::default());
+        let context = NtpContext::new(EmbassyTimestampGen::default());
 
         let ntp_addrs = stack
             .dns_query(NTP_SERVER, DnsQueryType::A)
@@ -165,23 +164,18 @@ cfg_unix! {
             return;
         }
 
-        loop {
-            let addr: IpAddr = ntp_addrs[0].into();
-            let result =
-                get_time(SocketAddr::from((addr, 123)), &socket, context)
-                    .await;
-
-            match result {
-                Ok(time) => {
-                    info!("Time: {:?}", time);
-                }
-                Err(e) => {
-                    error!("Error getting time: {:?}", e);
-                }
-            }
-
-            Timer::after(Duration::from_secs(15)).await;
-        }
+        // Launch a task that reports every time update
+        spawner.spawn(report_task()).unwrap();
+
+        let addr: IpAddr = ntp_addrs[0].into();
+        run_sync_loop(
+            &socket,
+            SocketAddr::from((addr, 123)),
+            context,
+            Duration::from_secs(15),
+            &LATEST_TIME,
+        )
+        .await;
     }
 
     static EXECUTOR: StaticCell<Executor> = StaticCell::new();
@@ -4,7 +4,7 @@
 //! required for the `sntpc` library
 use sntpc::{sync::get_time, NtpContext, StdTimestampGen};
 
-use std::net::{ToSocketAddrs, UdpSocket};
+use std::net::UdpSocket;
 use std::thread;
 use std::time::Duration;
 
@@ -27,7 +27,7 @@ fn main() {
         .set_read_timeout(Some(Duration::from_secs(2)))
         .expect("Unable to set UDP socket read timeout");
 
-    for addr in POOL_NTP_ADDR.to_socket_addrs().unwrap() {
+    for addr in sntpc::resolve(POOL_NTP_ADDR).unwrap() {
         let ntp_context = NtpContext::new(StdTimestampGen::default());
         let result = get_time(addr, &socket, ntp_context);
 
@@ -5,7 +5,7 @@
 //! on UNIX OS specific calls in the standard library. This example should provide all details on
 //! how to set up networking interface to use with the `sntpc` library though.
 //!
-//! ## How to set up the environment (IPv4 only considered for now):
+//! ## How to set up the environment (IPv4 is considered, IPv6 setup is similar):
 //!
 //! - create TAP interface (`sudo` may require):
 //! ```sh
@@ -79,7 +79,6 @@
 //!
 #[cfg(unix)]
 use {
-    core::cell::RefCell,
     core::net::{IpAddr, SocketAddr},
     core::str::FromStr,
     smoltcp::iface::PollResult,
@@ -88,9 +87,10 @@ use {
     smoltcp::phy::{wait, Medium},
     smoltcp::socket::udp,
     smoltcp::time::Instant,
-    smoltcp::wire::{EthernetAddress, IpCidr, Ipv4Address},
+    smoltcp::wire::{EthernetAddress, IpAddress, IpCidr},
     sntpc::{
-        sync::{sntp_process_response, sntp_send_request},
+        smoltcp::{Buffers, UdpSocketBuffers},
+        sync::{sntp_process_response_mut, sntp_send_request_mut},
         NtpContext,
     },
     std::os::unix::prelude::AsRawFd,
@@ -100,53 +100,8 @@ use {
 pub mod internal {
     use {
         clap::{crate_version, App, Arg, ArgMatches},
-        core::cell::RefCell,
-        core::fmt::Debug,
-        smoltcp::socket::udp,
-        smoltcp::socket::udp::UdpMetadata,
-        smoltcp::storage::PacketMetadata,
-        smoltcp::wire::{IpAddress, IpEndpoint},
-        sntpc::{Error, NtpTimestampGenerator, NtpUdpSocket},
-        std::fmt::Formatter,
-        std::net::{IpAddr, SocketAddr},
+        sntpc::NtpTimestampGenerator,
     };
-    pub struct Buffers {
-        pub rx_meta: [PacketMetadata<UdpMetadata>; 16],
-        pub tx_meta: [PacketMetadata<UdpMetadata>; 16],
-        pub rx_buffer: [u8; 256],
-        pub tx_buffer: [u8; 256],
-    }
-
-    impl Default for Buffers {
-        fn default() -> Self {
-            Buffers {
-                rx_meta: [PacketMetadata::EMPTY; 16],
-                tx_meta: [PacketMetadata::EMPTY; 16],
-                rx_buffer: [0u8; 256],
-                tx_buffer: [0u8; 256],
-            }
-        }
-    }
-
-    pub struct UdpSocketBuffers<'a> {
-        pub rx: udp::PacketBuffer<'a>,
-        pub tx: udp::PacketBuffer<'a>,
-    }
-
-    impl<'a> UdpSocketBuffers<'a> {
-        pub fn new(buffers: &'a mut Buffers) -> Self {
-            UdpSocketBuffers {
-                rx: udp::PacketBuffer::new(
-                    buffers.rx_meta.as_mut(),
-                    buffers.rx_buffer.as_mut(),
-                ),
-                tx: udp::PacketBuffer::new(
-                    buffers.tx_meta.as_mut(),
-                    buffers.tx_buffer.as_mut(),
-                ),
-            }
-        }
-    }
 
     #[derive(Copy, Clone, Default)]
     pub struct StdTimestampGen {
@@ -169,60 +124,6 @@ pub mod internal {
         }
     }
 
-    pub struct SmoltcpUdpSocketWrapper<'a, 'b> {
-        pub socket: RefCell<&'b mut udp::Socket<'a>>,
-    }
-
-    impl Debug for SmoltcpUdpSocketWrapper<'_, '_> {
-        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-            f.debug_struct("SmoltcpUdpSocketWrapper")
-                .field("socket", &self.socket.borrow().endpoint())
-                .finish()
-        }
-    }
-
-    impl NtpUdpSocket for SmoltcpUdpSocketWrapper<'_, '_> {
-        async fn send_to(
-            &self,
-            buf: &[u8],
-            addr: SocketAddr,
-        ) -> Result<usize, Error> {
-            let endpoint = match addr {
-                SocketAddr::V4(v4) => IpEndpoint::from(v4),
-                SocketAddr::V6(_) => return Err(Error::Network),
-            };
-
-            if self.socket.borrow_mut().send_slice(buf, endpoint).is_ok() {
-                return Ok(buf.len());
-            }
-
-            Err(Error::Network)
-        }
-
-        async fn recv_from(
-            &self,
-            buf: &mut [u8],
-        ) -> Result<(usize, SocketAddr), Error> {
-            let result = self.socket.borrow_mut().recv_slice(&mut buf[..]);
-
-            if let Ok((size, address)) = result {
-                // make compiler and clippy happy as without the else branch clippy complains
-                // that not all variants covered for some reason
-                #[allow(irrefutable_let_patterns)]
-                let IpAddress::Ipv4(v4) = address.endpoint.addr
-                else {
-                    todo!()
-                };
-                let sockaddr =
-                    SocketAddr::new(IpAddr::V4(v4), address.endpoint.port);
-
-                return Ok((size, sockaddr));
-            }
-
-            Err(Error::Network)
-        }
-    }
-
     #[must_use]
     pub fn create_app_cli() -> ArgMatches<'static> {
         const GOOGLE_NTP_ADDR: &str = "pool.ntp.org";
@@ -288,10 +189,7 @@ pub mod internal {
 }
 
 #[cfg(unix)]
-use internal::{
-    create_app_cli, Buffers, SmoltcpUdpSocketWrapper, StdTimestampGen,
-    UdpSocketBuffers,
-};
+use internal::{create_app_cli, StdTimestampGen};
 
 #[cfg(unix)]
 fn main() {
@@ -314,7 +212,7 @@ fn main() {
         .expect("Cannot parse MAC address of the interface");
     let ip_addr = IpCidr::from_str(app.value_of("ip").unwrap())
         .expect("Cannot parse IP address of the interface");
-    let default_gw = Ipv4Address::from_str(app.value_of("gw").unwrap())
+    let default_gw = IpAddress::from_str(app.value_of("gw").unwrap())
         .expect("Cannot parse GW address of the interface");
     let sock_port = u16::from_str(app.value_of("sock_port").unwrap())
         .expect("Unable to parse socket port");
@@ -331,10 +229,11 @@ fn main() {
     let mut iface =
         Interface::new(config, &mut tuntap, std::time::Instant::now().into());
     iface.update_ip_addrs(|ip_addrs| ip_addrs.push(ip_addr).unwrap());
-    iface
-        .routes_mut()
-        .add_default_ipv4_route(default_gw)
-        .unwrap();
+    match default_gw {
+        IpAddress::Ipv4(gw) => iface.routes_mut().add_default_ipv4_route(gw),
+        IpAddress::Ipv6(gw) => iface.routes_mut().add_default_ipv6_route(gw),
+    }
+    .unwrap();
 
     // let mut socket_items = [None; 1];
     let mut sockets = SocketSet::new(vec![]);
@@ -356,14 +255,12 @@ fn main() {
 
         if once_tx && sockets.get::<udp::Socket>(udp_handle).can_send() {
             once_tx = false;
-            let sock_wrapper = SmoltcpUdpSocketWrapper {
-                socket: RefCell::new(
-                    sockets.get_mut::<udp::Socket>(udp_handle),
-                ),
-            };
             let context = NtpContext::new(StdTimestampGen::default());
-            let result =
-                sntp_send_request(server_sock_addr, &sock_wrapper, context);
+            let result = sntp_send_request_mut(
+                server_sock_addr,
+                sockets.get_mut::<udp::Socket>(udp_handle),
+                context,
+            );
 
             match result {
                 Ok(result) => {
@@ -372,6 +269,8 @@ fn main() {
                 Err(e) => {
                     #[cfg(feature = "log")]
                     log::error!("send error: {e:?}");
+                    #[cfg(not(feature = "log"))]
+                    eprintln!("send error: {e:?}");
                     once_tx = true;
                 }
             }
@@ -384,24 +283,18 @@ fn main() {
             if once_rx && sockets.get::<udp::Socket>(udp_handle).can_recv() {
                 once_rx = false;
 
-                #[cfg(feature = "log")]
-                {
-                    let context = NtpContext::new(StdTimestampGen::default());
-                    let sock_wrapper = SmoltcpUdpSocketWrapper {
-                        socket: RefCell::new(
-                            sockets.get_mut::<udp::Socket>(udp_handle),
-                        ),
-                    };
-                    let result = sntp_process_response(
-                        server_sock_addr,
-                        &sock_wrapper,
-                        context,
-                        tx_result,
-                    );
+                let context = NtpContext::new(StdTimestampGen::default());
+                let result = sntp_process_response_mut(
+                    server_sock_addr,
+                    sockets.get_mut::<udp::Socket>(udp_handle),
+                    context,
+                    tx_result,
+                );
 
-                    #[cfg(feature = "log")]
-                    log::info!("{result:?}");
-                }
+                #[cfg(feature = "log")]
+                log::info!("{result:?}");
+                #[cfg(not(feature = "log"))]
+                println!("{result:?}");
             }
         }
 
@@ -84,6 +84,11 @@ fn main() {
             });
 
         println!("Received time: {result:?}");
-        sntpc::utils::update_system_time(result.sec(), result.sec_fraction());
+        if let Err(err) = sntpc::utils::update_system_time(
+            result.sec(),
+            result.sec_fraction(),
+        ) {
+            eprintln!("Unable to update system time: {err}");
+        }
     }
 }
@@ -0,0 +1,34 @@
+use futures_util::StreamExt;
+use sntpc::{stream::ntp_stream, NtpContext, StdTimestampGen};
+use tokio::net::{lookup_host, UdpSocket};
+use tokio::time::Duration;
+
+use core::net::SocketAddr;
+use std::sync::Arc;
+
+const POOL_NTP_ADDR: (&str, u16) = ("pool.ntp.org", 123);
+const POLL_INTERVAL: Duration = Duration::from_secs(16);
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    let socket = UdpSocket::bind("0.0.0.0:0".parse::<SocketAddr>().unwrap())
+        .await
+        .expect("Socket creation");
+    let ntp_context = NtpContext::new(StdTimestampGen::default());
+    let addr = lookup_host(POOL_NTP_ADDR)
+        .await
+        .expect("Unable to resolve address")
+        .find(SocketAddr::is_ipv4)
+        .expect("No IPv4 address resolved");
+    let mut results =
+        ntp_stream(addr, Arc::new(socket), ntp_context, POLL_INTERVAL);
+
+    println!("Polling {addr} every {POLL_INTERVAL:?}");
+
+    while let Some(res) = results.next().await {
+        match res {
+            Ok(res) => println!("RESULT: {res:?}"),
+            Err(err) => println!("ERROR: {err:?}"),
+        }
+    }
+}
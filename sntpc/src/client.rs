@@ -0,0 +1,132 @@
+//! SNTP client bound to a single server
+//!
+//! [`NtpClient`] keeps the socket, the server address and the context together, so periodic
+//! time queries do not need to pass them around on every call.
+use crate::net::SocketAddr;
+use crate::{
+    get_time, timestamp_micros, Error, NtpContext, NtpResult,
+    NtpTimestampGenerator, NtpUdpSocket, Result,
+};
+
+use core::cell::Cell;
+use core::time::Duration;
+
+/// SNTP client that queries a single server over a persistent socket
+///
+/// The client may optionally enforce a minimum interval between queries, so it is safe to be
+/// called in a loop without flooding the server:
+///
+/// ```rust,no_run
+/// # #[cfg(feature = "std-socket")]
+/// # async fn query() -> sntpc::Result<()> {
+/// use sntpc::{NtpClient, NtpContext, StdTimestampGen};
+/// use std::net::UdpSocket;
+/// use std::time::Duration;
+///
+/// let socket = UdpSocket::bind("0.0.0.0:0").unwrap();
+/// let context = NtpContext::new(StdTimestampGen::default());
+/// let server = "216.239.35.0:123".parse().unwrap();
+/// let client = NtpClient::new(server, socket, context)
+///     .with_min_interval(Duration::from_secs(16));
+///
+/// let result = client.query().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct NtpClient<U, T: NtpTimestampGenerator> {
+    server: SocketAddr,
+    socket: U,
+    context: NtpContext<T>,
+    min_interval: Duration,
+    last_query: Cell<Option<u64>>,
+}
+
+impl<U, T> NtpClient<U, T>
+where
+    U: NtpUdpSocket,
+    T: NtpTimestampGenerator + Copy,
+{
+    /// Create a client that queries `server` over `socket`
+    ///
+    /// Queries are not rate limited by default, see [`NtpClient::with_min_interval`].
+    pub fn new(server: SocketAddr, socket: U, context: NtpContext<T>) -> Self {
+        NtpClient {
+            server,
+            socket,
+            context,
+            min_interval: Duration::ZERO,
+            last_query: Cell::new(None),
+        }
+    }
+
+    /// Set the minimum interval between two queries
+    ///
+    /// Queries issued earlier fail with [`Error::RateLimited`]. The time is taken from the
+    /// context's timestamp generator.
+    #[must_use]
+    pub fn with_min_interval(mut self, min_interval: Duration) -> Self {
+        self.min_interval = min_interval;
+        self
+    }
+
+    /// Returns the address of the server the client queries
+    pub fn server(&self) -> SocketAddr {
+        self.server
+    }
+
+    /// Returns the socket used for queries
+    pub fn socket(&self) -> &U {
+        &self.socket
+    }
+
+    /// Returns the SNTP context used for queries
+    pub fn context(&self) -> &NtpContext<T> {
+        &self.context
+    }
+
+    /// Query the server for the current time
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::RateLimited`] with the remaining wait time if the previous query has
+    /// been issued less than the minimum interval ago. Otherwise, returns an `Err` in the same
+    /// cases as [`get_time`].
+    pub async fn query(&self) -> Result<NtpResult> {
+        self.acquire()?;
+
+        get_time(self.server, &self.socket, self.context).await
+    }
+
+    /// Query the server for the current time synchronously
+    ///
+    /// This is a synchronous version of [`NtpClient::query`] built on [`crate::sync::get_time`].
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` in the same cases as [`NtpClient::query`]
+    #[cfg(any(feature = "sync", feature = "minimal-sync"))]
+    pub fn query_sync(&self) -> Result<NtpResult> {
+        self.acquire()?;
+
+        crate::sync::get_time(self.server, &self.socket, self.context)
+    }
+
+    fn acquire(&self) -> Result<()> {
+        let mut timestamp_gen = self.context.timestamp_gen;
+        let now = timestamp_micros(&mut timestamp_gen);
+
+        if let Some(last_query) = self.last_query.get() {
+            let elapsed = Duration::from_micros(now.saturating_sub(last_query));
+
+            if elapsed < self.min_interval {
+                return Err(Error::RateLimited(
+                    self.min_interval.saturating_sub(elapsed),
+                ));
+            }
+        }
+
+        self.last_query.set(Some(now));
+        Ok(())
+    }
+}
@@ -0,0 +1,312 @@
+//! Helpers for running the SNTP client on top of the [`embassy`](https://embassy.dev) stack
+//!
+//! The module is available with the `embassy-socket` feature enabled and relies on
+//! `embassy-time` timers to bound requests and schedule periodic ones.
+#[cfg(any(feature = "log", feature = "defmt"))]
+use crate::log::{debug, error};
+use crate::types::USEC_IN_SEC;
+use crate::{
+    get_time, get_time_any, net::SocketAddr, Error, NtpContext, NtpResult,
+    NtpTimestampGenerator, NtpUdpSocket, Result,
+};
+
+use embassy_net::dns::DnsQueryType;
+use embassy_net::udp::{PacketMetadata, UdpSocket};
+use embassy_net::Stack;
+use embassy_sync::blocking_mutex::raw::RawMutex;
+use embassy_sync::signal::Signal;
+use embassy_time::{with_deadline, with_timeout, Duration, Instant, Timer};
+
+use core::net::{IpAddr, Ipv4Addr};
+
+/// Maximum power of two the sync interval is multiplied by after consecutive failures
+const MAX_BACKOFF_SHIFT: u32 = 3;
+/// Upper bound of the delay after failures, the maximum NTP poll interval of 2^17 seconds
+const MAX_BACKOFF_DELAY: Duration = Duration::from_secs(1 << 17);
+/// Maximum number of resolved server addresses [`embassy_get_time`] tries
+const MAX_SERVERS: usize = 4;
+/// Number of packets the socket created by [`embassy_get_time`] is able to hold
+const PACKETS_NUM: usize = 4;
+/// Size of the socket payload storage in each direction
+const PAYLOAD_SIZE: usize = 256;
+
+/// Resolve `host` and request the time from it over a temporary UDP socket
+///
+/// This is the end-to-end helper that performs DNS resolution with the `stack` DNS servers,
+/// binds a UDP socket to an ephemeral port, sends the request and waits for the response.
+/// Resolved addresses are tried in order until one of them responds. The whole operation,
+/// DNS resolution included, is bounded by `timeout`.
+///
+/// # Arguments
+///
+/// * `stack` - An `embassy-net` network stack with DNS servers configured
+/// * `host` - Host name or IPv4 address of the NTP server
+/// * `port` - Port of the NTP server, usually `123`
+/// * `timeout` - Maximum time to wait for the result
+/// * `context` - An SNTP context containing a timestamp generator
+///
+/// # Errors
+///
+/// Returns [`Error::AddressResolve`] if `host` cannot be resolved, [`Error::Network`] if the
+/// socket cannot be bound, [`Error::Timeout`] if `timeout` elapses before a valid response is
+/// received, or the last error reported by [`get_time_any`] if no server responded properly.
+///
+/// # Example
+///
+/// ```no_run
+/// # use sntpc::{embassy::embassy_get_time, NtpContext, NtpTimestampGenerator};
+/// # use embassy_time::Duration;
+/// # #[derive(Copy, Clone, Default)]
+/// # struct Timestamp;
+/// # impl NtpTimestampGenerator for Timestamp {
+/// #     fn init(&mut self) {}
+/// #     fn timestamp_sec(&self) -> u64 { 0 }
+/// #     fn timestamp_subsec_micros(&self) -> u32 { 0 }
+/// # }
+/// async fn print_time(stack: embassy_net::Stack<'_>) {
+///     let context = NtpContext::new(Timestamp::default());
+///     let result = embassy_get_time(
+///         stack,
+///         "pool.ntp.org",
+///         123,
+///         Duration::from_secs(5),
+///         context,
+///     )
+///     .await;
+///
+///     if let Ok(time) = result {
+///         assert_ne!(time.sec(), 0);
+///     }
+/// }
+/// ```
+#[cfg_attr(
+    not(any(feature = "log", feature = "defmt")),
+    allow(unused_variables)
+)]
+pub async fn embassy_get_time<T>(
+    stack: Stack<'_>,
+    host: &str,
+    port: u16,
+    timeout: Duration,
+    context: NtpContext<T>,
+) -> Result<NtpResult>
+where
+    T: NtpTimestampGenerator + Copy,
+{
+    let deadline = Instant::now() + timeout;
+    let resolved =
+        match with_deadline(deadline, stack.dns_query(host, DnsQueryType::A))
+            .await
+        {
+            Ok(Ok(resolved)) => resolved,
+            Ok(Err(e)) => {
+                #[cfg(any(feature = "log", feature = "defmt"))]
+                debug!("Unable to resolve {}: {:?}", host, e);
+                return Err(Error::AddressResolve);
+            }
+            Err(_) => return Err(Error::Timeout),
+        };
+
+    let mut servers =
+        [SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), port); MAX_SERVERS];
+    let mut count = 0;
+
+    for (slot, addr) in servers.iter_mut().zip(resolved.iter()) {
+        *slot = SocketAddr::new((*addr).into(), port);
+        count += 1;
+    }
+
+    let mut rx_meta = [PacketMetadata::EMPTY; PACKETS_NUM];
+    let mut rx_buffer = [0u8; PAYLOAD_SIZE];
+    let mut tx_meta = [PacketMetadata::EMPTY; PACKETS_NUM];
+    let mut tx_buffer = [0u8; PAYLOAD_SIZE];
+    let mut socket = UdpSocket::new(
+        stack,
+        &mut rx_meta,
+        &mut rx_buffer,
+        &mut tx_meta,
+        &mut tx_buffer,
+    );
+
+    if let Err(e) = socket.bind(0) {
+        #[cfg(any(feature = "log", feature = "defmt"))]
+        debug!("Unable to bind socket: {:?}", e);
+        return Err(Error::Network);
+    }
+
+    get_time_until(&servers[..count], &socket, context, deadline).await
+}
+
+/// Request the time from the first responding server in `addrs` within `timeout`
+///
+/// That is the socket agnostic part of [`embassy_get_time`] that may be used with already
+/// resolved addresses and any [`NtpUdpSocket`] implementation. Servers are tried in order as
+/// [`get_time_any`] does, but the whole sequence is bounded by a single `embassy-time` timer.
+///
+/// # Errors
+///
+/// Returns [`Error::Timeout`] if `timeout` elapses before a valid response is received, or
+/// the error reported by [`get_time_any`] otherwise.
+pub async fn get_time_with_timeout<U, T>(
+    addrs: &[SocketAddr],
+    socket: &U,
+    context: NtpContext<T>,
+    timeout: Duration,
+) -> Result<NtpResult>
+where
+    U: NtpUdpSocket,
+    T: NtpTimestampGenerator + Copy,
+{
+    get_time_until(addrs, socket, context, Instant::now() + timeout).await
+}
+
+async fn get_time_until<U, T>(
+    addrs: &[SocketAddr],
+    socket: &U,
+    context: NtpContext<T>,
+    deadline: Instant,
+) -> Result<NtpResult>
+where
+    U: NtpUdpSocket,
+    T: NtpTimestampGenerator + Copy,
+{
+    with_deadline(deadline, get_time_any(addrs, socket, context))
+        .await
+        .unwrap_or(Err(Error::Timeout))
+}
+
+/// Periodically query an NTP server and publish every successful result into `signal`
+///
+/// Each request is bounded by `interval`, so a lost response does not stall the loop. After
+/// a failure the next attempt is delayed by `interval` doubled for every consecutive failure,
+/// up to `interval * 2^3` or the maximum NTP poll interval of 2^17 seconds, whichever is
+/// shorter, but never below `interval`. A successful response resets the delay back to
+/// `interval`.
+///
+/// The function never returns and does not panic on network errors, so it is intended to be
+/// run as a dedicated task. Consumers may wait on `signal` to obtain the latest [`NtpResult`].
+///
+/// # Arguments
+///
+/// * `socket` - A reference to an object implementing the [`NtpUdpSocket`] trait, e.g.
+///   `embassy_net::udp::UdpSocket`
+/// * `server` - The socket address of the NTP server
+/// * `context` - An SNTP context containing a timestamp generator
+/// * `interval` - Delay between two successful requests
+/// * `signal` - Signal that receives the latest successful [`NtpResult`]
+#[cfg_attr(
+    not(any(feature = "log", feature = "defmt")),
+    allow(unused_variables)
+)]
+pub async fn run_sync_loop<U, T, M>(
+    socket: &U,
+    server: SocketAddr,
+    context: NtpContext<T>,
+    interval: Duration,
+    signal: &Signal<M, NtpResult>,
+) -> !
+where
+    U: NtpUdpSocket,
+    T: NtpTimestampGenerator + Copy,
+    M: RawMutex,
+{
+    let mut failures = 0u32;
+
+    loop {
+        match with_timeout(interval, get_time(server, socket, context)).await {
+            Ok(Ok(result)) => {
+                #[cfg(any(feature = "log", feature = "defmt"))]
+                debug!("Sync loop result: {:?}", result);
+                failures = 0;
+                signal.signal(result);
+            }
+            Ok(Err(e)) => {
+                #[cfg(any(feature = "log", feature = "defmt"))]
+                error!("Sync loop request failed: {:?}", e);
+                failures = failures.saturating_add(1);
+            }
+            Err(_) => {
+                #[cfg(any(feature = "log", feature = "defmt"))]
+                error!("Sync loop request timed out");
+                failures = failures.saturating_add(1);
+            }
+        }
+
+        Timer::after(backoff_delay(interval, failures)).await;
+    }
+}
+
+/// Delay before the next request of [`run_sync_loop`] after `failures` consecutive failures
+fn backoff_delay(interval: Duration, failures: u32) -> Duration {
+    let factor = 1u32
+        .checked_shl(failures.min(MAX_BACKOFF_SHIFT))
+        .unwrap_or(u32::MAX);
+    let delay = interval
+        .checked_mul(factor)
+        .map_or(MAX_BACKOFF_DELAY, |delay| delay.min(MAX_BACKOFF_DELAY));
+
+    delay.max(interval)
+}
+
+/// Timestamp generator based on the `embassy-time` monotonic clock
+///
+/// `embassy-time` counts time since an arbitrary epoch, usually the boot of the device, rather
+/// than since the UNIX epoch. The generator reports that time as is and the conversion to the
+/// NTP era is applied on top of it, so the offset of an [`NtpResult`] is the UNIX time of the
+/// `embassy-time` epoch, i.e. the wall clock time is the current [`Instant`] plus the offset:
+///
+/// ```no_run
+/// # use sntpc::embassy::EmbassyTimestampGen;
+/// # use sntpc::NtpContext;
+/// # async fn sync(socket: embassy_net::udp::UdpSocket<'_>) {
+/// let context = NtpContext::new(EmbassyTimestampGen::default());
+/// let server = "216.239.35.0:123".parse().unwrap();
+///
+/// if let Ok(result) = sntpc::get_time(server, &socket, context).await {
+///     let boot_time_us = result.offset();
+///     let now_us = embassy_time::Instant::now().as_micros() as i64 + boot_time_us;
+/// }
+/// # }
+/// ```
+#[derive(Copy, Clone, Debug, Default)]
+pub struct EmbassyTimestampGen {
+    micros: u64,
+}
+
+impl NtpTimestampGenerator for EmbassyTimestampGen {
+    fn init(&mut self) {
+        self.micros = Instant::now().as_micros();
+    }
+
+    fn timestamp_sec(&self) -> u64 {
+        self.micros / u64::from(USEC_IN_SEC)
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn timestamp_subsec_micros(&self) -> u32 {
+        (self.micros % u64::from(USEC_IN_SEC)) as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{backoff_delay, MAX_BACKOFF_DELAY};
+
+    use embassy_time::Duration;
+
+    #[test]
+    fn test_backoff_delay() {
+        let interval = Duration::from_secs(64);
+
+        assert_eq!(interval, backoff_delay(interval, 0));
+        assert_eq!(interval * 2, backoff_delay(interval, 1));
+        assert_eq!(interval * 8, backoff_delay(interval, 3));
+        assert_eq!(interval * 8, backoff_delay(interval, u32::MAX));
+        // the doubled interval is clamped to the maximum poll interval
+        let interval = Duration::from_secs(1 << 16);
+        assert_eq!(MAX_BACKOFF_DELAY, backoff_delay(interval, 3));
+        // large intervals must not overflow
+        let interval = Duration::from_ticks(u64::MAX / 2);
+        assert_eq!(interval, backoff_delay(interval, 3));
+    }
+}
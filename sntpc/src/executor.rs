@@ -0,0 +1,40 @@
+//! Minimal executor for the synchronous interface
+//!
+//! Enabled with the `minimal-sync` feature to drive the synchronous wrappers without the
+//! `miniloop` dependency. The `sync` feature takes priority if both are enabled.
+use core::future::Future;
+use core::pin::pin;
+use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+const NOOP_VTABLE: RawWakerVTable =
+    RawWakerVTable::new(|_| NOOP_RAW_WAKER, |_| {}, |_| {}, |_| {});
+const NOOP_RAW_WAKER: RawWaker = RawWaker::new(core::ptr::null(), &NOOP_VTABLE);
+
+/// Busy-polling executor that drives a single future to completion
+///
+/// Wake-ups are ignored and the future is polled again right away, which is enough for the
+/// socket implementations that block or fail instead of returning `Poll::Pending`.
+pub(crate) struct Executor;
+
+impl Executor {
+    pub(crate) fn new() -> Self {
+        Executor
+    }
+
+    // takes `&mut self` to mirror `miniloop::executor::Executor::block_on`
+    #[allow(clippy::unused_self)]
+    pub(crate) fn block_on<F: Future>(&mut self, future: F) -> F::Output {
+        // SAFETY: the vtable functions do not dereference the data pointer
+        let waker = unsafe { Waker::from_raw(NOOP_RAW_WAKER) };
+        let mut ctx = Context::from_waker(&waker);
+        let mut future = pin!(future);
+
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut ctx) {
+                return output;
+            }
+
+            core::hint::spin_loop();
+        }
+    }
+}
@@ -0,0 +1,615 @@
+//! Clock filter that smooths offsets of consecutive SNTP requests
+//!
+//! A single offset measurement is affected by network jitter, so NTP clients keep a window of
+//! recent samples and trust the one with the lowest roundtrip delay, since it has been affected
+//! by queuing the least. See RFC 5905, section 10 "Clock Filter Algorithm".
+//!
+//! Results of several servers are combined with [`marzullo_intersect`]
+//! to rule out falsetickers, or with [`select_best`] to simply pick the closest server.
+//! Results of the same server queried some time apart give the drift rate of the local clock
+//! with [`estimate_drift_ppm`], while a series of them gives the jitter and the stability of
+//! the local clock with [`calculate_jitter`] and [`calculate_allan_deviation`].
+use crate::{ntp_result_to_duration, NtpResult};
+
+use core::time::Duration;
+
+/// Number of the most recent samples [`NtpClockFilter`] keeps
+pub const FILTER_SIZE: usize = 8;
+
+#[derive(Debug, Copy, Clone)]
+struct Sample {
+    offset: i64,
+    roundtrip: u64,
+}
+
+/// Fixed-size NTP clock filter
+///
+/// The filter keeps the [`FILTER_SIZE`] most recent samples. The best estimation of the clock
+/// offset is the offset of the sample with the lowest roundtrip delay. The dispersion is
+/// estimated as the sum of the offset differences of the rest of the samples from the best
+/// one, weighted by `1 / 2^(i + 1)` in the order of increasing delay, similar to the filter
+/// dispersion calculation of the reference implementation.
+///
+/// The jitter is the root mean square of the offset differences from the best sample as
+/// defined by RFC 5905.
+///
+/// All values are in the units of the [`NtpResult`] samples, microseconds by default.
+///
+/// # Example
+///
+/// ```rust
+/// use sntpc::filter::NtpClockFilter;
+/// use sntpc::NtpResult;
+///
+/// let mut filter = NtpClockFilter::new();
+///
+/// filter.push(&NtpResult::new(0, 0, 2_000, 150, 1, 0));
+/// filter.push(&NtpResult::new(0, 0, 500, 100, 1, 0));
+/// filter.push(&NtpResult::new(0, 0, 900, 120, 1, 0));
+///
+/// assert_eq!(Some(100), filter.best_offset());
+/// ```
+#[derive(Debug, Copy, Clone, Default)]
+pub struct NtpClockFilter {
+    samples: [Option<NtpResult>; FILTER_SIZE],
+    next: usize,
+}
+
+impl NtpClockFilter {
+    /// Create an empty filter
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            samples: [None; FILTER_SIZE],
+            next: 0,
+        }
+    }
+
+    /// Add the result of an SNTP request, replacing the oldest sample if the filter is full
+    pub fn push(&mut self, result: &NtpResult) {
+        self.samples[self.next] = Some(*result);
+        self.next = (self.next + 1) % FILTER_SIZE;
+    }
+
+    /// Returns the sample with the lowest roundtrip delay
+    #[must_use]
+    pub fn best_sample(&self) -> Option<&NtpResult> {
+        self.samples
+            .iter()
+            .flatten()
+            .min_by_key(|sample| sample.roundtrip())
+    }
+
+    /// Returns the offset of the sample with the lowest roundtrip delay
+    #[must_use]
+    pub fn best_offset(&self) -> Option<i64> {
+        self.best_sample().map(NtpResult::offset)
+    }
+
+    /// Returns the lowest roundtrip delay among the samples
+    #[must_use]
+    pub fn best_roundtrip(&self) -> Option<u64> {
+        self.best_sample().map(NtpResult::roundtrip)
+    }
+
+    /// Returns the estimated dispersion of the samples
+    #[must_use]
+    pub fn dispersion(&self) -> Option<u64> {
+        let (sorted, len) = self.sorted();
+        let best = sorted[..len].first()?;
+
+        let dispersion = sorted[1..len]
+            .iter()
+            .zip(2u32..)
+            .map(|(sample, shift)| {
+                sample.offset.abs_diff(best.offset) >> shift.min(63)
+            })
+            .fold(0u64, u64::saturating_add);
+
+        Some(dispersion)
+    }
+
+    /// Returns the jitter of the samples, zero if there is a single sample
+    #[must_use]
+    pub fn jitter(&self) -> Option<u64> {
+        let best = self.best_sample()?;
+        let len = self.len();
+
+        if len == 1 {
+            return Some(0);
+        }
+
+        let sum = self
+            .samples
+            .iter()
+            .flatten()
+            .map(|sample| {
+                let diff = u128::from(sample.offset().abs_diff(best.offset()));
+                diff * diff
+            })
+            .fold(0u128, u128::saturating_add);
+        let jitter = (sum / (len as u128 - 1)).isqrt();
+
+        Some(u64::try_from(jitter).unwrap_or(u64::MAX))
+    }
+
+    /// Returns the number of samples in the filter
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.samples.iter().flatten().count()
+    }
+
+    /// Returns `true` if there are no samples in the filter
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Forget all samples, e.g. after stepping the clock or switching to another server
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    fn sorted(&self) -> ([Sample; FILTER_SIZE], usize) {
+        let mut sorted = [Sample {
+            offset: 0,
+            roundtrip: 0,
+        }; FILTER_SIZE];
+        let mut len = 0;
+
+        for (slot, sample) in
+            sorted.iter_mut().zip(self.samples.iter().flatten())
+        {
+            *slot = Sample {
+                offset: sample.offset(),
+                roundtrip: sample.roundtrip(),
+            };
+            len += 1;
+        }
+
+        sorted[..len].sort_unstable_by_key(|sample| sample.roundtrip);
+
+        (sorted, len)
+    }
+}
+
+/// Find the clock offset interval most of the servers agree on with Marzullo's algorithm
+///
+/// Each sample is the result of a server along with its uncertainty on top of the network
+/// delay, e.g. the root dispersion. The true offset of the local clock is expected to be within
+/// half of the roundtrip delay plus the uncertainty of the measured offset. The function
+/// returns the smallest interval that is consistent with the most samples as a pair of the
+/// lower and upper bound, so servers whose intervals do not overlap with it are falsetickers.
+/// Offsets do not depend on the moment a server has been queried, so the servers may be
+/// queried one after another.
+///
+/// The bounds and uncertainties are in the units of the results, microseconds by default. All
+/// samples are expected to use the same units.
+///
+/// Returns `None` if less than a majority of the samples agree on an interval.
+///
+/// # Example
+///
+/// ```rust
+/// use sntpc::filter::marzullo_intersect;
+/// use sntpc::NtpResult;
+///
+/// let samples = [
+///     (NtpResult::new(1_700_000_000, 0, 20_000, 1_000, 1, 0), 0),
+///     (NtpResult::new(1_700_000_001, 0, 20_000, 5_000, 1, 0), 0),
+///     // a falseticker 5 seconds ahead of the others
+///     (NtpResult::new(1_700_000_007, 0, 20_000, 5_000_000, 1, 0), 0),
+/// ];
+///
+/// assert_eq!(Some((-5_000, 11_000)), marzullo_intersect(&samples));
+/// ```
+#[must_use]
+pub fn marzullo_intersect(samples: &[(NtpResult, u64)]) -> Option<(i64, i64)> {
+    let interval = |(result, uncertainty): &(NtpResult, u64)| {
+        let error = (result.roundtrip() / 2).saturating_add(*uncertainty);
+        let error = i64::try_from(error).unwrap_or(i64::MAX);
+
+        (
+            result.offset().saturating_sub(error),
+            result.offset().saturating_add(error),
+        )
+    };
+    let mut best: Option<(usize, (i64, i64))> = None;
+
+    // the interval with the most overlaps starts at the lower bound of one of the samples
+    // and ends at the nearest upper bound of the samples that contain that lower bound
+    for (lower, _) in samples.iter().map(interval) {
+        let (count, upper) = samples
+            .iter()
+            .map(interval)
+            .filter(|&(start, end)| start <= lower && lower <= end)
+            .fold((0, i64::MAX), |(count, upper), (_, end)| {
+                (count + 1, upper.min(end))
+            });
+
+        if best.is_none_or(|(best_count, _)| count > best_count) {
+            best = Some((count, (lower, upper)));
+        }
+    }
+
+    best.filter(|&(count, _)| 2 * count > samples.len())
+        .map(|(_, interval)| interval)
+}
+
+/// Pick the result with the lowest roundtrip delay, which has been affected by the network
+/// the least
+///
+/// Returns `None` if `results` is empty.
+#[must_use]
+pub fn select_best(results: &[NtpResult]) -> Option<&NtpResult> {
+    results.iter().min_by_key(|result| result.roundtrip())
+}
+
+/// Estimate the drift rate of the local clock in parts per million from two results of the
+/// same server
+///
+/// `elapsed_local` is the time between the measurements according to the local clock. A
+/// positive drift means the offset grows, i.e. the local clock runs slower than the server
+/// one. Results may use different units.
+///
+/// Returns `None` if `elapsed_local` is zero.
+///
+/// # Example
+///
+/// ```rust
+/// use core::time::Duration;
+/// use sntpc::filter::estimate_drift_ppm;
+/// use sntpc::NtpResult;
+///
+/// let earlier = NtpResult::new(1_700_000_000, 0, 500, 1_000, 1, 0);
+/// let later = NtpResult::new(1_700_000_064, 0, 500, 1_640, 1, 0);
+///
+/// // 640us drift in 64s
+/// assert_eq!(
+///     Some(10),
+///     estimate_drift_ppm(&earlier, &later, Duration::from_secs(64))
+/// );
+/// ```
+#[must_use]
+pub fn estimate_drift_ppm(
+    earlier: &NtpResult,
+    later: &NtpResult,
+    elapsed_local: Duration,
+) -> Option<i64> {
+    let elapsed_us = i128::try_from(elapsed_local.as_micros()).ok()?;
+
+    if elapsed_us == 0 {
+        return None;
+    }
+
+    let drift = (offset_diff_us(earlier, later) * 1_000_000 / elapsed_us)
+        .clamp(i128::from(i64::MIN), i128::from(i64::MAX));
+
+    i64::try_from(drift).ok()
+}
+
+/// Estimate the drift rate of the local clock in parts per million, see
+/// [`estimate_drift_ppm`]
+///
+/// Returns `None` if `elapsed_local` is zero.
+#[cfg(feature = "std")]
+#[allow(clippy::cast_precision_loss)]
+#[must_use]
+pub fn estimate_drift(
+    earlier: &NtpResult,
+    later: &NtpResult,
+    elapsed_local: Duration,
+) -> Option<f64> {
+    if elapsed_local.is_zero() {
+        return None;
+    }
+
+    Some(offset_diff_us(earlier, later) as f64 / elapsed_local.as_secs_f64())
+}
+
+/// Calculate the jitter of a series of results of the same server in microseconds
+///
+/// The jitter is the root mean square of the offset differences between successive samples,
+/// so a constant offset gives zero jitter regardless of its value. Samples are expected in the
+/// order they have been received and may use different units.
+///
+/// Returns `None` if there are less than two samples.
+///
+/// # Example
+///
+/// ```rust
+/// use sntpc::filter::calculate_jitter;
+/// use sntpc::NtpResult;
+///
+/// let samples = [0, 2, 0, 2, 0].map(|offset| NtpResult::new(0, 0, 100, offset, 1, 0));
+///
+/// assert_eq!(Some(2.0), calculate_jitter(&samples));
+/// ```
+#[allow(clippy::cast_precision_loss)]
+#[must_use]
+pub fn calculate_jitter(samples: &[NtpResult]) -> Option<f64> {
+    if samples.len() < 2 {
+        return None;
+    }
+
+    let sum = samples
+        .windows(2)
+        .map(|pair| {
+            let diff = offset_diff_us(&pair[0], &pair[1]) as f64;
+            diff * diff
+        })
+        .sum::<f64>();
+
+    Some(libm::sqrt(sum / (samples.len() - 1) as f64))
+}
+
+/// Calculate the overlapping Allan deviation of the local clock from a series of results of the
+/// same server
+///
+/// The offsets are the phase of the local clock relative to the server one, so the
+/// deviation is the dimensionless fractional frequency stability, e.g. `1e-6` for 1 ppm, at the
+/// averaging time of the sample interval. The interval is the average time between the
+/// samples according to the server, so the samples are expected to be evenly spaced and in
+/// the order they have been received. Samples may use different units.
+///
+/// Returns `None` if there are less than three samples or they have been taken at the same
+/// time.
+#[allow(clippy::cast_precision_loss)]
+#[must_use]
+pub fn calculate_allan_deviation(samples: &[NtpResult]) -> Option<f64> {
+    let (first, last) = (samples.first()?, samples.last()?);
+
+    if samples.len() < 3 {
+        return None;
+    }
+
+    let span = ntp_result_to_duration(last)
+        .checked_sub(ntp_result_to_duration(first))?
+        .as_secs_f64();
+
+    if span == 0.0 {
+        return None;
+    }
+
+    let tau = span / (samples.len() - 1) as f64;
+    let sum = samples
+        .windows(3)
+        .map(|triple| {
+            let second_diff = (offset_diff_us(&triple[1], &triple[2])
+                - offset_diff_us(&triple[0], &triple[1]))
+                as f64
+                / 1e6;
+            second_diff * second_diff
+        })
+        .sum::<f64>();
+    let variance = sum / (2.0 * tau * tau * (samples.len() - 2) as f64);
+
+    Some(libm::sqrt(variance))
+}
+
+/// Offset change between two results in microseconds
+fn offset_diff_us(earlier: &NtpResult, later: &NtpResult) -> i128 {
+    i128::from(later.metrics().offset_us)
+        - i128::from(earlier.metrics().offset_us)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        marzullo_intersect, select_best, NtpClockFilter, NtpResult, FILTER_SIZE,
+    };
+    use crate::fixtures::ntp_result;
+
+    #[test]
+    fn test_empty_filter() {
+        let filter = NtpClockFilter::new();
+
+        assert!(filter.is_empty());
+        assert!(filter.best_sample().is_none());
+        assert_eq!(None, filter.best_offset());
+        assert_eq!(None, filter.dispersion());
+        assert_eq!(None, filter.jitter());
+    }
+
+    #[test]
+    fn test_best_offset_has_lowest_delay() {
+        let mut filter = NtpClockFilter::new();
+
+        filter.push(&ntp_result(0, 3_000, 900));
+        filter.push(&ntp_result(0, 400, -120));
+        filter.push(&ntp_result(0, 1_000, 200));
+
+        assert_eq!(3, filter.len());
+        assert_eq!(Some(-120), filter.best_offset());
+        assert_eq!(Some(400), filter.best_roundtrip());
+        // |200 - -120| / 4 + |900 - -120| / 8
+        assert_eq!(Some(80 + 127), filter.dispersion());
+    }
+
+    #[test]
+    fn test_best_sample_and_jitter() {
+        let mut filter = NtpClockFilter::new();
+
+        filter.push(&NtpResult::new(1_700_000_000, 0, 3_000, 900, 2, -20));
+        assert_eq!(Some(0), filter.jitter());
+
+        filter.push(&NtpResult::new(1_700_000_016, 0, 400, -100, 1, -23));
+        filter.push(&ntp_result(0, 1_000, 200));
+
+        let best = filter.best_sample().unwrap();
+        assert_eq!(1_700_000_016, best.sec());
+        assert_eq!(1, best.stratum());
+        // sqrt((1_000^2 + 300^2) / 2)
+        assert_eq!(Some(738), filter.jitter());
+
+        for _ in 0..FILTER_SIZE {
+            filter.push(&ntp_result(0, 1_000, 200));
+        }
+        assert_eq!(Some(0), filter.jitter());
+    }
+
+    #[test]
+    fn test_oldest_sample_replaced() {
+        let mut filter = NtpClockFilter::new();
+
+        filter.push(&ntp_result(0, 100, 42));
+        for _ in 0..FILTER_SIZE - 1 {
+            filter.push(&ntp_result(0, 1_000, 0));
+        }
+        assert_eq!(Some(42), filter.best_offset());
+
+        filter.push(&ntp_result(0, 1_000, 0));
+        assert_eq!(FILTER_SIZE, filter.len());
+        assert_eq!(Some(0), filter.best_offset());
+        assert_eq!(Some(0), filter.dispersion());
+
+        filter.clear();
+        assert!(filter.is_empty());
+    }
+
+    #[test]
+    fn test_marzullo_outlier_excluded() {
+        let samples = [
+            (ntp_result(1_700_000_000, 40_000, 0), 0),
+            (ntp_result(1_700_000_000, 40_000, 10_000), 0),
+            (ntp_result(1_700_000_000, 40_000, 3_000_000), 0),
+        ];
+
+        assert_eq!(Some((-10_000, 20_000)), marzullo_intersect(&samples));
+
+        // the outlier does not affect the result wherever it is
+        let samples = [
+            (ntp_result(1_700_000_000, 10_000, -10_000_000), 0),
+            (ntp_result(1_700_000_000, 40_000, 0), 0),
+            (ntp_result(1_700_000_000, 40_000, 10_000), 0),
+        ];
+        assert_eq!(Some((-10_000, 20_000)), marzullo_intersect(&samples));
+
+        // the narrower interval of three overlapping ones, the uncertainty widens the interval
+        let samples = [
+            (ntp_result(1_700_000_000, 40_000, 0), 0),
+            (ntp_result(1_700_000_000, 40_000, 10_000), 0),
+            (ntp_result(1_700_000_000, 0, 5_000), 1_000),
+        ];
+        assert_eq!(Some((4_000, 6_000)), marzullo_intersect(&samples));
+    }
+
+    #[test]
+    fn test_marzullo_sequential_queries() {
+        // the servers are queried seconds apart, so the reported times differ by far more
+        // than the roundtrip, while the offsets agree
+        let samples = [
+            (ntp_result(1_700_000_000, 2_000, 500), 0),
+            (ntp_result(1_700_000_002, 2_000, 800), 0),
+            (ntp_result(1_700_000_005, 2_000, 300), 0),
+        ];
+
+        assert_eq!(Some((-200, 1_300)), marzullo_intersect(&samples));
+    }
+
+    #[test]
+    fn test_marzullo_no_majority() {
+        let samples = [
+            (ntp_result(1_700_000_000, 2_000, 0), 0),
+            (ntp_result(1_700_000_000, 2_000, 1_000_000), 0),
+            (ntp_result(1_700_000_000, 2_000, 2_000_000), 0),
+        ];
+
+        assert_eq!(None, marzullo_intersect(&samples));
+        assert_eq!(None, marzullo_intersect(&samples[..2]));
+        assert_eq!(None, marzullo_intersect(&[]));
+    }
+
+    #[test]
+    fn test_select_best() {
+        let results = [
+            ntp_result(1_700_000_000, 3_000, 0),
+            ntp_result(1_700_000_000, 400, 0),
+            ntp_result(1_700_000_000, 1_000, 0),
+        ];
+
+        assert_eq!(400, select_best(&results).unwrap().roundtrip());
+        assert!(select_best(&[]).is_none());
+    }
+
+    #[test]
+    fn test_estimate_drift() {
+        use super::estimate_drift_ppm;
+        use crate::Units;
+        use core::time::Duration;
+
+        let earlier = ntp_result(0, 500, 1_000);
+        let mut later = ntp_result(0, 500, -1_000);
+        let elapsed = Duration::from_secs(100);
+
+        assert_eq!(Some(-20), estimate_drift_ppm(&earlier, &later, elapsed));
+        assert_eq!(Some(20), estimate_drift_ppm(&later, &earlier, elapsed));
+        assert_eq!(None, estimate_drift_ppm(&earlier, &later, Duration::ZERO));
+
+        // the reported units do not affect the stored offset
+        later.offset = 1_000;
+        later.units = Units::Milliseconds;
+        assert_eq!(Some(0), estimate_drift_ppm(&earlier, &later, elapsed));
+
+        #[cfg(feature = "std")]
+        {
+            use super::estimate_drift;
+
+            let later = ntp_result(0, 500, 1_001);
+            let drift = estimate_drift(&earlier, &later, elapsed).unwrap();
+            assert!((drift - 0.01).abs() < 1e-9);
+            assert_eq!(None, estimate_drift(&earlier, &later, Duration::ZERO));
+        }
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_calculate_jitter() {
+        use super::calculate_jitter;
+
+        fn samples<const N: usize>(offsets: [i64; N]) -> [NtpResult; N] {
+            offsets.map(|offset| ntp_result(0, 500, offset))
+        }
+
+        assert_eq!(None, calculate_jitter(&[]));
+        assert_eq!(None, calculate_jitter(&samples([1_000])));
+        assert_eq!(Some(0.0), calculate_jitter(&samples([1_000; 8])));
+        // the offset differences are +-2, i.e. the variance is 4
+        assert_eq!(Some(2.0), calculate_jitter(&samples([0, 2, 0, 2, 0])));
+        assert_eq!(
+            Some(2.0),
+            calculate_jitter(&samples([-1_001, -999, -1_001]))
+        );
+    }
+
+    #[test]
+    fn test_calculate_allan_deviation() {
+        use super::calculate_allan_deviation;
+
+        // samples taken every second
+        fn samples<const N: usize>(offsets: [i64; N]) -> [NtpResult; N] {
+            let mut sec = 1_700_000_000;
+
+            offsets.map(|offset| {
+                sec += 1;
+                ntp_result(sec, 500, offset)
+            })
+        }
+
+        assert_eq!(None, calculate_allan_deviation(&samples([0, 1])));
+        // samples taken at the same time
+        assert_eq!(
+            None,
+            calculate_allan_deviation(
+                &[0, 1, 0].map(|offset| ntp_result(0, 500, offset))
+            )
+        );
+        // a constant frequency error gives no phase instability
+        let deviation =
+            calculate_allan_deviation(&samples([0, 10, 20, 30])).unwrap();
+        assert!(deviation.abs() < 1e-15);
+        // the second difference is 2us at 1s interval: sqrt(4e-12 / 2)
+        let deviation = calculate_allan_deviation(&samples([0, 1, 0])).unwrap();
+        assert!((deviation - core::f64::consts::SQRT_2 * 1e-6).abs() < 1e-15);
+    }
+}
@@ -0,0 +1,33 @@
+//! Fixtures shared by the unit tests of the crate modules
+use crate::net::SocketAddr;
+use crate::{NtpResult, NtpTimestampGenerator};
+
+use core::net::{IpAddr, Ipv4Addr};
+
+/// Timestamp generator that reports the time set by the test
+#[derive(Copy, Clone, Default)]
+pub(crate) struct ManualTimestampGen {
+    pub(crate) usec: u64,
+}
+
+impl NtpTimestampGenerator for ManualTimestampGen {
+    fn init(&mut self) {}
+
+    fn timestamp_sec(&self) -> u64 {
+        self.usec / 1_000_000
+    }
+
+    fn timestamp_subsec_micros(&self) -> u32 {
+        u32::try_from(self.usec % 1_000_000).unwrap()
+    }
+}
+
+/// Address of a server in the `10.0.0.0/24` network
+pub(crate) fn server_addr(last_octet: u8) -> SocketAddr {
+    SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, last_octet)), 123)
+}
+
+/// Result of a stratum 1 server reporting `sec` seconds past the epoch
+pub(crate) fn ntp_result(sec: u32, roundtrip: u64, offset: i64) -> NtpResult {
+    NtpResult::new(sec, 0, roundtrip, offset, 1, 0)
+}
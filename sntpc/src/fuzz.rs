@@ -0,0 +1,84 @@
+//! Entry points of the fuzzing harness in the `fuzz` directory
+//!
+//! The module is only built by `cargo fuzz`, which enables `--cfg fuzzing`, and gives the fuzz
+//! targets access to the packet processing internals. It is not a part of the public API.
+use crate::types::{NtpPacket, RawNtpPacket, VERSION_MASK, VERSION_SHIFT};
+use crate::{
+    decode_response, process_response as process, NtpContext,
+    NtpTimestampGenerator, SendRequestResult,
+};
+
+const PACKET_SIZE: usize = size_of::<NtpPacket>();
+
+#[derive(Copy, Clone, Default)]
+struct ZeroTimestampGen;
+
+impl NtpTimestampGenerator for ZeroTimestampGen {
+    fn init(&mut self) {}
+
+    fn timestamp_sec(&self) -> u64 {
+        0
+    }
+
+    fn timestamp_subsec_micros(&self) -> u32 {
+        0
+    }
+}
+
+/// Process `data` as a server response
+///
+/// The first 48 bytes are the response packet, the next 8 bytes are the receive timestamp.
+/// The request is crafted to match the origin timestamp and the version of the response and
+/// the request age is not limited, so inputs get past the request correlation checks.
+pub fn process_response(data: &[u8]) {
+    let Some((packet, rest)) = data.split_first_chunk::<PACKET_SIZE>() else {
+        return;
+    };
+    let Some(recv_timestamp) = rest.first_chunk::<8>() else {
+        return;
+    };
+    let recv_timestamp = u64::from_be_bytes(*recv_timestamp);
+    let request = SendRequestResult::new(
+        decode_response(RawNtpPacket(*packet), recv_timestamp).origin_timestamp,
+        (packet[0] & VERSION_MASK) >> VERSION_SHIFT,
+    );
+    let context = NtpContext::new(ZeroTimestampGen)
+        .with_max_request_age(core::time::Duration::MAX);
+
+    let _ = process(request, RawNtpPacket(*packet), recv_timestamp, &context);
+}
+
+/// Convert `data` to a packet and back, verifying that nothing is lost on the way
+///
+/// # Panics
+///
+/// Panics if the packet fields change after the conversion roundtrip
+pub fn raw_packet_roundtrip(data: &[u8]) {
+    let Some(bytes) = data.first_chunk::<PACKET_SIZE>() else {
+        return;
+    };
+    let packet = NtpPacket::from(RawNtpPacket(*bytes));
+    let raw = RawNtpPacket::from(&packet);
+    let restored = NtpPacket::from(RawNtpPacket(raw.0));
+
+    assert_eq!(fields(&packet), fields(&restored));
+}
+
+#[allow(clippy::type_complexity)]
+fn fields(
+    packet: &NtpPacket,
+) -> (u8, u8, i8, i8, u32, u32, u32, u64, u64, u64, u64) {
+    (
+        packet.li_vn_mode,
+        packet.stratum,
+        packet.poll,
+        packet.precision,
+        packet.root_delay,
+        packet.root_dispersion,
+        packet.ref_id,
+        packet.ref_timestamp,
+        packet.origin_timestamp,
+        packet.recv_timestamp,
+        packet.tx_timestamp,
+    )
+}
@@ -0,0 +1,89 @@
+/// Number of [`RoundtripHistogram`] buckets
+pub const ROUNDTRIP_BUCKETS: usize = 4;
+
+/// Exclusive upper bounds of all but the last bucket in microseconds
+const BUCKET_BOUNDS: [u64; ROUNDTRIP_BUCKETS - 1] = [1_000, 10_000, 100_000];
+
+/// Histogram of roundtrip delays of SNTP requests
+///
+/// The histogram counts delays in fixed buckets: below 1 ms, 1 to 10 ms, 10 to 100 ms and
+/// 100 ms or more. It allows devices to report network quality without storing samples, and
+/// does not require heap allocations or floating point arithmetic.
+///
+/// ```rust
+/// use sntpc::RoundtripHistogram;
+///
+/// let mut histogram = RoundtripHistogram::new();
+///
+/// histogram.record(800);
+/// histogram.record(25_000);
+/// histogram.record(31_000);
+///
+/// assert_eq!([1, 0, 2, 0], histogram.counts());
+/// ```
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RoundtripHistogram {
+    counts: [u32; ROUNDTRIP_BUCKETS],
+}
+
+impl RoundtripHistogram {
+    /// Create an empty histogram
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            counts: [0; ROUNDTRIP_BUCKETS],
+        }
+    }
+
+    /// Count the roundtrip delay in microseconds, e.g. [`crate::NtpResult::roundtrip`]
+    ///
+    /// Counters saturate at `u32::MAX`.
+    pub fn record(&mut self, roundtrip_micros: u64) {
+        let bucket = BUCKET_BOUNDS
+            .iter()
+            .position(|&bound| roundtrip_micros < bound)
+            .unwrap_or(ROUNDTRIP_BUCKETS - 1);
+
+        self.counts[bucket] = self.counts[bucket].saturating_add(1);
+    }
+
+    /// Returns the counts of the buckets in the order of increasing delay
+    #[must_use]
+    pub const fn counts(&self) -> [u32; ROUNDTRIP_BUCKETS] {
+        self.counts
+    }
+
+    /// Returns the total number of recorded delays
+    #[must_use]
+    pub fn total(&self) -> u64 {
+        self.counts.iter().map(|&count| u64::from(count)).sum()
+    }
+
+    /// Reset all counters
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RoundtripHistogram, ROUNDTRIP_BUCKETS};
+
+    #[test]
+    fn test_histogram_buckets() {
+        let mut histogram = RoundtripHistogram::new();
+
+        for roundtrip in [0, 999, 1_000, 9_999, 10_000, 99_999, 100_000] {
+            histogram.record(roundtrip);
+        }
+        histogram.record(u64::MAX);
+
+        assert_eq!([2, 2, 2, 2], histogram.counts());
+        assert_eq!(8, histogram.total());
+
+        histogram.clear();
+        assert_eq!([0; ROUNDTRIP_BUCKETS], histogram.counts());
+        assert_eq!(RoundtripHistogram::default(), histogram);
+    }
+}
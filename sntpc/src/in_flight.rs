@@ -0,0 +1,95 @@
+use crate::net::SocketAddr;
+use crate::SendRequestResult;
+
+/// Fixed-capacity tracker of SNTP requests awaiting a response
+///
+/// The tracker allows to query several NTP servers concurrently over a single socket without
+/// heap allocations. Every sent request is recorded along with the server address, so a
+/// response can be correlated with the server by its originate timestamp regardless of the
+/// order responses arrive in. See [`crate::sntp_process_in_flight_response`].
+///
+/// Originate timestamps are used as the key, so the timestamp generator must provide distinct
+/// values for every request that is tracked at the same time.
+#[derive(Debug, Copy, Clone)]
+pub struct InFlight<const N: usize> {
+    entries: [Option<(SocketAddr, SendRequestResult)>; N],
+}
+
+impl<const N: usize> InFlight<N> {
+    /// Create an empty tracker
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { entries: [None; N] }
+    }
+
+    /// Record the request sent to `addr`
+    ///
+    /// Returns `false` if the tracker is full or there is a request with the same originate
+    /// timestamp in flight already, in which case the request is not recorded
+    #[must_use]
+    pub fn insert(
+        &mut self,
+        addr: SocketAddr,
+        send_req_result: SendRequestResult,
+    ) -> bool {
+        if self.find(send_req_result.originate_timestamp).is_some() {
+            return false;
+        }
+
+        match self.entries.iter_mut().find(|entry| entry.is_none()) {
+            Some(slot) => {
+                *slot = Some((addr, send_req_result));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the server address a request with the given originate timestamp has been sent to
+    #[must_use]
+    pub fn get(&self, originate_timestamp: u64) -> Option<SocketAddr> {
+        self.find(originate_timestamp)
+            .and_then(|idx| self.entries[idx])
+            .map(|(addr, _)| addr)
+    }
+
+    /// Remove the request with the given originate timestamp from the tracker
+    pub fn remove(
+        &mut self,
+        originate_timestamp: u64,
+    ) -> Option<(SocketAddr, SendRequestResult)> {
+        self.find(originate_timestamp)
+            .and_then(|idx| self.entries[idx].take())
+    }
+
+    /// Returns the number of requests in flight
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entries.iter().flatten().count()
+    }
+
+    /// Returns `true` if there are no requests in flight
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Forget all requests in flight, e.g. after a timeout
+    pub fn clear(&mut self) {
+        self.entries = [None; N];
+    }
+
+    fn find(&self, originate_timestamp: u64) -> Option<usize> {
+        self.entries.iter().position(|entry| {
+            entry.is_some_and(|(_, req)| {
+                req.originate_timestamp == originate_timestamp
+            })
+        })
+    }
+}
+
+impl<const N: usize> Default for InFlight<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,214 @@
+use crate::net::SocketAddr;
+use crate::{timestamp_micros, Error, KissCode, NtpTimestampGenerator, Result};
+
+use core::time::Duration;
+
+#[derive(Debug, Copy, Clone)]
+struct KodEntry {
+    addr: SocketAddr,
+    code: KissCode,
+    // moment in microseconds until the server must not be queried, `None` means forever
+    until: Option<u64>,
+}
+
+/// Fixed-capacity cache of servers that responded with a Kiss-o'-Death packet
+///
+/// According to RFC 4330 a client must stop querying a server that responded with `DENY` or
+/// `RSTR` kiss codes, and reduce its polling rate after the `RATE` kiss code. The cache
+/// remembers such servers, so helpers like [`crate::get_time_with_kod_cache`] are able to
+/// refuse sending new requests to them:
+///
+/// - servers that responded with `DENY` or `RSTR` are skipped for the lifetime of the cache
+/// - servers that responded with `RATE` are skipped for the configured backoff delay
+///
+/// Other kiss codes are not recorded. Time is taken from the [`NtpTimestampGenerator`], so no
+/// `std` is required. When the cache is full, `RATE` entries are replaced first, so denied
+/// servers are never forgotten.
+#[derive(Debug, Copy, Clone)]
+pub struct KodCache<const N: usize> {
+    rate_delay: Duration,
+    entries: [Option<KodEntry>; N],
+}
+
+impl<const N: usize> KodCache<N> {
+    /// Create an empty cache. Servers that responded with the `RATE` kiss code are not queried
+    /// for `rate_delay` afterward.
+    #[must_use]
+    pub const fn new(rate_delay: Duration) -> Self {
+        Self {
+            rate_delay,
+            entries: [None; N],
+        }
+    }
+
+    /// Record the kiss code received from `addr`
+    pub fn record<T: NtpTimestampGenerator>(
+        &mut self,
+        addr: SocketAddr,
+        code: KissCode,
+        timestamp_gen: &mut T,
+    ) {
+        let until = match code {
+            KissCode::Deny | KissCode::Restricted => None,
+            KissCode::RateExceeded => {
+                let delay = u64::try_from(self.rate_delay.as_micros())
+                    .unwrap_or(u64::MAX);
+
+                Some(timestamp_micros(timestamp_gen).saturating_add(delay))
+            }
+            KissCode::Other(_) => return,
+        };
+        let entry = KodEntry { addr, code, until };
+
+        if let Some(slot) = self
+            .entries
+            .iter_mut()
+            .find(|slot| slot.is_some_and(|e| e.addr == addr))
+        {
+            // a denied server stays denied
+            if slot.is_some_and(|e| e.until.is_some()) {
+                *slot = Some(entry);
+            }
+            return;
+        }
+
+        // prefer empty slots, then the `RATE` entries expiring first
+        let slot = self
+            .entries
+            .iter_mut()
+            .filter(|slot| slot.is_none_or(|e| e.until.is_some()))
+            .min_by_key(|slot| slot.and_then(|e| e.until));
+
+        if let Some(slot) = slot {
+            *slot = Some(entry);
+        }
+    }
+
+    /// Check whether `addr` may be queried at the current moment
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::KissOfDeath`] if the server responded with `DENY` or `RSTR` kiss codes
+    /// earlier, or [`Error::RateLimited`] with the remaining delay if the server responded with
+    /// the `RATE` kiss code and the delay has not passed yet.
+    pub fn check<T: NtpTimestampGenerator>(
+        &self,
+        addr: SocketAddr,
+        timestamp_gen: &mut T,
+    ) -> Result<()> {
+        let Some(entry) = self.find(addr) else {
+            return Ok(());
+        };
+
+        match entry.until {
+            None => Err(Error::KissOfDeath(entry.code)),
+            Some(until) => {
+                let now = timestamp_micros(timestamp_gen);
+
+                if now < until {
+                    Err(Error::RateLimited(Duration::from_micros(until - now)))
+                } else {
+                    Ok(())
+                }
+            }
+        }
+    }
+
+    /// Returns the last kiss code recorded for `addr`
+    #[must_use]
+    pub fn get(&self, addr: SocketAddr) -> Option<KissCode> {
+        self.find(addr).map(|entry| entry.code)
+    }
+
+    /// Forget all recorded servers
+    pub fn clear(&mut self) {
+        self.entries = [None; N];
+    }
+
+    fn find(&self, addr: SocketAddr) -> Option<KodEntry> {
+        self.entries
+            .iter()
+            .flatten()
+            .find(|entry| entry.addr == addr)
+            .copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::KodCache;
+    use crate::fixtures::{server_addr, ManualTimestampGen};
+    use crate::{Error, KissCode};
+
+    use core::time::Duration;
+
+    #[test]
+    fn test_kiss_code() {
+        assert_eq!(KissCode::Deny, KissCode::from(*b"DENY"));
+        assert_eq!(KissCode::Restricted, KissCode::from(*b"RSTR"));
+        assert_eq!(KissCode::RateExceeded, KissCode::from(*b"RATE"));
+        assert_eq!(KissCode::Other(*b"INIT"), KissCode::from(*b"INIT"));
+    }
+
+    #[test]
+    fn test_kod_cache_deny() {
+        let mut cache = KodCache::<2>::new(Duration::from_secs(30));
+        let mut clock = ManualTimestampGen::default();
+
+        cache.record(server_addr(1), KissCode::Deny, &mut clock);
+        cache.record(server_addr(2), KissCode::Other(*b"INIT"), &mut clock);
+        clock.usec += 3_600_000_000;
+
+        assert_eq!(
+            Err(Error::KissOfDeath(KissCode::Deny)),
+            cache.check(server_addr(1), &mut clock)
+        );
+        assert_eq!(Some(KissCode::Deny), cache.get(server_addr(1)));
+        assert!(cache.check(server_addr(2), &mut clock).is_ok());
+        assert_eq!(None, cache.get(server_addr(2)));
+
+        // a denied server stays denied even if it responds with a softer kiss code
+        cache.record(server_addr(1), KissCode::RateExceeded, &mut clock);
+        assert_eq!(Some(KissCode::Deny), cache.get(server_addr(1)));
+
+        cache.clear();
+        assert!(cache.check(server_addr(1), &mut clock).is_ok());
+    }
+
+    #[test]
+    fn test_kod_cache_rate() {
+        let mut cache = KodCache::<2>::new(Duration::from_secs(30));
+        let mut clock = ManualTimestampGen::default();
+
+        cache.record(server_addr(1), KissCode::RateExceeded, &mut clock);
+        clock.usec += 20_000_000;
+        assert_eq!(
+            Err(Error::RateLimited(Duration::from_secs(10))),
+            cache.check(server_addr(1), &mut clock)
+        );
+
+        clock.usec += 10_000_000;
+        assert!(cache.check(server_addr(1), &mut clock).is_ok());
+        assert_eq!(Some(KissCode::RateExceeded), cache.get(server_addr(1)));
+    }
+
+    #[test]
+    fn test_kod_cache_keeps_denied_servers_when_full() {
+        let mut cache = KodCache::<2>::new(Duration::from_secs(30));
+        let mut clock = ManualTimestampGen::default();
+
+        cache.record(server_addr(1), KissCode::Restricted, &mut clock);
+        cache.record(server_addr(2), KissCode::RateExceeded, &mut clock);
+        cache.record(server_addr(3), KissCode::RateExceeded, &mut clock);
+
+        assert_eq!(Some(KissCode::Restricted), cache.get(server_addr(1)));
+        assert_eq!(None, cache.get(server_addr(2)));
+        assert_eq!(Some(KissCode::RateExceeded), cache.get(server_addr(3)));
+
+        cache.record(server_addr(4), KissCode::Deny, &mut clock);
+        cache.record(server_addr(5), KissCode::Deny, &mut clock);
+        assert_eq!(Some(KissCode::Restricted), cache.get(server_addr(1)));
+        assert_eq!(Some(KissCode::Deny), cache.get(server_addr(4)));
+        assert_eq!(None, cache.get(server_addr(5)));
+    }
+}
@@ -6,6 +6,8 @@
 //! and process responses, extracting received timestamp. Supported SNTP protocol
 //! versions:
 //! - [SNTPv4](https://datatracker.ietf.org/doc/html/rfc4330)
+//! - `SNTPv3` responses can be accepted on demand with [`NtpContext::with_accepted_versions`]
+//! - `SNTPv3` requests can be sent on demand with [`NtpContext::with_version`]
 //!
 //! # Usage
 //!
@@ -20,12 +22,33 @@
 //! `sntpc` supports several features:
 //! - `std`: includes functionality that depends on the standard library
 //! - `sync`: enables synchronous interface
+//! - `minimal-sync`: enables synchronous interface driven by a built-in busy-polling executor
+//!   instead of `miniloop`
 //! - `utils`: includes functionality that mostly OS specific and allows system time sync
+//! - `chrono`: add conversion of [`NtpResult`] to `chrono::DateTime<Utc>`
+//! - `time`: add conversion of [`NtpResult`] to `time::OffsetDateTime`
 //! - `log`: enables library debug output during execution
 //! - `defmt`: enables library debug output using defmt
 //! - `std-socket`: add `NtpUdpSocket` trait implementation for `std::net::UdpSocket`
 //! - `embassy-socket`: add `NtpUdpSocket` trait implementation for `embassy_net::udp::UdpSocket`
+//!   and `sntpc::embassy` helpers built on `embassy-time` timers
 //! - `tokio-socket`: add `NtpUdpSocket` trait implementation for `tokio::net::UdpSocket`
+//!   that also supports sockets connected to a single server,
+//!   `TokioSendHalf`/`TokioRecvHalf` split socket halves, `NtpHostResolver` implementation
+//!   based on `tokio::net::lookup_host` and `sntpc::stream` module for periodic polling of a
+//!   server as an asynchronous stream. Enables `std`
+//! - `hickory-resolver`: add `HickoryResolver`, an `NtpHostResolver` and `NtpSrvResolver`
+//!   implementation based on `hickory-resolver`. Enables `tokio-socket`
+//! - `serde`: add `serde::Serialize` and `serde::Deserialize` implementations for [`NtpResult`]
+//!   and [`Error`]
+//! - `smoltcp-socket`: add `sntpc::smoltcp` module with `NtpUdpSocket` trait implementation
+//!   for `smoltcp::socket::udp::Socket`
+//! - `test-utils`: add `sntpc::testing` module with a mock SNTP server to test clients without
+//!   access to the Internet
+//! - `dont-fragment`: add `sntpc::net::set_dont_fragment` to disable fragmentation of sent
+//!   datagrams on Linux
+//! - `request-parts`: add `SendRequestResult::from_parts` to restore the state of a request
+//!   sent by a custom transport
 //!
 //! <div class="warning">
 //!
@@ -158,10 +181,53 @@
 #[cfg(feature = "utils")]
 pub mod utils;
 
+#[cfg(feature = "embassy-socket")]
+pub mod embassy;
+pub mod filter;
+pub mod packet;
+#[cfg(feature = "tokio-socket")]
+pub mod stream;
+#[cfg(any(all(test, feature = "std"), feature = "test-utils"))]
+pub mod testing;
+
+mod client;
+#[cfg(feature = "minimal-sync")]
+#[cfg_attr(feature = "sync", allow(dead_code))]
+mod executor;
+#[cfg(test)]
+mod fixtures;
+#[cfg(fuzzing)]
+#[doc(hidden)]
+pub mod fuzz;
+mod histogram;
+mod in_flight;
+mod kod;
 mod log;
+mod pending;
+mod rate_limiter;
+mod request;
+mod resolver;
+mod scheduler;
+#[cfg(feature = "std")]
+mod server_addr;
 mod socket;
 mod types;
 
+pub use crate::client::*;
+pub use crate::histogram::*;
+pub use crate::in_flight::*;
+pub use crate::kod::*;
+pub use crate::pending::*;
+pub use crate::rate_limiter::*;
+pub use crate::request::*;
+pub use crate::resolver::*;
+pub use crate::scheduler::*;
+#[cfg(feature = "std")]
+pub use crate::server_addr::*;
+#[cfg(feature = "smoltcp-socket")]
+pub use crate::socket::smoltcp;
+#[cfg(feature = "tokio-socket")]
+pub use crate::socket::tokio::{TokioRecvHalf, TokioSendHalf};
 pub use crate::types::*;
 
 #[cfg(any(feature = "log", feature = "defmt"))]
@@ -169,14 +235,165 @@ use crate::log::debug;
 
 /// Network types used by the `sntpc` crate
 pub mod net {
-    pub use core::net::SocketAddr;
+    pub use core::net::{IpAddr, SocketAddr};
 
     #[cfg(feature = "std")]
     pub use std::net::UdpSocket;
+
+    /// Bind a UDP socket to an ephemeral port on the unspecified address of the same
+    /// family as `addr`, i.e. `0.0.0.0:0` for IPv4 and `[::]:0` for IPv6 servers
+    ///
+    /// A socket bound to `0.0.0.0:0` is not able to reach IPv6 servers, so the helper
+    /// allows to create a socket suitable for the given server:
+    ///
+    /// ```rust,no_run
+    /// let server = "[2606:4700:f1::1]:123".parse().unwrap();
+    /// let socket = sntpc::net::bind_for(server).expect("Unable to create UDP socket");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the socket can not be bound
+    #[cfg(feature = "std")]
+    pub fn bind_for(addr: SocketAddr) -> std::io::Result<UdpSocket> {
+        use core::net::{Ipv4Addr, Ipv6Addr};
+
+        match addr {
+            SocketAddr::V4(_) => UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)),
+            SocketAddr::V6(_) => UdpSocket::bind((Ipv6Addr::UNSPECIFIED, 0)),
+        }
+    }
+
+    /// Bind a UDP socket to the given local address, e.g. an address of a specific interface
+    /// on a multi-homed host, so requests leave through that interface
+    ///
+    /// Use port `0` to bind to an ephemeral port:
+    ///
+    /// ```rust,no_run
+    /// let local = "192.168.1.5:0".parse().unwrap();
+    /// let socket = sntpc::net::bind_local(local).expect("Unable to create UDP socket");
+    /// ```
+    ///
+    /// The socket is able to reach servers of the same address family as `local` only.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the socket can not be bound, e.g. if the address is not assigned
+    /// to any local interface
+    #[cfg(feature = "std")]
+    pub fn bind_local(local: SocketAddr) -> std::io::Result<UdpSocket> {
+        UdpSocket::bind(local)
+    }
+
+    /// Set the "don't fragment" bit on datagrams sent over `socket`
+    ///
+    /// Path MTU discovery is enforced for the socket, so a datagram that does not fit into the
+    /// path MTU is rejected by the kernel with an error instead of being fragmented, which may
+    /// be silently dropped by some networks. It matters once extension fields make requests
+    /// large. Works for any socket that exposes its file descriptor, e.g. `std` and `tokio`
+    /// UDP sockets of both address families:
+    ///
+    /// ```rust,no_run
+    /// let socket = std::net::UdpSocket::bind("0.0.0.0:0").unwrap();
+    /// sntpc::net::set_dont_fragment(&socket).expect("Unable to set DF bit");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the socket option can not be set
+    #[cfg(all(feature = "dont-fragment", target_os = "linux"))]
+    pub fn set_dont_fragment<S: std::os::fd::AsFd>(
+        socket: &S,
+    ) -> std::io::Result<()> {
+        let socket = socket.as_fd();
+
+        if int_option(socket, libc::SOL_SOCKET, libc::SO_DOMAIN)?
+            == libc::AF_INET6
+        {
+            set_int_option(
+                socket,
+                libc::IPPROTO_IPV6,
+                libc::IPV6_MTU_DISCOVER,
+                libc::IPV6_PMTUDISC_DO,
+            )
+        } else {
+            set_int_option(
+                socket,
+                libc::IPPROTO_IP,
+                libc::IP_MTU_DISCOVER,
+                libc::IP_PMTUDISC_DO,
+            )
+        }
+    }
+
+    #[cfg(all(feature = "dont-fragment", target_os = "linux"))]
+    #[allow(clippy::cast_possible_truncation)]
+    const INT_OPTION_LEN: libc::socklen_t =
+        size_of::<libc::c_int>() as libc::socklen_t;
+
+    /// Read an integer socket option
+    #[cfg(all(feature = "dont-fragment", target_os = "linux"))]
+    pub(crate) fn int_option(
+        socket: std::os::fd::BorrowedFd<'_>,
+        level: libc::c_int,
+        name: libc::c_int,
+    ) -> std::io::Result<libc::c_int> {
+        use std::os::fd::AsRawFd;
+
+        let mut value: libc::c_int = 0;
+        let mut len = INT_OPTION_LEN;
+        // SAFETY: the descriptor is borrowed for the duration of the call and the buffer is
+        // a valid `c_int` with its size passed along
+        let ret = unsafe {
+            libc::getsockopt(
+                socket.as_raw_fd(),
+                level,
+                name,
+                (&raw mut value).cast(),
+                &raw mut len,
+            )
+        };
+
+        if ret == 0 {
+            Ok(value)
+        } else {
+            Err(std::io::Error::last_os_error())
+        }
+    }
+
+    #[cfg(all(feature = "dont-fragment", target_os = "linux"))]
+    fn set_int_option(
+        socket: std::os::fd::BorrowedFd<'_>,
+        level: libc::c_int,
+        name: libc::c_int,
+        value: libc::c_int,
+    ) -> std::io::Result<()> {
+        use std::os::fd::AsRawFd;
+
+        // SAFETY: the descriptor is borrowed for the duration of the call and the value is
+        // a valid `c_int` with its size passed along
+        let ret = unsafe {
+            libc::setsockopt(
+                socket.as_raw_fd(),
+                level,
+                name,
+                (&raw const value).cast(),
+                INT_OPTION_LEN,
+            )
+        };
+
+        if ret == 0 {
+            Ok(())
+        } else {
+            Err(std::io::Error::last_os_error())
+        }
+    }
 }
 
 use cfg_if::cfg_if;
 
+use core::fmt::Write as _;
+
 /// Retrieves the current time from an NTP server.
 ///
 /// This asynchronous function performs the complete SNTP flow:
@@ -187,9 +404,9 @@ use cfg_if::cfg_if;
 ///
 /// * `addr` - The socket address (`SocketAddr`) of the NTP server.
 /// * `socket` - A reference to an object implementing the [`NtpUdpSocket`] trait that allows
-///    sending/receiving UDP packets.
+///   sending/receiving UDP packets.
 /// * `context` - An SNTP context (`NtpContext<T>`) containing a timestamp generator that implements
-///    the [`NtpTimestampGenerator`] trait. This ensures precise timestamp creation for request and response processing.
+///   the [`NtpTimestampGenerator`] trait. This ensures precise timestamp creation for request and response processing.
 ///
 /// # Returns
 ///
@@ -292,13 +509,20 @@ use cfg_if::cfg_if;
 /// * The SNTP packet could not be sent to the server.
 /// * The response payload is invalid or indicates an error.
 /// * Mismatch between the expected and actual server addresses.
+///
+/// # Cancel safety
+///
+/// The function may be cancelled at any point, e.g. by wrapping it into
+/// `embassy_time::with_timeout`. A response to the cancelled request may still arrive later.
+/// Such a response does not carry the originate timestamp of the next request, so it is
+/// dropped while waiting for the response to that request rather than failing the call.
 pub async fn get_time<U, T>(
     addr: net::SocketAddr,
     socket: &U,
     context: NtpContext<T>,
 ) -> Result<NtpResult>
 where
-    U: NtpUdpSocket,
+    U: NtpUdpSocket + ?Sized,
     T: NtpTimestampGenerator + Copy,
 {
     let result = sntp_send_request(addr, socket, context).await?;
@@ -306,374 +530,2125 @@ where
     sntp_process_response(addr, socket, context, result).await
 }
 
-/// Sends an SNTP request to an NTP server.
+/// Retrieves the current time from an NTP server along with every field of the response
 ///
-/// This function creates an SNTP packet using the given timestamp generator and
-/// sends it to the given NTP server via the provided UDP socket.
+/// The function behaves the same way as [`get_time`], but returns [`NtpResponse`], see
+/// [`sntp_process_response_raw`].
 ///
-/// # Arguments
+/// # Errors
 ///
-/// * `dest` - The socket address (`SocketAddr`) of the NTP server.
-/// * `socket` - A reference to an object implementing the [`NtpUdpSocket`] trait
-///    that is used to send/receive UDP packets.
-/// * `context` - An SNTP context (`NtpContext<T>`) containing a timestamp generator
-///    that implements the [`NtpTimestampGenerator`] trait to provide a custom mechanism for generating timestamps.
+/// This function returns an `Err` in the same cases as [`get_time`].
+pub async fn get_time_raw<U, T>(
+    addr: net::SocketAddr,
+    socket: &U,
+    context: NtpContext<T>,
+) -> Result<NtpResponse>
+where
+    U: NtpUdpSocket + ?Sized,
+    T: NtpTimestampGenerator + Copy,
+{
+    let result = sntp_send_request(addr, socket, context).await?;
+
+    sntp_process_response_raw(addr, socket, context, result).await
+}
+
+/// Retrieves the current time from an NTP server using a socket that requires mutable access
 ///
-/// # Returns
+/// The function behaves the same way as [`get_time`], but accepts sockets implementing
+/// [`NtpUdpSocketMut`], e.g. `smoltcp::socket::udp::Socket` with the `smoltcp-socket` feature,
+/// so no interior mutability is required on the caller side.
 ///
-/// Returns a `Result<SendRequestResult>`:
-/// * `Ok(SendRequestResult)` - If the packet was successfully sent, includes details
-///    about the request, such as the originate timestamp.
-/// * `Err(Error)` - If there was an error in sending the request, such as a network failure.
+/// # Errors
 ///
-/// # Examples
+/// This function returns an `Err` in the same cases as [`get_time`].
+pub async fn get_time_mut<U, T>(
+    addr: net::SocketAddr,
+    socket: &mut U,
+    context: NtpContext<T>,
+) -> Result<NtpResult>
+where
+    U: NtpUdpSocketMut + ?Sized,
+    T: NtpTimestampGenerator + Copy,
+{
+    get_time(addr, &MutSocket::new(socket), context).await
+}
+
+/// Retrieves the current time from an NTP server driving a cooperative network stack in between
 ///
-/// ```rust
-/// use sntpc::{sntp_process_response, sntp_send_request, NtpContext, NtpTimestampGenerator, NtpUdpSocket, Result};
-/// # use miniloop::executor::Executor;
-/// use std::net::{SocketAddr, ToSocketAddrs};
-/// use core::net::{IpAddr, Ipv4Addr};
-/// # #[cfg(feature="std")]
-/// use std::net::UdpSocket;
+/// The function behaves the same way as [`get_time`], but calls `poll` every time the socket
+/// is not ready to proceed, i.e. `send_to` or `recv_from` returns [`Poll::Pending`], and then
+/// tries again right away. Bare-metal stacks that have to be polled by the application to
+/// process incoming packets may be serviced from `poll`, so the request can be completed from
+/// a superloop without an async executor with the help of `sync::get_time_with_poll`, which is
+/// available with the `sync` feature enabled.
 ///
-/// #[derive(Copy, Clone)]
-/// struct Timestamp;
-/// # #[cfg(not(feature="std"))]
-/// #[derive(Debug, Clone)]
-/// struct UdpSocket;
+/// The socket must not be borrowed by `poll`: for stacks that keep sockets in a shared set the
+/// socket implementation is expected to borrow the set only within `send_to` and `recv_from`.
 ///
-/// impl NtpTimestampGenerator for Timestamp {
-///     fn init(&mut self) {
-///         // ...
-///     }
-///     fn timestamp_sec(&self) -> u64 {
-///         0u64
-///     }
-///     fn timestamp_subsec_micros(&self) -> u32 {
-///         0u32
-///     }
-/// }
+/// [`Poll::Pending`]: core::task::Poll::Pending
 ///
-/// impl Default for Timestamp {
-///     fn default() -> Self {
-///         Self {}
-///     }
-/// }
+/// # Errors
 ///
-/// # #[cfg(not(feature = "std"))]
-/// # impl UdpSocket {
-/// #     fn bind(addr: &str) -> Result<Self> {
-/// #         Ok(UdpSocket{})
-/// #     }
-/// #     fn send_to<T: ToSocketAddrs>(&self, buf: &[u8], dest: T) -> Result<usize> {
-/// #        Ok(0usize)
-/// #     }
-/// #     fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddr)> {
-/// #        Ok((0usize, SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), 0)))
-/// #     }
-/// # }
-/// # #[cfg(not(feature="std"))]
-/// # impl NtpUdpSocket for UdpSocket {
-/// #     async fn send_to(
-/// #         &self,
-/// #         buf: &[u8],
-/// #         addr: SocketAddr,
-/// #     ) -> Result<usize> {
-/// #         match self.send_to(buf, addr) {
-/// #             Ok(usize) => Ok(usize),
-/// #             Err(_) => Err(sntpc::Error::Network),
-/// #         }
-/// #     }
-/// #
-/// #     async fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddr)> {
-/// #         match self.recv_from(buf) {
-/// #             Ok((size, addr)) => Ok((size, addr)),
-/// #             Err(_) => Err(sntpc::Error::Network),
-/// #         }
-/// #     }
-/// # }
+/// This function returns an `Err` in the same cases as [`get_time`].
+pub async fn get_time_with_poll<U, T, F>(
+    addr: net::SocketAddr,
+    socket: &U,
+    context: NtpContext<T>,
+    poll: F,
+) -> Result<NtpResult>
+where
+    U: NtpUdpSocket + ?Sized,
+    T: NtpTimestampGenerator + Copy,
+    F: FnMut(),
+{
+    poll_while_pending(get_time(addr, socket, context), poll).await
+}
+
+/// Polls `future` to completion calling `poll` every time it is not ready yet
+async fn poll_while_pending<F, P>(future: F, mut poll: P) -> F::Output
+where
+    F: core::future::Future,
+    P: FnMut(),
+{
+    let mut future = core::pin::pin!(future);
+
+    core::future::poll_fn(|cx| {
+        let output = future.as_mut().poll(cx);
+
+        if output.is_pending() {
+            poll();
+            // the progress depends on `poll` rather than on a wake up from the socket
+            cx.waker().wake_by_ref();
+        }
+
+        output
+    })
+    .await
+}
+
+/// Retrieves the current time from an NTP server with a burst of requests
 ///
-/// fn main() {
-///     let socket = UdpSocket::bind("0.0.0.0:0").expect("Unable to crate UDP socket");
-///     let context = NtpContext::new(Timestamp::default());
-///     let server_addr: SocketAddr = "time.google.com:123".to_socket_addrs().expect("Unable to resolve host").next().unwrap();
-///     # let mut executor = Executor::new();
+/// `count` requests (at least one) are sent one after another and the result with the lowest
+/// roundtrip delay is returned, since it has been affected by network queuing the least, see
+/// [`filter::NtpClockFilter`].
 ///
-///     let request_result = executor.block_on(async {
-///            sntp_send_request(server_addr, &socket, context).await
-///     });
-/// }
-/// ```
+/// If `max_spread` is set, the offsets of the samples may differ by no more than `max_spread`
+/// in the units of the context, see [`NtpResult::offset`]. Otherwise the burst is rejected, since the server or the
+/// network path is too jittery to trust any of the samples.
+///
+/// Servers may respond with a Kiss-o'-Death packet to clients that send requests too often,
+/// so the burst should be short and not repeated frequently.
 ///
 /// # Errors
 ///
-/// Returns `Err` if:
-/// * The SNTP packet fails to send to the provided address due to network issues.
-/// * The socket behavior does not conform to the expectations of the [`NtpUdpSocket`] trait.
-pub async fn sntp_send_request<U, T>(
-    dest: net::SocketAddr,
+/// Returns the error of the first failed request, or [`Error::InconsistentSamples`] if the
+/// offsets vary by more than `max_spread`.
+pub async fn get_time_burst<U, T>(
+    addr: net::SocketAddr,
     socket: &U,
     context: NtpContext<T>,
-) -> Result<SendRequestResult>
+    count: u32,
+    max_spread: Option<u64>,
+) -> Result<NtpResult>
 where
-    U: NtpUdpSocket,
-    T: NtpTimestampGenerator,
+    U: NtpUdpSocket + ?Sized,
+    T: NtpTimestampGenerator + Copy,
 {
-    #[cfg(any(feature = "log", feature = "defmt"))]
-    debug!("send request - Address: {:?}", dest);
-    let request = NtpPacket::new(context.timestamp_gen);
+    let mut best = get_time(addr, socket, context).await?;
+    let (mut min_offset, mut max_offset) = (best.offset(), best.offset());
 
-    send_request(dest, &request, socket).await?;
-    Ok(SendRequestResult::from(request))
+    for _ in 1..count {
+        let result = get_time(addr, socket, context).await?;
+
+        min_offset = min_offset.min(result.offset());
+        max_offset = max_offset.max(result.offset());
+        if result.roundtrip() < best.roundtrip() {
+            best = result;
+        }
+    }
+
+    if max_spread.is_some_and(|spread| max_offset.abs_diff(min_offset) > spread)
+    {
+        #[cfg(any(feature = "log", feature = "defmt"))]
+        debug!("Offsets vary from {} to {}", min_offset, max_offset);
+        return Err(Error::InconsistentSamples);
+    }
+
+    Ok(best)
 }
 
-/// Processes the response from an NTP server.
-///
-/// This function validates the SNTP response, ensuring that it comes from the expected server and that
-/// the payload size and structure are correct. It then calculates and returns the offset and
-/// roundtrip delay based on the time information.
+/// Retrieves the current time from an NTP server retrying failed requests
 ///
-/// # Arguments
+/// The request is attempted up to `max_attempts` times (at least once). A failed request is
+/// retried right away, so every attempt should be bounded with
+/// [`NtpContext::with_recv_timeout`] or the socket's own timeout. Use
+/// [`get_time_with_retry_and_delay`] with a runtime-specific sleep function to back off
+/// between attempts.
 ///
-/// * `dest` - The expected socket address (`SocketAddr`) of the NTP server.
-/// * `socket` - A reference to an object implementing the [`NtpUdpSocket`] trait
-///    used for receiving the response.
-/// * `context` - An SNTP context (`NtpContext<T>`) containing a timestamp generator
-///    that manages internal time calculations.
-/// * `send_req_result` - The result of the previously sent request, containing the originate timestamp
-///    of the SNTP request.
+/// A server that responded with a Kiss-o'-Death packet is not retried.
 ///
-/// # Returns
+/// # Errors
 ///
-/// Returns a `Result<NtpResult>`:
-/// * `Ok(NtpResult)` - If the response is valid, includes:
-///   - Calculated clock offset
-///   - Roundtrip delay
-///   - Stratum level
-///   - Precision level
-/// * `Err(Error)` - On failure, for reasons such as:
-///   - Mismatched server response address
-///   - Invalid packet size or structure
-///   - Incorrect mode or incorrect originate timestamp in the response
+/// Returns the last error if all attempts fail, or [`Error::KissOfDeath`] immediately.
+pub async fn get_time_with_retry<U, T>(
+    addr: net::SocketAddr,
+    socket: &U,
+    context: NtpContext<T>,
+    max_attempts: u32,
+) -> Result<NtpResult>
+where
+    U: NtpUdpSocket,
+    T: NtpTimestampGenerator + Copy,
+{
+    get_time_with_retry_and_delay(
+        addr,
+        socket,
+        context,
+        max_attempts,
+        0,
+        |_| async {},
+    )
+    .await
+}
+
+/// Retrieves the current time from an NTP server retrying failed requests with exponential backoff
 ///
-/// # Examples
+/// The request is attempted up to `max_attempts` times (at least once). After each failure the
+/// function waits `base_delay_ms` doubled for every previous failure, i.e. `base_delay_ms`,
+/// `2 * base_delay_ms`, `4 * base_delay_ms` and so on, with the provided `delay` function, that
+/// receives the delay duration in milliseconds, e.g.:
 ///
-/// ```rust
-/// use sntpc::{sntp_process_response, sntp_send_request, NtpContext, NtpTimestampGenerator, NtpUdpSocket, Result};
-/// # use miniloop::executor::Executor;
-/// use std::net::{SocketAddr, ToSocketAddrs};
-/// use core::net::{IpAddr, Ipv4Addr};
-/// # #[cfg(feature="std")]
+/// ```rust,no_run
+/// # #[cfg(feature = "std-socket")]
+/// # async fn retry() -> sntpc::Result<()> {
+/// use sntpc::{get_time_with_retry_and_delay, NtpContext, StdTimestampGen};
 /// use std::net::UdpSocket;
+/// use std::time::Duration;
 ///
-/// #[derive(Copy, Clone)]
-/// struct Timestamp;
-/// # #[cfg(not(feature="std"))]
-/// #[derive(Debug, Clone)]
-/// struct UdpSocket;
+/// let socket = UdpSocket::bind("0.0.0.0:0").unwrap();
+/// let context = NtpContext::new(StdTimestampGen::default());
+/// let addr = "216.239.35.0:123".parse().unwrap();
 ///
-/// impl NtpTimestampGenerator for Timestamp {
-///     fn init(&mut self) {
-///         // ...
-///     }
-///     fn timestamp_sec(&self) -> u64 {
-///         0u64
-///     }
-///     fn timestamp_subsec_micros(&self) -> u32 {
-///         0u32
-///     }
-/// }
+/// let result = get_time_with_retry_and_delay(addr, &socket, context, 5, 100, |ms| {
+///     tokio::time::sleep(Duration::from_millis(ms.into()))
+/// })
+/// .await?;
+/// # Ok(())
+/// # }
+/// ```
 ///
-/// impl Default for Timestamp {
-///     fn default() -> Self {
-///         Self {}
-///     }
-/// }
+/// A server that responded with a Kiss-o'-Death packet is not retried.
 ///
-/// # #[cfg(not(feature = "std"))]
-/// # impl UdpSocket {
-/// #     fn bind(addr: &str) -> Result<Self> {
-/// #         Ok(UdpSocket{})
-/// #     }
-/// #     fn send_to<T: ToSocketAddrs>(&self, buf: &[u8], dest: T) -> Result<usize> {
-/// #        Ok(buf.len())
-/// #     }
-/// #     fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddr)> {
-/// #        Ok((0usize, SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), 0)))
-/// #     }
-/// # }
-/// # #[cfg(not(feature="std"))]
-/// # impl NtpUdpSocket for UdpSocket {
-/// #     async fn send_to(
-/// #         &self,
-/// #         buf: &[u8],
-/// #         addr: SocketAddr,
-/// #     ) -> Result<usize> {
-/// #         match self.send_to(buf, addr) {
-/// #             Ok(usize) => Ok(usize),
-/// #             Err(_) => Err(sntpc::Error::Network),
-/// #         }
-/// #     }
-/// #
-/// #     async fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddr)> {
-/// #         match self.recv_from(buf) {
-/// #             Ok((size, addr)) => Ok((size, addr)),
-/// #             Err(_) => Err(sntpc::Error::Network),
-/// #         }
-/// #     }
+/// # Errors
+///
+/// Returns the last error if all attempts fail, or [`Error::KissOfDeath`] immediately.
+#[cfg_attr(
+    not(any(feature = "log", feature = "defmt")),
+    allow(unused_variables)
+)]
+pub async fn get_time_with_retry_and_delay<U, T, F, Fut>(
+    addr: net::SocketAddr,
+    socket: &U,
+    context: NtpContext<T>,
+    max_attempts: u32,
+    base_delay_ms: u32,
+    mut delay: F,
+) -> Result<NtpResult>
+where
+    U: NtpUdpSocket,
+    T: NtpTimestampGenerator + Copy,
+    F: FnMut(u32) -> Fut,
+    Fut: core::future::Future<Output = ()>,
+{
+    let max_attempts = max_attempts.max(1);
+    let mut attempt = 1;
+
+    loop {
+        match get_time(addr, socket, context).await {
+            Ok(result) => return Ok(result),
+            Err(e @ Error::KissOfDeath(_)) => return Err(e),
+            Err(e) if attempt >= max_attempts => return Err(e),
+            Err(e) => {
+                let delay_ms =
+                    1u32.checked_shl(attempt - 1).map_or(u32::MAX, |factor| {
+                        base_delay_ms.saturating_mul(factor)
+                    });
+                #[cfg(any(feature = "log", feature = "defmt"))]
+                debug!(
+                    "Attempt {}/{} failed: {:?}, retrying in {} ms",
+                    attempt, max_attempts, e, delay_ms
+                );
+                delay(delay_ms).await;
+                attempt += 1;
+            }
+        }
+    }
+}
+
+/// Retrieves the current time from the first NTP server in the list that responds
+///
+/// Servers are queried one by one in the given order. Servers that fail with [`Error::Timeout`],
+/// [`Error::Network`] or [`Error::NoRoute`] are skipped, any other error stops the iteration,
+/// since it is unlikely to be resolved by asking another server. The socket should have a
+/// receive timeout configured (e.g. `std::net::UdpSocket::set_read_timeout`), otherwise an
+/// unresponsive server blocks the iteration.
+///
+/// # Arguments
+///
+/// * `addrs` - The socket addresses of NTP servers, e.g. all addresses `pool.ntp.org` resolves to.
+/// * `socket` - A reference to an object implementing the [`NtpUdpSocket`] trait.
+/// * `context` - An SNTP context containing a timestamp generator.
+///
+/// # Returns
+///
+/// Returns the address of the server that answered along with the result.
+///
+/// # Errors
+///
+/// Returns [`Error::AddressResolve`] if `addrs` is empty, the last error if all servers failed
+/// with [`Error::Timeout`], [`Error::Network`] or [`Error::NoRoute`], or the first error of any
+/// other kind.
+pub async fn get_time_multi<U, T>(
+    addrs: &[net::SocketAddr],
+    socket: &U,
+    context: NtpContext<T>,
+) -> Result<(net::SocketAddr, NtpResult)>
+where
+    U: NtpUdpSocket,
+    T: NtpTimestampGenerator + Copy,
+{
+    get_time_first_of(addrs.iter().copied(), socket, context, |e| {
+        matches!(e, Error::Timeout | Error::Network | Error::NoRoute)
+    })
+    .await
+}
+
+/// Retrieves the current time from the first NTP server in the list that provides a valid response
+///
+/// Unlike [`get_time_multi`], any error is treated as a failure of the particular server, so
+/// the next address is tried until one of them succeeds. Each response is verified against
+/// the address the request has been sent to. That is handy when a server hostname resolves
+/// to several addresses:
+///
+/// ```rust,no_run
+/// # #[cfg(feature = "std-socket")]
+/// # async fn any() -> sntpc::Result<()> {
+/// use sntpc::{get_time_any, NtpContext, StdTimestampGen};
+/// use std::net::{ToSocketAddrs, UdpSocket};
+///
+/// let socket = UdpSocket::bind("0.0.0.0:0").unwrap();
+/// let addrs: Vec<_> = "pool.ntp.org:123".to_socket_addrs().unwrap().collect();
+/// let context = NtpContext::new(StdTimestampGen::default());
+/// let result = get_time_any(&addrs, &socket, context).await?;
+/// # Ok(())
 /// # }
+/// ```
 ///
-/// fn main() {
-///     let socket = UdpSocket::bind("0.0.0.0:0").expect("Unable to crate UDP socket");
-///     let context = NtpContext::new(Timestamp::default());
-///     let server_addr: SocketAddr = "time.google.com:123".to_socket_addrs().expect("Unable to resolve host").filter(SocketAddr::is_ipv4).next().unwrap();
-///     # let mut executor = Executor::new();
+/// # Errors
 ///
-///     let request_result = executor.block_on(async {
-///            sntp_send_request(server_addr, &socket, context).await.expect("Unable to send request")
-///     });
+/// Returns [`Error::AddressResolve`] if `addrs` is empty or the last error if all servers failed.
+pub async fn get_time_any<U, T>(
+    addrs: &[net::SocketAddr],
+    socket: &U,
+    context: NtpContext<T>,
+) -> Result<NtpResult>
+where
+    U: NtpUdpSocket,
+    T: NtpTimestampGenerator + Copy,
+{
+    get_time_first_of(addrs.iter().copied(), socket, context, |_| true)
+        .await
+        .map(|(_, result)| result)
+}
+
+/// Retrieves the current time from the first NTP server in the list that provides a valid response
+/// within the given time
 ///
-///     match executor.block_on(async {
-///         sntp_process_response(server_addr, &socket, context, request_result).await
-///     })
-///     {
-///         Ok(response_result) => println!("Response processed: {response_result:?}"),
-///         Err(err) => eprintln!("Error: {err:?}"),
-///     }
-/// }
+/// Same as [`get_time_any`], but the request to each server is bounded by the future returned by
+/// `timeout`, so a server that accepts the request but never answers does not stall the
+/// iteration even if the socket has no receive timeout. The request is abandoned and the next
+/// address is tried as soon as the `timeout` future completes, e.g.:
+///
+/// ```rust,no_run
+/// # #[cfg(feature = "tokio-socket")]
+/// # async fn any() -> sntpc::Result<()> {
+/// use sntpc::{get_time_any_with_timeout, NtpContext, StdTimestampGen};
+/// use std::time::Duration;
+/// use tokio::net::UdpSocket;
+///
+/// let socket = UdpSocket::bind("0.0.0.0:0").await.unwrap();
+/// let addrs: Vec<_> = tokio::net::lookup_host("pool.ntp.org:123").await.unwrap().collect();
+/// let context = NtpContext::new(StdTimestampGen::default());
+/// let result = get_time_any_with_timeout(&addrs, &socket, context, || {
+///     tokio::time::sleep(Duration::from_secs(2))
+/// })
+/// .await?;
+/// # Ok(())
+/// # }
 /// ```
 ///
 /// # Errors
 ///
-/// This function returns an `Err` in any of the following situations:
-/// * The source address of the response does not match the server address used for the request.
-/// * The size of the response is incorrect or does not match the expected format.
-/// * The mode or version in the response is invalid.
-pub async fn sntp_process_response<U, T>(
-    dest: net::SocketAddr,
+/// Returns [`Error::AddressResolve`] if `addrs` is empty or the last error if all servers failed.
+/// A server that did not respond in time fails with [`Error::Timeout`].
+#[cfg_attr(
+    not(any(feature = "log", feature = "defmt")),
+    allow(unused_variables)
+)]
+pub async fn get_time_any_with_timeout<U, T, F, Fut>(
+    addrs: &[net::SocketAddr],
     socket: &U,
-    mut context: NtpContext<T>,
-    send_req_result: SendRequestResult,
+    context: NtpContext<T>,
+    mut timeout: F,
 ) -> Result<NtpResult>
 where
     U: NtpUdpSocket,
-    T: NtpTimestampGenerator,
+    T: NtpTimestampGenerator + Copy,
+    F: FnMut() -> Fut,
+    Fut: core::future::Future<Output = ()>,
 {
-    let mut response_buf = RawNtpPacket::default();
-    let (response, src) = socket.recv_from(response_buf.0.as_mut()).await?;
-    context.timestamp_gen.init();
-    let recv_timestamp = get_ntp_timestamp(&context.timestamp_gen);
-    #[cfg(any(feature = "log", feature = "defmt"))]
-    debug!("Response: {}", response);
+    let mut last_error = Error::AddressResolve;
 
-    if dest != src {
-        return Err(Error::ResponseAddressMismatch);
-    }
+    for &addr in addrs {
+        let result = with_timeout(get_time(addr, socket, context), timeout())
+            .await
+            .unwrap_or(Err(Error::Timeout));
 
-    if response != size_of::<NtpPacket>() {
-        return Err(Error::IncorrectPayload);
+        match result {
+            Ok(result) => return Ok(result),
+            Err(e) => {
+                #[cfg(any(feature = "log", feature = "defmt"))]
+                debug!("Skipping server {:?}: {:?}", addr, e);
+                last_error = e;
+            }
+        }
     }
 
-    let result =
-        process_response(send_req_result, response_buf, recv_timestamp);
+    Err(last_error)
+}
 
-    #[cfg(any(feature = "log", feature = "defmt"))]
-    if let Ok(r) = &result {
-        debug!("{:?}", r);
-    }
+/// Polls `future` until it completes or `timeout` elapses, whichever comes first
+async fn with_timeout<F, D>(future: F, timeout: D) -> Option<F::Output>
+where
+    F: core::future::Future,
+    D: core::future::Future<Output = ()>,
+{
+    let mut future = core::pin::pin!(future);
+    let mut timeout = core::pin::pin!(timeout);
 
-    result
+    core::future::poll_fn(|cx| {
+        if let core::task::Poll::Ready(output) = future.as_mut().poll(cx) {
+            return core::task::Poll::Ready(Some(output));
+        }
+
+        timeout.as_mut().poll(cx).map(|()| None)
+    })
+    .await
 }
 
-async fn send_request<U>(
-    dest: net::SocketAddr,
-    req: &NtpPacket,
+/// Retrieves the current time from an NTP server with the given host name
+///
+/// The host name is resolved with `resolver`, then the resolved addresses are queried on the
+/// standard NTP port `123` one by one until one of them responds, as [`get_time_any`] does.
+///
+/// # Arguments
+///
+/// * `host` - The host name of the NTP server, e.g. `pool.ntp.org`.
+/// * `resolver` - A reference to an object implementing the [`NtpHostResolver`] trait.
+/// * `socket` - A reference to an object implementing the [`NtpUdpSocket`] trait.
+/// * `context` - An SNTP context containing a timestamp generator.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # #[cfg(feature = "std-socket")]
+/// # async fn by_name() -> sntpc::Result<()> {
+/// use sntpc::{get_time_by_name, NtpContext, StdResolver, StdTimestampGen};
+/// use std::net::UdpSocket;
+///
+/// let socket = UdpSocket::bind("0.0.0.0:0").unwrap();
+/// let context = NtpContext::new(StdTimestampGen::default());
+/// let result =
+///     get_time_by_name("pool.ntp.org", &StdResolver, &socket, context).await?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// Returns [`Error::AddressResolve`] if `host` can not be resolved or resolves to no
+/// addresses, or the last error if all servers failed.
+pub async fn get_time_by_name<R, U, T>(
+    host: &str,
+    resolver: &R,
     socket: &U,
-) -> Result<()>
+    context: NtpContext<T>,
+) -> Result<NtpResult>
 where
+    R: NtpHostResolver,
     U: NtpUdpSocket,
+    T: NtpTimestampGenerator + Copy,
 {
-    let buf = RawNtpPacket::from(req);
+    let addrs = resolver.resolve(host, NTP_PORT).await?;
 
-    match socket.send_to(&buf.0, dest).await {
-        Ok(size) => {
-            if size == buf.0.len() {
-                Ok(())
-            } else {
-                Err(Error::Network)
-            }
-        }
-        Err(_) => Err(Error::Network),
-    }
+    get_time_first_of(addrs, socket, context, |_| true)
+        .await
+        .map(|(_, result)| result)
 }
 
-/// Synchronous interface for the SNTP client
-#[cfg(feature = "sync")]
-pub mod sync {
-    #[cfg(any(feature = "log", feature = "defmt"))]
-    use crate::log::debug;
-    use crate::net;
-    use crate::types::{
-        NtpContext, NtpResult, NtpTimestampGenerator, NtpUdpSocket, Result,
-        SendRequestResult,
+/// Retrieves the current time from an NTP server given as [`ServerAddr`]
+///
+/// Host names are resolved with `std::net::ToSocketAddrs`, then the resolved addresses are
+/// queried one by one until one of them responds, as [`get_time_any`] does.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # #[cfg(feature = "std-socket")]
+/// # async fn by_server() -> sntpc::Result<()> {
+/// use sntpc::{get_time_by_server, NtpContext, StdTimestampGen};
+/// use std::net::UdpSocket;
+///
+/// let socket = UdpSocket::bind("0.0.0.0:0").unwrap();
+/// let context = NtpContext::new(StdTimestampGen::default());
+/// let server = "time.google.com".parse()?;
+/// let result = get_time_by_server(&server, &socket, context).await?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// Returns [`Error::AddressResolve`] if the server host name can not be resolved, or the last
+/// error if all resolved servers failed.
+#[cfg(feature = "std")]
+pub async fn get_time_by_server<U, T>(
+    server: &ServerAddr,
+    socket: &U,
+    context: NtpContext<T>,
+) -> Result<NtpResult>
+where
+    U: NtpUdpSocket,
+    T: NtpTimestampGenerator + Copy,
+{
+    let addrs = std::net::ToSocketAddrs::to_socket_addrs(server)
+        .map_err(|_| Error::AddressResolve)?;
+
+    get_time_first_of(addrs, socket, context, |_| true)
+        .await
+        .map(|(_, result)| result)
+}
+
+/// Maximum number of SRV targets [`get_time_by_srv`] tries
+const MAX_SRV_TARGETS: usize = 8;
+
+/// Retrieves the current time from NTP servers published in DNS SRV records of a domain
+///
+/// The function looks up `_ntp._udp.<domain>` SRV records with `resolver` and queries the
+/// targets one by one until one of them responds. Targets are ordered by priority, and the
+/// targets with the same priority are ordered with the weighted random selection of
+/// [RFC 2782](https://datatracker.ietf.org/doc/html/rfc2782), so targets with higher weight
+/// are likely to be queried first. The selection is seeded with the current time of the
+/// context's timestamp generator. Up to 8 targets are tried.
+///
+/// If the SRV lookup fails or returns no records, the function falls back to
+/// [`get_time_by_name`] with `domain` as the host name.
+///
+/// # Arguments
+///
+/// * `domain` - The domain SRV records are published for, e.g. `example.com`.
+/// * `resolver` - A reference to an object implementing the [`NtpSrvResolver`] trait.
+/// * `socket` - A reference to an object implementing the [`NtpUdpSocket`] trait.
+/// * `context` - An SNTP context containing a timestamp generator.
+///
+/// # Errors
+///
+/// Returns [`Error::AddressResolve`] if neither SRV records nor the host name can be
+/// resolved, or the last error if all servers failed.
+pub async fn get_time_by_srv<R, U, T>(
+    domain: &str,
+    resolver: &R,
+    socket: &U,
+    context: NtpContext<T>,
+) -> Result<NtpResult>
+where
+    R: NtpSrvResolver,
+    U: NtpUdpSocket,
+    T: NtpTimestampGenerator + Copy,
+{
+    let mut name = NameBuf::new();
+
+    if write!(name, "_ntp._udp.{domain}").is_err() {
+        return Err(Error::AddressResolve);
+    }
+
+    let unspecified = SrvTarget {
+        priority: 0,
+        weight: 0,
+        addr: net::SocketAddr::new(core::net::Ipv4Addr::UNSPECIFIED.into(), 0),
     };
+    let mut targets = [unspecified; MAX_SRV_TARGETS];
+    let mut len = 0;
 
-    use miniloop::executor::Executor;
-    /// Send request to a NTP server with the given address and process the response in a single call
-    ///
-    /// May be useful under an environment with `std` networking implementation, where all
-    /// network stuff is hidden within system's kernel. For environment with custom
-    /// Uses [`NtpUdpSocket`] and [`NtpTimestampGenerator`] trait bounds to allow generic specification
-    /// of objects that can be used with the library
-    ///
-    /// # Arguments
-    ///
-    /// - `pool_addrs` - Server's name or IP address with port specification as a string
-    /// - `socket` - UDP socket object that will be used during NTP request-response
-    ///   communication
-    /// - `context` - SNTP client context to provide timestamp generation feature
-    ///
-    /// # Errors
-    ///
-    /// Will return `Err` if an SNTP request cannot be sent or SNTP response fails
-    pub fn get_time<U, T>(
-        addr: net::SocketAddr,
-        socket: &U,
-        context: NtpContext<T>,
-    ) -> Result<NtpResult>
-    where
-        U: NtpUdpSocket,
-        T: NtpTimestampGenerator + Copy,
-    {
-        let result = sntp_send_request(addr, socket, context)?;
+    if let Ok(resolved) = resolver.resolve_srv(name.as_str()).await {
+        for (slot, target) in targets.iter_mut().zip(resolved) {
+            *slot = target;
+            len += 1;
+        }
+    }
+
+    if len == 0 {
         #[cfg(any(feature = "log", feature = "defmt"))]
-        debug!("{:?}", result);
+        debug!("No SRV records found for {}", domain);
+        return get_time_by_name(domain, resolver, socket, context).await;
+    }
 
-        sntp_process_response(addr, socket, context, result)
+    let mut timestamp_gen = context.timestamp_gen;
+    order_srv_targets(
+        &mut targets[..len],
+        timestamp_micros(&mut timestamp_gen),
+    );
+
+    get_time_first_of(
+        targets[..len].iter().map(|target| target.addr),
+        socket,
+        context,
+        |_| true,
+    )
+    .await
+    .map(|(_, result)| result)
+}
+
+/// Query servers in order until one of them responds, skipping servers with errors `skip`
+/// returns `true` for
+async fn get_time_first_of<I, U, T, F>(
+    addrs: I,
+    socket: &U,
+    context: NtpContext<T>,
+    skip: F,
+) -> Result<(net::SocketAddr, NtpResult)>
+where
+    I: IntoIterator<Item = net::SocketAddr>,
+    U: NtpUdpSocket,
+    T: NtpTimestampGenerator + Copy,
+    F: Fn(&Error) -> bool,
+{
+    let mut last_error = Error::AddressResolve;
+
+    for addr in addrs {
+        match get_time(addr, socket, context).await {
+            Ok(result) => return Ok((addr, result)),
+            Err(e) if skip(&e) => {
+                #[cfg(any(feature = "log", feature = "defmt"))]
+                debug!("Skipping server {:?}: {:?}", addr, e);
+                last_error = e;
+            }
+            Err(e) => return Err(e),
+        }
     }
 
-    /// Send an SNTP request to the specified destination synchronously.
-    ///
-    /// This function is a synchronous wrapper for the asynchronous [`crate::sntp_send_request`].
-    /// It uses an executor to block the current thread while waiting for the underlying
-    /// asynchronous operation to complete.
-    ///
-    /// # Arguments
-    ///
-    /// * `dest` - The destination NTP server's socket address to send the request to.
-    /// * `socket` - A reference to an object implementing the [`NtpUdpSocket`] trait to send/receive data.
-    /// * `context` - The SNTP client context (implementing [`NtpTimestampGenerator`]) that
-    ///    assists in generating timestamps for the request.
-    ///
-    /// # Errors
-    ///
-    /// Returns an `Err` if the underlying async SNTP request fails for any reason,
+    Err(last_error)
+}
+
+/// Retrieves the current time from an NTP server unless the server has been queried too recently
+///
+/// The function consults the given [`RateLimiter`] before sending the request, so it is safe to
+/// be called in a retry loop without flooding public NTP servers.
+///
+/// # Arguments
+///
+/// * `addr` - The socket address of the NTP server.
+/// * `socket` - A reference to an object implementing the [`NtpUdpSocket`] trait.
+/// * `context` - An SNTP context containing a timestamp generator, that is also used as the
+///   time source for the rate limiter.
+/// * `limiter` - A rate limiter that tracks the last request time per server.
+///
+/// # Errors
+///
+/// Returns [`Error::RateLimited`] if the minimum interval since the previous request to `addr`
+/// has not elapsed yet. Otherwise, returns an `Err` in the same cases as [`get_time`].
+pub async fn get_time_rate_limited<U, T, const N: usize>(
+    addr: net::SocketAddr,
+    socket: &U,
+    mut context: NtpContext<T>,
+    limiter: &mut RateLimiter<N>,
+) -> Result<NtpResult>
+where
+    U: NtpUdpSocket,
+    T: NtpTimestampGenerator + Copy,
+{
+    limiter.acquire(addr, &mut context.timestamp_gen)?;
+
+    get_time(addr, socket, context).await
+}
+
+/// Retrieves the current time from an NTP server unless the server sent a Kiss-o'-Death
+/// response earlier
+///
+/// The function consults the given [`KodCache`] before sending the request and records the
+/// kiss code if the server responds with a Kiss-o'-Death packet, so servers that denied access
+/// are never queried again and servers that asked to reduce the rate are backed off.
+///
+/// # Arguments
+///
+/// * `addr` - The socket address of the NTP server.
+/// * `socket` - A reference to an object implementing the [`NtpUdpSocket`] trait.
+/// * `context` - An SNTP context containing a timestamp generator, that is also used as the
+///   time source for the cache.
+/// * `cache` - A cache of servers that responded with Kiss-o'-Death packets.
+///
+/// # Errors
+///
+/// Returns [`Error::KissOfDeath`] if the server denied access, [`Error::RateLimited`] if the
+/// server asked to reduce the rate and the backoff delay has not passed yet. Otherwise, returns
+/// an `Err` in the same cases as [`get_time`].
+pub async fn get_time_with_kod_cache<U, T, const N: usize>(
+    addr: net::SocketAddr,
+    socket: &U,
+    mut context: NtpContext<T>,
+    cache: &mut KodCache<N>,
+) -> Result<NtpResult>
+where
+    U: NtpUdpSocket,
+    T: NtpTimestampGenerator + Copy,
+{
+    cache.check(addr, &mut context.timestamp_gen)?;
+
+    let result = get_time(addr, socket, context).await;
+
+    if let Err(Error::KissOfDeath(code)) = result {
+        cache.record(addr, code, &mut context.timestamp_gen);
+    }
+
+    result
+}
+
+/// Sends an SNTP request to an NTP server.
+///
+/// This function creates an SNTP packet using the given timestamp generator and
+/// sends it to the given NTP server via the provided UDP socket.
+///
+/// # Arguments
+///
+/// * `dest` - The socket address (`SocketAddr`) of the NTP server.
+/// * `socket` - A reference to an object implementing the [`NtpSendSocket`] trait
+///   that is used to send/receive UDP packets.
+/// * `context` - An SNTP context (`NtpContext<T>`) containing a timestamp generator
+///   that implements the [`NtpTimestampGenerator`] trait to provide a custom mechanism for generating timestamps.
+///
+/// # Returns
+///
+/// Returns a `Result<SendRequestResult>`:
+/// * `Ok(SendRequestResult)` - If the packet was successfully sent, includes details
+///   about the request, such as the originate timestamp.
+/// * `Err(Error)` - If there was an error in sending the request, such as a network failure.
+///
+/// # Examples
+///
+/// ```rust
+/// use sntpc::{sntp_process_response, sntp_send_request, NtpContext, NtpTimestampGenerator, NtpUdpSocket, Result};
+/// # use miniloop::executor::Executor;
+/// use std::net::{SocketAddr, ToSocketAddrs};
+/// use core::net::{IpAddr, Ipv4Addr};
+/// # #[cfg(feature="std")]
+/// use std::net::UdpSocket;
+///
+/// #[derive(Copy, Clone)]
+/// struct Timestamp;
+/// # #[cfg(not(feature="std"))]
+/// #[derive(Debug, Clone)]
+/// struct UdpSocket;
+///
+/// impl NtpTimestampGenerator for Timestamp {
+///     fn init(&mut self) {
+///         // ...
+///     }
+///     fn timestamp_sec(&self) -> u64 {
+///         0u64
+///     }
+///     fn timestamp_subsec_micros(&self) -> u32 {
+///         0u32
+///     }
+/// }
+///
+/// impl Default for Timestamp {
+///     fn default() -> Self {
+///         Self {}
+///     }
+/// }
+///
+/// # #[cfg(not(feature = "std"))]
+/// # impl UdpSocket {
+/// #     fn bind(addr: &str) -> Result<Self> {
+/// #         Ok(UdpSocket{})
+/// #     }
+/// #     fn send_to<T: ToSocketAddrs>(&self, buf: &[u8], dest: T) -> Result<usize> {
+/// #        Ok(0usize)
+/// #     }
+/// #     fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddr)> {
+/// #        Ok((0usize, SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), 0)))
+/// #     }
+/// # }
+/// # #[cfg(not(feature="std"))]
+/// # impl NtpUdpSocket for UdpSocket {
+/// #     async fn send_to(
+/// #         &self,
+/// #         buf: &[u8],
+/// #         addr: SocketAddr,
+/// #     ) -> Result<usize> {
+/// #         match self.send_to(buf, addr) {
+/// #             Ok(usize) => Ok(usize),
+/// #             Err(_) => Err(sntpc::Error::Network),
+/// #         }
+/// #     }
+/// #
+/// #     async fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddr)> {
+/// #         match self.recv_from(buf) {
+/// #             Ok((size, addr)) => Ok((size, addr)),
+/// #             Err(_) => Err(sntpc::Error::Network),
+/// #         }
+/// #     }
+/// # }
+///
+/// fn main() {
+///     let socket = UdpSocket::bind("0.0.0.0:0").expect("Unable to crate UDP socket");
+///     let context = NtpContext::new(Timestamp::default());
+///     let server_addr: SocketAddr = "time.google.com:123".to_socket_addrs().expect("Unable to resolve host").next().unwrap();
+///     # let mut executor = Executor::new();
+///
+///     let request_result = executor.block_on(async {
+///            sntp_send_request(server_addr, &socket, context).await
+///     });
+/// }
+/// ```
+///
+/// # Errors
+///
+/// Returns `Err` if:
+/// * The SNTP packet fails to send to the provided address due to network issues.
+/// * The socket behavior does not conform to the expectations of the [`NtpUdpSocket`] trait.
+pub async fn sntp_send_request<U, T>(
+    dest: net::SocketAddr,
+    socket: &U,
+    context: NtpContext<T>,
+) -> Result<SendRequestResult>
+where
+    U: NtpSendSocket + ?Sized,
+    T: NtpTimestampGenerator,
+{
+    #[cfg(any(feature = "log", feature = "defmt"))]
+    debug!("send request - Address: {:?}", dest);
+    let inspector = context.inspector;
+    let request = build_request(context);
+    #[cfg(any(feature = "log", feature = "defmt"))]
+    debug!(
+        "Request: li_vn_mode: {:#x}, version: {}, tx_timestamp: {:#x}",
+        request.li_vn_mode,
+        shifter(request.li_vn_mode, VERSION_MASK, VERSION_SHIFT),
+        request.tx_timestamp
+    );
+
+    send_request(dest, &request, socket, inspector).await?;
+    Ok(SendRequestResult::from(request))
+}
+
+/// Send a custom SNTP request to the specified destination
+///
+/// The function behaves the same way as [`sntp_send_request`], but sends the header fields of
+/// `request` instead of the ones configured with the context. The transmit timestamp is taken
+/// from the context's timestamp generator and recorded in the returned [`SendRequestResult`],
+/// so the response is processed with [`sntp_process_response`] as usual.
+///
+/// # Errors
+///
+/// This function returns an `Err` in the same situations as [`sntp_send_request`].
+pub async fn sntp_send_custom_request<U, T>(
+    dest: net::SocketAddr,
+    request: &NtpRequest,
+    socket: &U,
+    context: NtpContext<T>,
+) -> Result<SendRequestResult>
+where
+    U: NtpSendSocket + ?Sized,
+    T: NtpTimestampGenerator,
+{
+    let request = request.to_packet(context.timestamp_gen);
+
+    send_request(dest, &request, socket, context.inspector).await?;
+    Ok(SendRequestResult::from(request))
+}
+
+/// Processes the response from an NTP server.
+///
+/// This function validates the SNTP response, ensuring that it comes from the expected server and that
+/// the payload size and structure are correct. It then calculates and returns the offset and
+/// roundtrip delay based on the time information.
+///
+/// # Arguments
+///
+/// * `dest` - The expected socket address (`SocketAddr`) of the NTP server.
+/// * `socket` - A reference to an object implementing the [`NtpRecvSocket`] trait
+///   used for receiving the response.
+/// * `context` - An SNTP context (`NtpContext<T>`) containing a timestamp generator
+///   that manages internal time calculations.
+/// * `send_req_result` - The result of the previously sent request, containing the originate timestamp
+///   of the SNTP request.
+///
+/// # Returns
+///
+/// Returns a `Result<NtpResult>`:
+/// * `Ok(NtpResult)` - If the response is valid, includes:
+///   - Calculated clock offset
+///   - Roundtrip delay
+///   - Stratum level
+///   - Precision level
+/// * `Err(Error)` - On failure, for reasons such as:
+///   - Mismatched server response address
+///   - Invalid packet size or structure
+///   - Incorrect mode or incorrect originate timestamp in the response
+///
+/// # Examples
+///
+/// ```rust
+/// use sntpc::{sntp_process_response, sntp_send_request, NtpContext, NtpTimestampGenerator, NtpUdpSocket, Result};
+/// # use miniloop::executor::Executor;
+/// use std::net::{SocketAddr, ToSocketAddrs};
+/// use core::net::{IpAddr, Ipv4Addr};
+/// # #[cfg(feature="std")]
+/// use std::net::UdpSocket;
+///
+/// #[derive(Copy, Clone)]
+/// struct Timestamp;
+/// # #[cfg(not(feature="std"))]
+/// #[derive(Debug, Clone)]
+/// struct UdpSocket;
+///
+/// impl NtpTimestampGenerator for Timestamp {
+///     fn init(&mut self) {
+///         // ...
+///     }
+///     fn timestamp_sec(&self) -> u64 {
+///         0u64
+///     }
+///     fn timestamp_subsec_micros(&self) -> u32 {
+///         0u32
+///     }
+/// }
+///
+/// impl Default for Timestamp {
+///     fn default() -> Self {
+///         Self {}
+///     }
+/// }
+///
+/// # #[cfg(not(feature = "std"))]
+/// # impl UdpSocket {
+/// #     fn bind(addr: &str) -> Result<Self> {
+/// #         Ok(UdpSocket{})
+/// #     }
+/// #     fn send_to<T: ToSocketAddrs>(&self, buf: &[u8], dest: T) -> Result<usize> {
+/// #        Ok(buf.len())
+/// #     }
+/// #     fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddr)> {
+/// #        Ok((0usize, SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), 0)))
+/// #     }
+/// # }
+/// # #[cfg(not(feature="std"))]
+/// # impl NtpUdpSocket for UdpSocket {
+/// #     async fn send_to(
+/// #         &self,
+/// #         buf: &[u8],
+/// #         addr: SocketAddr,
+/// #     ) -> Result<usize> {
+/// #         match self.send_to(buf, addr) {
+/// #             Ok(usize) => Ok(usize),
+/// #             Err(_) => Err(sntpc::Error::Network),
+/// #         }
+/// #     }
+/// #
+/// #     async fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddr)> {
+/// #         match self.recv_from(buf) {
+/// #             Ok((size, addr)) => Ok((size, addr)),
+/// #             Err(_) => Err(sntpc::Error::Network),
+/// #         }
+/// #     }
+/// # }
+///
+/// fn main() {
+///     let socket = UdpSocket::bind("0.0.0.0:0").expect("Unable to crate UDP socket");
+///     let context = NtpContext::new(Timestamp::default());
+///     let server_addr: SocketAddr = "time.google.com:123".to_socket_addrs().expect("Unable to resolve host").filter(SocketAddr::is_ipv4).next().unwrap();
+///     # let mut executor = Executor::new();
+///
+///     let request_result = executor.block_on(async {
+///            sntp_send_request(server_addr, &socket, context).await.expect("Unable to send request")
+///     });
+///
+///     match executor.block_on(async {
+///         sntp_process_response(server_addr, &socket, context, request_result).await
+///     })
+///     {
+///         Ok(response_result) => println!("Response processed: {response_result:?}"),
+///         Err(err) => eprintln!("Error: {err:?}"),
+///     }
+/// }
+/// ```
+///
+/// # Errors
+///
+/// This function returns an `Err` in any of the following situations:
+/// * The source address of the response does not match the server address used for the request.
+/// * The size of the response is incorrect or does not match the expected format.
+/// * The mode or version in the response is invalid.
+///
+/// # Cancel safety
+///
+/// The function keeps no state outside of the call: the receive buffer is local to the future
+/// and [`SendRequestResult`] is copied in. If the future is dropped before completion, e.g.
+/// because `embassy_time::with_timeout` expired, the function may be called again with the
+/// same [`SendRequestResult`] to wait for a response that is still in flight without sending
+/// another request, as long as the socket's `recv_from` is cancel safe itself.
+pub async fn sntp_process_response<U, T>(
+    dest: net::SocketAddr,
+    socket: &U,
+    mut context: NtpContext<T>,
+    send_req_result: SendRequestResult,
+) -> Result<NtpResult>
+where
+    U: NtpRecvSocket + ?Sized,
+    T: NtpTimestampGenerator,
+{
+    receive_response(dest, socket, &mut context, send_req_result, None).await
+}
+
+/// Process the SNTP response and return every field of the validated response header
+///
+/// The function behaves the same way as [`sntp_process_response`], but returns
+/// [`NtpResponse`] that carries the raw header fields and timestamps T1-T4 along with the
+/// computed [`NtpResult`], e.g. to feed a custom filtering algorithm.
+///
+/// # Errors
+///
+/// This function returns an `Err` in the same situations as [`sntp_process_response`].
+pub async fn sntp_process_response_raw<U, T>(
+    dest: net::SocketAddr,
+    socket: &U,
+    mut context: NtpContext<T>,
+    send_req_result: SendRequestResult,
+) -> Result<NtpResponse>
+where
+    U: NtpRecvSocket + ?Sized,
+    T: NtpTimestampGenerator,
+{
+    let (response_buf, size, src, recv_timestamp) =
+        recv_matching_response(socket, &mut context, &send_req_result).await?;
+    let result = process_datagram(
+        dest,
+        src,
+        response_buf,
+        size,
+        &mut context,
+        send_req_result,
+        Some(recv_timestamp),
+    )?;
+    let mut packet = NtpPacket::from(response_buf);
+
+    convert_from_network(&mut packet);
+
+    Ok(NtpResponse::new(&packet, result))
+}
+
+/// Send an SNTP request using a socket that requires mutable access
+///
+/// The function behaves the same way as [`sntp_send_request`], but accepts sockets
+/// implementing [`NtpUdpSocketMut`].
+///
+/// # Errors
+///
+/// This function returns an `Err` in the same situations as [`sntp_send_request`].
+pub async fn sntp_send_request_mut<U, T>(
+    dest: net::SocketAddr,
+    socket: &mut U,
+    context: NtpContext<T>,
+) -> Result<SendRequestResult>
+where
+    U: NtpUdpSocketMut + ?Sized,
+    T: NtpTimestampGenerator + Copy,
+{
+    sntp_send_request(dest, &MutSocket::new(socket), context).await
+}
+
+/// Process the SNTP response using a socket that requires mutable access
+///
+/// The function behaves the same way as [`sntp_process_response`], but accepts sockets
+/// implementing [`NtpUdpSocketMut`].
+///
+/// # Errors
+///
+/// This function returns an `Err` in the same situations as [`sntp_process_response`].
+pub async fn sntp_process_response_mut<U, T>(
+    dest: net::SocketAddr,
+    socket: &mut U,
+    context: NtpContext<T>,
+    send_req_result: SendRequestResult,
+) -> Result<NtpResult>
+where
+    U: NtpUdpSocketMut + ?Sized,
+    T: NtpTimestampGenerator,
+{
+    sntp_process_response(
+        dest,
+        &MutSocket::new(socket),
+        context,
+        send_req_result,
+    )
+    .await
+}
+
+/// Process the SNTP response with the receive timestamp (T4) provided by the caller
+///
+/// The function behaves the same way as [`sntp_process_response`], except it does not stamp
+/// the moment of the response reception with the context's timestamp generator. Instead, the
+/// caller supplies the receive timestamp, so more precise sources like kernel or hardware RX
+/// timestamps can be used for the offset and roundtrip calculation.
+///
+/// # Arguments
+///
+/// * `dest` - The expected socket address (`SocketAddr`) of the NTP server.
+/// * `socket` - A reference to an object implementing the [`NtpRecvSocket`] trait
+///   used for receiving the response.
+/// * `context` - An SNTP context (`NtpContext<T>`) used for the response validation.
+/// * `send_req_result` - The result of the previously sent request, containing the originate timestamp
+///   of the SNTP request.
+/// * `recv_timestamp_ntp` - The moment the response has been received in the NTP timestamp format:
+///   seconds since the NTP epoch in the upper 32 bits and the fraction of a second in the lower 32 bits.
+///
+/// # Errors
+///
+/// This function returns an `Err` in the same situations as [`sntp_process_response`].
+pub async fn sntp_process_response_with_recv_ts<U, T>(
+    dest: net::SocketAddr,
+    socket: &U,
+    mut context: NtpContext<T>,
+    send_req_result: SendRequestResult,
+    recv_timestamp_ntp: u64,
+) -> Result<NtpResult>
+where
+    U: NtpRecvSocket,
+    T: NtpTimestampGenerator,
+{
+    receive_response(
+        dest,
+        socket,
+        &mut context,
+        send_req_result,
+        Some(recv_timestamp_ntp),
+    )
+    .await
+}
+
+async fn receive_response<U, T>(
+    dest: net::SocketAddr,
+    socket: &U,
+    context: &mut NtpContext<T>,
+    send_req_result: SendRequestResult,
+    recv_timestamp: Option<u64>,
+) -> Result<NtpResult>
+where
+    U: NtpRecvSocket + ?Sized,
+    T: NtpTimestampGenerator,
+{
+    let (response_buf, response, src, received_at) =
+        recv_matching_response(socket, context, &send_req_result).await?;
+
+    process_datagram(
+        dest,
+        src,
+        response_buf,
+        response,
+        context,
+        send_req_result,
+        Some(recv_timestamp.unwrap_or(received_at)),
+    )
+}
+
+/// Process a datagram of `size` bytes received from `src` with the NTP header in `response_buf`
+fn process_datagram<T: NtpTimestampGenerator>(
+    dest: net::SocketAddr,
+    src: net::SocketAddr,
+    response_buf: RawNtpPacket,
+    size: usize,
+    context: &mut NtpContext<T>,
+    send_req_result: SendRequestResult,
+    recv_timestamp: Option<u64>,
+) -> Result<NtpResult> {
+    let recv_timestamp = recv_timestamp.unwrap_or_else(|| {
+        context.timestamp_gen.init();
+        ntp_timestamp_from_gen(&context.timestamp_gen)
+    });
+    #[cfg(any(feature = "log", feature = "defmt"))]
+    debug!("Response: {}", size);
+
+    if context.verify_address && dest != src {
+        return Err(Error::ResponseAddressMismatch);
+    }
+
+    if size < size_of::<NtpPacket>() {
+        return Err(Error::IncorrectPayload);
+    }
+
+    process_received_response(
+        src,
+        send_req_result,
+        response_buf,
+        recv_timestamp,
+        context,
+    )
+}
+
+/// Process an SNTP response received outside of the library
+///
+/// Some platforms hand received datagrams over in a driver callback, so there is no socket to
+/// pass to [`sntp_process_response`]. The function performs the same validation of the
+/// datagram `buf` received from `src`, i.e. the source address, payload size, headers and
+/// originate timestamp checks. The response is stamped as received with the context's
+/// timestamp generator, see [`sntp_process_buffer_with_recv_ts`] to provide the receive
+/// timestamp captured by the driver instead.
+///
+/// # Arguments
+///
+/// * `dest` - The address of the NTP server the request has been sent to.
+/// * `src` - The address the datagram has been received from.
+/// * `buf` - The received datagram.
+/// * `context` - An SNTP context (`NtpContext<T>`) used for the response validation.
+/// * `send_req_result` - The result of the previously sent request, containing the originate timestamp
+///   of the SNTP request.
+///
+/// # Errors
+///
+/// This function returns an `Err` in the same situations as [`sntp_process_response`], except
+/// for socket errors.
+pub fn sntp_process_buffer<T: NtpTimestampGenerator>(
+    dest: net::SocketAddr,
+    src: net::SocketAddr,
+    buf: &[u8],
+    mut context: NtpContext<T>,
+    send_req_result: SendRequestResult,
+) -> Result<NtpResult> {
+    process_buffer(dest, src, buf, &mut context, send_req_result, None)
+}
+
+/// Process an SNTP response received outside of the library with the receive timestamp (T4)
+/// provided by the caller
+///
+/// The function behaves the same way as [`sntp_process_buffer`], but uses `recv_timestamp_ntp`
+/// as the moment of the response reception. The timestamp is in the NTP format: seconds since
+/// the NTP epoch in the upper 32 bits and the fraction of a second in the lower 32 bits.
+///
+/// # Errors
+///
+/// This function returns an `Err` in the same situations as [`sntp_process_buffer`].
+pub fn sntp_process_buffer_with_recv_ts<T: NtpTimestampGenerator>(
+    dest: net::SocketAddr,
+    src: net::SocketAddr,
+    buf: &[u8],
+    mut context: NtpContext<T>,
+    send_req_result: SendRequestResult,
+    recv_timestamp_ntp: u64,
+) -> Result<NtpResult> {
+    process_buffer(
+        dest,
+        src,
+        buf,
+        &mut context,
+        send_req_result,
+        Some(recv_timestamp_ntp),
+    )
+}
+
+fn process_buffer<T: NtpTimestampGenerator>(
+    dest: net::SocketAddr,
+    src: net::SocketAddr,
+    buf: &[u8],
+    context: &mut NtpContext<T>,
+    send_req_result: SendRequestResult,
+    recv_timestamp: Option<u64>,
+) -> Result<NtpResult> {
+    let mut response_buf = RawNtpPacket::default();
+
+    if let Some(header) = buf.first_chunk() {
+        response_buf.0 = *header;
+    }
+
+    process_datagram(
+        dest,
+        src,
+        response_buf,
+        buf.len(),
+        context,
+        send_req_result,
+        recv_timestamp,
+    )
+}
+
+/// Receive a response to any of the requests tracked by [`InFlight`] and process it
+///
+/// The function allows to query several NTP servers concurrently over a single socket. Each
+/// response is correlated with the server the request has been sent to by its originate
+/// timestamp, so responses may arrive in any order. The matched request is removed from
+/// `in_flight`.
+///
+/// # Arguments
+///
+/// * `socket` - A reference to an object implementing the [`NtpRecvSocket`] trait
+///   used for receiving the response.
+/// * `context` - An SNTP context (`NtpContext<T>`) containing a timestamp generator.
+/// * `in_flight` - Tracker of the requests sent with [`sntp_send_request`].
+///
+/// # Returns
+///
+/// Returns the address of the server that sent the response along with the processed result.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use sntpc::{net::SocketAddr, InFlight, NtpContext, NtpTimestampGenerator, NtpUdpSocket, Result};
+/// # async fn query<U: NtpUdpSocket, T: NtpTimestampGenerator + Copy>(
+/// #     socket: &U,
+/// #     context: NtpContext<T>,
+/// #     servers: [SocketAddr; 2],
+/// # ) -> Result<()> {
+/// let mut in_flight = InFlight::<2>::new();
+///
+/// for server in servers {
+///     let request = sntpc::sntp_send_request(server, socket, context).await?;
+///     assert!(in_flight.insert(server, request));
+/// }
+///
+/// while !in_flight.is_empty() {
+///     let (server, result) =
+///         sntpc::sntp_process_in_flight_response(socket, context, &mut in_flight).await?;
+///     println!("{server}: {result}");
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// This function returns an `Err` in any of the following situations:
+/// * The response does not match any request in flight by the originate timestamp.
+/// * The source address of the response does not match the server address the matched request
+///   has been sent to. The request is kept in `in_flight` in that case.
+/// * The size of the response is incorrect or the response is invalid.
+pub async fn sntp_process_in_flight_response<U, T, const N: usize>(
+    socket: &U,
+    mut context: NtpContext<T>,
+    in_flight: &mut InFlight<N>,
+) -> Result<(net::SocketAddr, NtpResult)>
+where
+    U: NtpRecvSocket,
+    T: NtpTimestampGenerator,
+{
+    let timeout = context.recv_timeout;
+    let (response_buf, response, src, recv_timestamp) =
+        recv_response(socket, &mut context, timeout).await?;
+    #[cfg(any(feature = "log", feature = "defmt"))]
+    debug!("Response: {} from {}", response, src);
+
+    if response < size_of::<NtpPacket>() {
+        return Err(Error::IncorrectPayload);
+    }
+
+    let mut packet = NtpPacket::from(response_buf);
+    convert_from_network(&mut packet);
+
+    match in_flight.get(packet.origin_timestamp) {
+        Some(addr) if addr == src || !context.verify_address => {}
+        Some(_) => return Err(Error::ResponseAddressMismatch),
+        None => return Err(Error::IncorrectOriginTimestamp),
+    }
+
+    let (_, send_req_result) = in_flight
+        .remove(packet.origin_timestamp)
+        .ok_or(Error::IncorrectOriginTimestamp)?;
+
+    process_received_response(
+        src,
+        send_req_result,
+        response_buf,
+        recv_timestamp,
+        &context,
+    )
+    .map(|result| (src, result))
+}
+
+/// Receive an unsolicited packet from an NTP server operating in broadcast mode
+///
+/// Some networks run NTP servers that periodically broadcast (or multicast) the time instead
+/// of answering requests. The function waits for a single broadcast packet (mode 5) on
+/// `socket`, which should be bound to the port the server broadcasts to, usually `123`.
+/// The socket is used as is, so the caller is responsible for joining the multicast group
+/// the server sends to, e.g. the NTP group `224.0.1.1` with `UdpSocket::join_multicast_v4`.
+///
+/// There is no request to match the packet against, so the origin timestamp is not checked
+/// and the roundtrip delay is reported as `0`. The offset is calculated as the difference
+/// between the server transmit timestamp and the receive timestamp, so it includes the
+/// one-way network delay.
+///
+/// # Arguments
+///
+/// * `socket` - A reference to an object implementing the [`NtpRecvSocket`] trait.
+/// * `context` - An SNTP context containing a timestamp generator. The packet version is
+///   validated against the version and accepted versions of the context.
+///
+/// # Errors
+///
+/// Returns [`Error::IncorrectMode`] if the received packet is not a broadcast packet, or
+/// other errors if the packet is malformed or the socket fails.
+pub async fn listen_broadcast<U, T>(
+    socket: &U,
+    context: NtpContext<T>,
+) -> Result<NtpResult>
+where
+    U: NtpRecvSocket,
+    T: NtpTimestampGenerator,
+{
+    receive_broadcast(socket, context, None).await
+}
+
+/// Receive an unsolicited broadcast packet from one of the allowed NTP servers
+///
+/// The function behaves the same way as [`listen_broadcast`], but accepts packets only from
+/// the servers in `allowed`. Broadcast packets can not be matched against a request, so the
+/// allowlist is the only protection against spoofed packets sent by other hosts of the
+/// network.
+///
+/// # Errors
+///
+/// Returns [`Error::ResponseAddressMismatch`] if the packet has been received from an address
+/// that is not in `allowed`, or the same errors as [`listen_broadcast`] otherwise.
+pub async fn sntp_listen_broadcast_from<U, T>(
+    socket: &U,
+    context: NtpContext<T>,
+    allowed: &[net::IpAddr],
+) -> Result<NtpResult>
+where
+    U: NtpRecvSocket,
+    T: NtpTimestampGenerator,
+{
+    receive_broadcast(socket, context, Some(allowed)).await
+}
+
+async fn receive_broadcast<U, T>(
+    socket: &U,
+    mut context: NtpContext<T>,
+    allowed: Option<&[net::IpAddr]>,
+) -> Result<NtpResult>
+where
+    U: NtpRecvSocket,
+    T: NtpTimestampGenerator,
+{
+    let timeout = context.recv_timeout;
+    let (response_buf, response, src, recv_timestamp) =
+        recv_response(socket, &mut context, timeout).await?;
+    #[cfg(any(feature = "log", feature = "defmt"))]
+    debug!("Broadcast: {} from {}", response, src);
+
+    if allowed.is_some_and(|allowed| !allowed.contains(&src.ip())) {
+        return Err(Error::ResponseAddressMismatch);
+    }
+
+    if response < size_of::<NtpPacket>() {
+        return Err(Error::IncorrectPayload);
+    }
+
+    let mut result = process_broadcast(response_buf, recv_timestamp, &context)?;
+    adjust_ref_id(src, &mut result);
+    result.source = Some(src);
+
+    Ok(result)
+}
+
+/// Size of the receive buffer, enough for the NTP header followed by a MAC or extension fields
+const RESPONSE_BUF_SIZE: usize = 128;
+
+/// Maximum number of datagrams with a mismatched originate timestamp dropped while waiting for
+/// the response, bounds the time spent on a socket that is flooded with traffic
+const MAX_STALE_RESPONSES: usize = 8;
+
+/// Receive the response to the request `send_req_result` along with the moment of its reception
+///
+/// Datagrams with another originate timestamp, e.g. late responses to cancelled requests or
+/// responses to requests of other tasks sharing the socket, are dropped and the receive goes
+/// on until the context receive timeout expires. The time is measured with the context's
+/// timestamp generator, so the socket configuration is left intact.
+async fn recv_matching_response<U, T>(
+    socket: &U,
+    context: &mut NtpContext<T>,
+    send_req_result: &SendRequestResult,
+) -> Result<(RawNtpPacket, usize, net::SocketAddr, u64)>
+where
+    U: NtpRecvSocket + ?Sized,
+    T: NtpTimestampGenerator,
+{
+    context.timestamp_gen.init();
+    let start = ntp_timestamp_from_gen(&context.timestamp_gen);
+    let mut timeout = context.recv_timeout;
+
+    for _ in 0..MAX_STALE_RESPONSES {
+        let (response_buf, size, src, recv_timestamp) =
+            recv_response(socket, context, timeout).await?;
+
+        if size < size_of::<NtpPacket>()
+            || origin_timestamp(response_buf)
+                == send_req_result.originate_timestamp
+        {
+            return Ok((response_buf, size, src, recv_timestamp));
+        }
+
+        #[cfg(any(feature = "log", feature = "defmt"))]
+        debug!(
+            "Dropped datagram with mismatched origin timestamp from {}",
+            src
+        );
+
+        if let Some(recv_timeout) = context.recv_timeout {
+            let remaining = recv_timeout
+                .checked_sub(request_age(start, recv_timestamp))
+                .filter(|remaining| !remaining.is_zero())
+                .ok_or(Error::Timeout)?;
+            timeout = Some(remaining);
+        }
+    }
+
+    Err(Error::IncorrectOriginTimestamp)
+}
+
+/// Receive a datagram, stamp the moment of its reception and copy the NTP header out of it
+///
+/// Servers may append extension fields or a MAC to the header. They are not used by SNTP
+/// clients, so everything past the first 48 bytes is ignored. The returned size is the size of
+/// the whole datagram. The inspector is invoked after the receive timestamp is taken, so its
+/// run time is not included in the roundtrip and offset.
+async fn recv_response<U, T>(
+    socket: &U,
+    context: &mut NtpContext<T>,
+    timeout: Option<core::time::Duration>,
+) -> Result<(RawNtpPacket, usize, net::SocketAddr, u64)>
+where
+    U: NtpRecvSocket + ?Sized,
+    T: NtpTimestampGenerator,
+{
+    let mut buf = [0u8; RESPONSE_BUF_SIZE];
+    let (size, src) = match timeout {
+        Some(timeout) => socket.recv_from_timeout(&mut buf, timeout).await?,
+        None => socket.recv_from(&mut buf).await?,
+    };
+    context.timestamp_gen.init();
+    let recv_timestamp = ntp_timestamp_from_gen(&context.timestamp_gen);
+
+    if let Some(inspector) = context.inspector {
+        inspector(
+            PacketDirection::Received,
+            &buf[..size.min(RESPONSE_BUF_SIZE)],
+            src,
+        );
+    }
+
+    let mut response_buf = RawNtpPacket::default();
+
+    response_buf
+        .0
+        .copy_from_slice(&buf[..size_of::<NtpPacket>()]);
+
+    Ok((response_buf, size, src, recv_timestamp))
+}
+
+fn process_received_response<T: NtpTimestampGenerator>(
+    src: net::SocketAddr,
+    send_req_result: SendRequestResult,
+    response_buf: RawNtpPacket,
+    recv_timestamp: u64,
+    context: &NtpContext<T>,
+) -> Result<NtpResult> {
+    let mut result = process_response(
+        send_req_result,
+        response_buf,
+        recv_timestamp,
+        context,
+    );
+
+    if let Ok(r) = &mut result {
+        adjust_ref_id(src, r);
+        r.source = Some(src);
+        #[cfg(any(feature = "log", feature = "defmt"))]
+        debug!("{:?}", r);
+    }
+
+    result
+}
+
+fn build_request<T: NtpTimestampGenerator>(
+    context: NtpContext<T>,
+) -> NtpPacket {
+    let mut request = NtpPacket::new(context.timestamp_gen, context.version);
+    request.ref_id = context.ref_id;
+
+    request
+}
+
+/// Reference identifiers of IPv6 servers are hashes of the address rather than IPv4 addresses
+fn adjust_ref_id(src: net::SocketAddr, result: &mut NtpResult) {
+    if let (
+        RefId::Ipv4(addr) | RefId::LocalClock(addr),
+        net::SocketAddr::V6(_),
+    ) = (result.ref_id, src)
+    {
+        result.ref_id = RefId::Ipv6Hash(addr.octets());
+    }
+
+    #[cfg(any(feature = "log", feature = "defmt"))]
+    if let RefId::LocalClock(addr) = result.ref_id {
+        debug!("Server is synchronized to a local clock {:?}", addr);
+    }
+}
+
+#[cfg_attr(
+    not(any(feature = "log", feature = "defmt")),
+    allow(unused_variables)
+)]
+async fn send_request<U>(
+    dest: net::SocketAddr,
+    req: &NtpPacket,
+    socket: &U,
+    inspector: Option<PacketInspector>,
+) -> Result<()>
+where
+    U: NtpSendSocket + ?Sized,
+{
+    let buf = RawNtpPacket::from(req);
+    let sent = socket.send_to(&buf.0, dest).await;
+
+    // invoked after the send, so the run time is not included in the roundtrip and offset
+    if let Some(inspector) = inspector {
+        inspector(PacketDirection::Sent, &buf.0, dest);
+    }
+
+    match sent {
+        Ok(size) if size == buf.0.len() => Ok(()),
+        Ok(0) => {
+            #[cfg(any(feature = "log", feature = "defmt"))]
+            debug!("No data sent to {:?}, socket may be disconnected", dest);
+            Err(Error::PartialSend)
+        }
+        Ok(size) => {
+            #[cfg(any(feature = "log", feature = "defmt"))]
+            debug!("Only {} bytes of request sent to {:?}", size, dest);
+            Err(Error::PartialSend)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Synchronous interface for the SNTP client
+#[cfg(any(feature = "sync", feature = "minimal-sync"))]
+pub mod sync {
+    #[cfg(any(feature = "log", feature = "defmt"))]
+    use crate::log::debug;
+    use crate::net;
+    use crate::resolver::{NtpHostResolver, NtpSrvResolver};
+    use crate::types::{
+        NtpContext, NtpRecvSocket, NtpResponse, NtpResult, NtpSendSocket,
+        NtpTimestampGenerator, NtpUdpSocket, NtpUdpSocketMut, Result,
+        SendRequestResult,
+    };
+    use crate::{KodCache, RateLimiter};
+
+    #[cfg(not(feature = "sync"))]
+    use crate::executor::Executor;
+    #[cfg(feature = "sync")]
+    use miniloop::executor::Executor;
+    /// Send request to a NTP server with the given address and process the response in a single call
+    ///
+    /// May be useful under an environment with `std` networking implementation, where all
+    /// network stuff is hidden within system's kernel. For environment with custom
+    /// Uses [`NtpUdpSocket`] and [`NtpTimestampGenerator`] trait bounds to allow generic specification
+    /// of objects that can be used with the library
+    ///
+    /// # Arguments
+    ///
+    /// - `pool_addrs` - Server's name or IP address with port specification as a string
+    /// - `socket` - UDP socket object that will be used during NTP request-response
+    ///   communication
+    /// - `context` - SNTP client context to provide timestamp generation feature
+    ///
+    /// The call blocks until a response is received, so a timeout has to be set on the socket
+    /// before the call, e.g. with `UdpSocket::set_read_timeout`. Sockets that implement
+    /// [`NtpUdpSocket::recv_from_timeout`] may be bounded with
+    /// [`NtpContext::with_recv_timeout`] instead.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an SNTP request cannot be sent or SNTP response fails
+    pub fn get_time<U, T>(
+        addr: net::SocketAddr,
+        socket: &U,
+        context: NtpContext<T>,
+    ) -> Result<NtpResult>
+    where
+        U: NtpUdpSocket + ?Sized,
+        T: NtpTimestampGenerator + Copy,
+    {
+        let result = sntp_send_request(addr, socket, context)?;
+        #[cfg(any(feature = "log", feature = "defmt"))]
+        debug!("{:?}", result);
+
+        sntp_process_response(addr, socket, context, result)
+    }
+
+    /// Retrieve the current time from an NTP server along with every field of the response
+    ///
+    /// This is a synchronous wrapper around [`crate::get_time_raw`]
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` in the same cases as [`crate::get_time_raw`]
+    pub fn get_time_raw<U, T>(
+        addr: net::SocketAddr,
+        socket: &U,
+        context: NtpContext<T>,
+    ) -> Result<NtpResponse>
+    where
+        U: NtpUdpSocket + ?Sized,
+        T: NtpTimestampGenerator + Copy,
+    {
+        Executor::new().block_on(crate::get_time_raw(addr, socket, context))
+    }
+
+    /// Retrieve the current time from an NTP server using a socket that requires mutable access
+    ///
+    /// This is a synchronous wrapper around [`crate::get_time_mut`]
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` in the same cases as [`crate::get_time_mut`]
+    pub fn get_time_mut<U, T>(
+        addr: net::SocketAddr,
+        socket: &mut U,
+        context: NtpContext<T>,
+    ) -> Result<NtpResult>
+    where
+        U: NtpUdpSocketMut + ?Sized,
+        T: NtpTimestampGenerator + Copy,
+    {
+        Executor::new().block_on(crate::get_time_mut(addr, socket, context))
+    }
+
+    /// Retrieve the current time from an NTP server driving a cooperative network stack in
+    /// between
+    ///
+    /// This is a synchronous wrapper around [`crate::get_time_with_poll`]
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` in the same cases as [`crate::get_time_with_poll`]
+    pub fn get_time_with_poll<U, T, F>(
+        addr: net::SocketAddr,
+        socket: &U,
+        context: NtpContext<T>,
+        poll: F,
+    ) -> Result<NtpResult>
+    where
+        U: NtpUdpSocket + ?Sized,
+        T: NtpTimestampGenerator + Copy,
+        F: FnMut(),
+    {
+        Executor::new()
+            .block_on(crate::get_time_with_poll(addr, socket, context, poll))
+    }
+
+    /// Retrieve the current time from an NTP server with a burst of requests
+    ///
+    /// This is a synchronous wrapper around [`crate::get_time_burst`]
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` in the same cases as [`crate::get_time_burst`]
+    pub fn get_time_burst<U, T>(
+        addr: net::SocketAddr,
+        socket: &U,
+        context: NtpContext<T>,
+        count: u32,
+        max_spread: Option<u64>,
+    ) -> Result<NtpResult>
+    where
+        U: NtpUdpSocket + ?Sized,
+        T: NtpTimestampGenerator + Copy,
+    {
+        Executor::new().block_on(crate::get_time_burst(
+            addr, socket, context, count, max_spread,
+        ))
+    }
+
+    /// Retrieve the current time from an NTP server retrying failed requests
+    ///
+    /// This is a synchronous wrapper around [`crate::get_time_with_retry`]
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` in the same cases as [`crate::get_time_with_retry`]
+    pub fn get_time_with_retry<U, T>(
+        addr: net::SocketAddr,
+        socket: &U,
+        context: NtpContext<T>,
+        max_attempts: u32,
+    ) -> Result<NtpResult>
+    where
+        U: NtpUdpSocket,
+        T: NtpTimestampGenerator + Copy,
+    {
+        Executor::new().block_on(crate::get_time_with_retry(
+            addr,
+            socket,
+            context,
+            max_attempts,
+        ))
+    }
+
+    /// Retrieve the current time from the first NTP server in the list that provides a valid
+    /// response
+    ///
+    /// This is a synchronous wrapper around [`crate::get_time_any`]
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` in the same cases as [`crate::get_time_any`]
+    pub fn get_time_any<U, T>(
+        addrs: &[net::SocketAddr],
+        socket: &U,
+        context: NtpContext<T>,
+    ) -> Result<NtpResult>
+    where
+        U: NtpUdpSocket,
+        T: NtpTimestampGenerator + Copy,
+    {
+        Executor::new().block_on(crate::get_time_any(addrs, socket, context))
+    }
+
+    /// Retrieve the current time from an NTP server with the given host name
+    ///
+    /// This is a synchronous wrapper around [`crate::get_time_by_name`]
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` in the same cases as [`crate::get_time_by_name`]
+    pub fn get_time_by_name<R, U, T>(
+        host: &str,
+        resolver: &R,
+        socket: &U,
+        context: NtpContext<T>,
+    ) -> Result<NtpResult>
+    where
+        R: NtpHostResolver,
+        U: NtpUdpSocket,
+        T: NtpTimestampGenerator + Copy,
+    {
+        Executor::new()
+            .block_on(crate::get_time_by_name(host, resolver, socket, context))
+    }
+
+    /// Receive an unsolicited packet from an NTP server operating in broadcast mode
+    ///
+    /// This is a synchronous wrapper around [`crate::listen_broadcast`]
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` in the same cases as [`crate::listen_broadcast`]
+    pub fn listen_broadcast<U, T>(
+        socket: &U,
+        context: NtpContext<T>,
+    ) -> Result<NtpResult>
+    where
+        U: NtpRecvSocket,
+        T: NtpTimestampGenerator,
+    {
+        Executor::new().block_on(crate::listen_broadcast(socket, context))
+    }
+
+    /// Receive an unsolicited broadcast packet from one of the allowed NTP servers
+    ///
+    /// This is a synchronous wrapper around [`crate::sntp_listen_broadcast_from`]
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` in the same cases as [`crate::sntp_listen_broadcast_from`]
+    pub fn sntp_listen_broadcast_from<U, T>(
+        socket: &U,
+        context: NtpContext<T>,
+        allowed: &[net::IpAddr],
+    ) -> Result<NtpResult>
+    where
+        U: NtpRecvSocket,
+        T: NtpTimestampGenerator,
+    {
+        Executor::new().block_on(crate::sntp_listen_broadcast_from(
+            socket, context, allowed,
+        ))
+    }
+
+    /// Retrieve the current time from an NTP server given as [`crate::ServerAddr`]
+    ///
+    /// This is a synchronous wrapper around [`crate::get_time_by_server`]
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` in the same cases as [`crate::get_time_by_server`]
+    #[cfg(feature = "std")]
+    pub fn get_time_by_server<U, T>(
+        server: &crate::ServerAddr,
+        socket: &U,
+        context: NtpContext<T>,
+    ) -> Result<NtpResult>
+    where
+        U: NtpUdpSocket,
+        T: NtpTimestampGenerator + Copy,
+    {
+        Executor::new()
+            .block_on(crate::get_time_by_server(server, socket, context))
+    }
+
+    /// Retrieve the current time from NTP servers published in DNS SRV records of a domain
+    ///
+    /// This is a synchronous wrapper around [`crate::get_time_by_srv`]
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` in the same cases as [`crate::get_time_by_srv`]
+    pub fn get_time_by_srv<R, U, T>(
+        domain: &str,
+        resolver: &R,
+        socket: &U,
+        context: NtpContext<T>,
+    ) -> Result<NtpResult>
+    where
+        R: NtpSrvResolver,
+        U: NtpUdpSocket,
+        T: NtpTimestampGenerator + Copy,
+    {
+        Executor::new()
+            .block_on(crate::get_time_by_srv(domain, resolver, socket, context))
+    }
+
+    /// Retrieve the current time from the first NTP server in the list that responds
+    ///
+    /// This is a synchronous wrapper around [`crate::get_time_multi`]. Servers are queried
+    /// in order with the socket's read timeout in effect, servers that time out or fail with
+    /// a network error are skipped.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use sntpc::{NtpContext, NtpTimestampGenerator};
+    ///
+    /// use std::net::{ToSocketAddrs, UdpSocket};
+    /// use std::time::Duration;
+    /// # #[derive(Copy, Clone, Default)]
+    /// # struct Timestamp;
+    /// # impl NtpTimestampGenerator for Timestamp {
+    /// #     fn init(&mut self) {}
+    /// #     fn timestamp_sec(&self) -> u64 { 0 }
+    /// #     fn timestamp_subsec_micros(&self) -> u32 { 0 }
+    /// # }
+    ///
+    /// let socket = UdpSocket::bind("0.0.0.0:0").unwrap();
+    /// socket.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+    /// let addrs: Vec<_> = "pool.ntp.org:123".to_socket_addrs().unwrap().collect();
+    /// let context = NtpContext::new(Timestamp::default());
+    ///
+    /// match sntpc::sync::get_time_multi(&addrs, &socket, context) {
+    ///     Ok((addr, result)) => println!("{addr} answered: {result}"),
+    ///     Err(e) => eprintln!("No server answered: {e}"),
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` in the same cases as [`crate::get_time_multi`]
+    pub fn get_time_multi<U, T>(
+        addrs: &[net::SocketAddr],
+        socket: &U,
+        context: NtpContext<T>,
+    ) -> Result<(net::SocketAddr, NtpResult)>
+    where
+        U: NtpUdpSocket,
+        T: NtpTimestampGenerator + Copy,
+    {
+        Executor::new().block_on(crate::get_time_multi(addrs, socket, context))
+    }
+
+    /// Retrieve the current time from an NTP server unless the server sent a Kiss-o'-Death
+    /// response earlier
+    ///
+    /// This is a synchronous wrapper around [`crate::get_time_with_kod_cache`]
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` in the same cases as [`crate::get_time_with_kod_cache`]
+    pub fn get_time_with_kod_cache<U, T, const N: usize>(
+        addr: net::SocketAddr,
+        socket: &U,
+        context: NtpContext<T>,
+        cache: &mut KodCache<N>,
+    ) -> Result<NtpResult>
+    where
+        U: NtpUdpSocket,
+        T: NtpTimestampGenerator + Copy,
+    {
+        Executor::new().block_on(crate::get_time_with_kod_cache(
+            addr, socket, context, cache,
+        ))
+    }
+
+    /// Retrieve the current time from an NTP server unless the server has been queried too recently
+    ///
+    /// This is a synchronous wrapper around [`crate::get_time_rate_limited`]
+    ///
+    /// # Errors
+    ///
+    /// Will return [`crate::Error::RateLimited`] if the server has been queried too recently,
+    /// or `Err` if an SNTP request cannot be sent or SNTP response fails
+    pub fn get_time_rate_limited<U, T, const N: usize>(
+        addr: net::SocketAddr,
+        socket: &U,
+        context: NtpContext<T>,
+        limiter: &mut RateLimiter<N>,
+    ) -> Result<NtpResult>
+    where
+        U: NtpUdpSocket,
+        T: NtpTimestampGenerator + Copy,
+    {
+        Executor::new().block_on(crate::get_time_rate_limited(
+            addr, socket, context, limiter,
+        ))
+    }
+
+    /// Send an SNTP request to the specified destination synchronously.
+    ///
+    /// This function is a synchronous wrapper for the asynchronous [`crate::sntp_send_request`].
+    /// It uses an executor to block the current thread while waiting for the underlying
+    /// asynchronous operation to complete.
+    ///
+    /// # Arguments
+    ///
+    /// * `dest` - The destination NTP server's socket address to send the request to.
+    /// * `socket` - A reference to an object implementing the [`NtpSendSocket`] trait to send data.
+    /// * `context` - The SNTP client context (implementing [`NtpTimestampGenerator`]) that
+    ///   assists in generating timestamps for the request.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Err` if the underlying async SNTP request fails for any reason,
     /// such as network failure, invalid server response, or timeout.
     ///
     /// # Examples
     ///
     /// ```
-    /// use sntpc::{self, NtpContext, NtpTimestampGenerator, Result};
+    /// use sntpc::{self, NtpContext, NtpTimestampGenerator, Result};
+    /// # use core::future::Future;
+    /// # use std::time::Duration;
+    /// # use std::str::FromStr;
+    /// # #[cfg(feature = "std")]
+    /// # use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+    /// # #[derive(Debug)]
+    /// # struct UdpSocketWrapper(UdpSocket);
+    /// #
+    /// # impl sntpc::NtpUdpSocket for UdpSocketWrapper {
+    /// #     async fn send_to(
+    /// #         &self,
+    /// #         buf: &[u8],
+    /// #         addr: SocketAddr,
+    /// #     ) -> Result<usize> {
+    /// #         self.0.send_to(buf, addr).map_err(|_| sntpc::Error::Network)
+    /// #     }
+    /// #
+    /// #     async fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddr)> {
+    /// #         self.0.recv_from(buf).map_err(|_| sntpc::Error::Network)
+    /// #     }
+    /// # }
+    /// # #[derive(Copy, Clone, Default)]
+    /// # struct StdTimestampGen {
+    /// #     duration: Duration,
+    /// # }
+    /// #
+    /// # impl NtpTimestampGenerator for StdTimestampGen {
+    /// #     fn init(&mut self) {
+    /// #         self.duration = std::time::SystemTime::now()
+    /// #             .duration_since(std::time::SystemTime::UNIX_EPOCH)
+    /// #             .unwrap();
+    /// #     }
+    /// #
+    /// #     fn timestamp_sec(&self) -> u64 {
+    /// #         self.duration.as_secs()
+    /// #     }
+    /// #
+    /// #     fn timestamp_subsec_micros(&self) -> u32 {
+    /// #         self.duration.subsec_micros()
+    /// #     }
+    /// # }
+    /// let socket = UdpSocketWrapper(UdpSocket::bind("0.0.0.0:0").unwrap());
+    /// let context = NtpContext::new(StdTimestampGen::default());
+    /// // "time.google.com:123" string here used for the sake of simplicity. In the real app
+    /// // you would want to fix destination address, since string hostname may resolve to
+    /// // different IP addresses
+    /// let addr = "time.google.com:123".to_socket_addrs().unwrap().next().unwrap();
+    ///
+    /// let result = sntpc::sync::sntp_send_request(addr, &socket, context);
+    /// match result {
+    ///     Ok(response) => println!("Received response: {:?}", response),
+    ///     Err(e) => eprintln!("Failed to send request: {:?}", e),
+    /// }
+    /// ```
+    pub fn sntp_send_request<U, T>(
+        dest: net::SocketAddr,
+        socket: &U,
+        context: NtpContext<T>,
+    ) -> Result<SendRequestResult>
+    where
+        U: NtpSendSocket + ?Sized,
+        T: NtpTimestampGenerator + Copy,
+    {
+        Executor::new()
+            .block_on(crate::sntp_send_request(dest, socket, context))
+    }
+
+    /// Processes the response from an SNTP server and calculates the NTP offset and round-trip delay.
+    ///
+    /// This is a synchronous wrapper around the asynchronous SNTP response processing function.
+    /// It uses an executor to block the current thread while waiting for the underlying
+    /// asynchronous operation to complete.
+    ///
+    /// # Arguments
+    ///
+    /// - `dest` - The destination NTP server's socket address from which the response was received.
+    /// - `socket` - A reference to an object implementing the [`NtpRecvSocket`] trait used for network communication.
+    /// - `context` - The SNTP client context (implementing [`NtpTimestampGenerator`]) responsible for generating and validating timestamps.
+    /// - `send_req_result` - The result obtained from sending the SNTP request, including the originate timestamp.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Err` if the underlying async SNTP response processing fails for any reason,
+    /// such as:
+    /// - Incorrect origin timestamp in the response,
+    /// - A response mode other than the server mode (`SNTP_UNICAST`),
+    /// - A mismatch between the request and response versions,
+    /// - Errors in the response headers (e.g., incorrect stratum, leap indicator),
+    /// - Network errors during processing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sntpc::{self, NtpContext, NtpTimestampGenerator, Result, SendRequestResult};
     /// # use core::future::Future;
     /// # use std::time::Duration;
     /// # use std::str::FromStr;
@@ -720,579 +2695,4260 @@ pub mod sync {
     /// // "time.google.com:123" string here used for the sake of simplicity. In the real app
     /// // you would want to fix destination address, since string hostname may resolve to
     /// // different IP addresses
-    /// let addr = "time.google.com:123".to_socket_addrs().unwrap().next().unwrap();
+    /// let addr = "time.google.com:123".to_socket_addrs().unwrap().filter(SocketAddr::is_ipv4).next().unwrap();
+    /// let send_request_result = sntpc::sync::sntp_send_request(addr, &socket, context).unwrap();
+    /// let result = sntpc::sync::sntp_process_response(addr, &socket, context, send_request_result);
     ///
-    /// let result = sntpc::sync::sntp_send_request(addr, &socket, context);
     /// match result {
-    ///     Ok(response) => println!("Received response: {:?}", response),
-    ///     Err(e) => eprintln!("Failed to send request: {:?}", e),
+    ///     Ok(ntp_result) => println!("NTP Result: {:?}", ntp_result),
+    ///     Err(e) => eprintln!("Failed to process response: {:?}", e),
     /// }
     /// ```
-    pub fn sntp_send_request<U, T>(
+    pub fn sntp_process_response<U, T>(
         dest: net::SocketAddr,
         socket: &U,
         context: NtpContext<T>,
+        send_req_result: SendRequestResult,
+    ) -> Result<NtpResult>
+    where
+        U: NtpRecvSocket + ?Sized,
+        T: NtpTimestampGenerator + Copy,
+    {
+        Executor::new().block_on(crate::sntp_process_response(
+            dest,
+            socket,
+            context,
+            send_req_result,
+        ))
+    }
+
+    /// Send an SNTP request using a socket that requires mutable access
+    ///
+    /// This is a synchronous wrapper around [`crate::sntp_send_request_mut`]
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` in the same situations as [`crate::sntp_send_request_mut`]
+    pub fn sntp_send_request_mut<U, T>(
+        dest: net::SocketAddr,
+        socket: &mut U,
+        context: NtpContext<T>,
     ) -> Result<SendRequestResult>
     where
-        U: NtpUdpSocket,
+        U: NtpUdpSocketMut + ?Sized,
+        T: NtpTimestampGenerator + Copy,
+    {
+        Executor::new()
+            .block_on(crate::sntp_send_request_mut(dest, socket, context))
+    }
+
+    /// Process the SNTP response using a socket that requires mutable access
+    ///
+    /// This is a synchronous wrapper around [`crate::sntp_process_response_mut`]
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` in the same situations as [`crate::sntp_process_response_mut`]
+    pub fn sntp_process_response_mut<U, T>(
+        dest: net::SocketAddr,
+        socket: &mut U,
+        context: NtpContext<T>,
+        send_req_result: SendRequestResult,
+    ) -> Result<NtpResult>
+    where
+        U: NtpUdpSocketMut + ?Sized,
+        T: NtpTimestampGenerator + Copy,
+    {
+        Executor::new().block_on(crate::sntp_process_response_mut(
+            dest,
+            socket,
+            context,
+            send_req_result,
+        ))
+    }
+
+    /// Process the SNTP response with the receive timestamp provided by the caller
+    ///
+    /// This is a synchronous wrapper around [`crate::sntp_process_response_with_recv_ts`]
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` in the same situations as [`crate::sntp_process_response_with_recv_ts`]
+    pub fn sntp_process_response_with_recv_ts<U, T>(
+        dest: net::SocketAddr,
+        socket: &U,
+        context: NtpContext<T>,
+        send_req_result: SendRequestResult,
+        recv_timestamp_ntp: u64,
+    ) -> Result<NtpResult>
+    where
+        U: NtpRecvSocket,
         T: NtpTimestampGenerator + Copy,
     {
+        Executor::new().block_on(crate::sntp_process_response_with_recv_ts(
+            dest,
+            socket,
+            context,
+            send_req_result,
+            recv_timestamp_ntp,
+        ))
+    }
+}
+
+/// SNTP unicast server mode
+const SNTP_UNICAST: u8 = 4;
+/// SNTP broadcast server mode
+const SNTP_BROADCAST: u8 = 5;
+
+fn process_response<T: NtpTimestampGenerator>(
+    send_req_result: SendRequestResult,
+    resp: RawNtpPacket,
+    recv_timestamp: u64,
+    context: &NtpContext<T>,
+) -> Result<NtpResult> {
+    let packet = decode_response(resp, recv_timestamp);
+
+    if send_req_result.originate_timestamp != packet.origin_timestamp {
+        return Err(Error::IncorrectOriginTimestamp);
+    }
+
+    if request_age(send_req_result.originate_timestamp, recv_timestamp)
+        > context.max_request_age
+    {
+        return Err(Error::StaleRequest);
+    }
+
+    let req_version = send_req_result.version;
+    let leap_indicator =
+        validate_response(&packet, SNTP_UNICAST, req_version, context)?;
+    // System clock offset:
+    // theta = T(B) - T(A) = 1/2 * [(T2-T1) + (T3-T4)]
+    // Round-trip delay:
+    // delta = T(ABA) = (T4-T1) - (T3-T2).
+    // where:
+    // - T1 = client's TX timestamp
+    // - T2 = server's RX timestamp
+    // - T3 = server's TX timestamp
+    // - T4 = client's RX timestamp
+    let t1 = packet.origin_timestamp;
+    let t2 = packet.recv_timestamp;
+    let t3 = packet.tx_timestamp;
+    let t4 = recv_timestamp;
+    let units = Units::Microseconds;
+    let roundtrip = roundtrip_calculate(t1, t2, t3, t4, units);
+    let offset = offset_calculate(t1, t2, t3, t4, units);
+    let timestamp = match context.time_source {
+        TimeSource::ServerTx => t3,
+        TimeSource::LocalPlusOffset => {
+            t4.wrapping_add_signed(theta(t1, t2, t3, t4))
+        }
+    };
+
+    #[cfg(any(feature = "log", feature = "defmt"))]
+    debug!(
+        "Roundtrip delay: {} {}. Offset: {} {}",
+        roundtrip, units, offset, units
+    );
+
+    let mut result = build_result(
+        &packet,
+        timestamp,
+        roundtrip,
+        offset,
+        leap_indicator,
+        context.units,
+    );
+    result.originate_timestamp = t1;
+    result.destination_timestamp = t4;
+
+    Ok(result)
+}
+
+/// Time elapsed between the request and the response timestamps. A receive timestamp that
+/// precedes the request, e.g. because the local clock has been stepped back, yields zero
+#[allow(clippy::cast_possible_truncation)]
+fn request_age(
+    originate_timestamp: u64,
+    recv_timestamp: u64,
+) -> core::time::Duration {
+    let age = recv_timestamp.saturating_sub(originate_timestamp);
+
+    core::time::Duration::from_secs(age >> 32)
+        + fraction_to_duration(age as u32)
+}
+
+/// Process an unsolicited broadcast packet received at `recv_timestamp`
+///
+/// There is no request to match the packet against, so the origin timestamp is not checked
+/// and the roundtrip delay is unknown. The offset is the difference between the server
+/// transmit timestamp and the receive timestamp.
+fn process_broadcast<T: NtpTimestampGenerator>(
+    resp: RawNtpPacket,
+    recv_timestamp: u64,
+    context: &NtpContext<T>,
+) -> Result<NtpResult> {
+    let packet = decode_response(resp, recv_timestamp);
+
+    let req_version = context.version & (VERSION_MASK >> VERSION_SHIFT);
+    let leap_indicator =
+        validate_response(&packet, SNTP_BROADCAST, req_version, context)?;
+    // with T1 = T4 and T2 = T3 the offset is reduced to T3 - T4
+    let offset = offset_calculate(
+        recv_timestamp,
+        packet.tx_timestamp,
+        packet.tx_timestamp,
+        recv_timestamp,
+        Units::Microseconds,
+    );
+
+    // T4 + (T3 - T4) is T3, so both time sources report the same time
+    let mut result = build_result(
+        &packet,
+        packet.tx_timestamp,
+        0,
+        offset,
+        leap_indicator,
+        context.units,
+    );
+    result.originate_timestamp = recv_timestamp;
+    result.destination_timestamp = recv_timestamp;
+
+    Ok(result)
+}
+
+/// Originate timestamp of the response in the host byte order
+fn origin_timestamp(resp: RawNtpPacket) -> u64 {
+    let mut packet = NtpPacket::from(resp);
+
+    convert_from_network(&mut packet);
+
+    packet.origin_timestamp
+}
+
+fn decode_response(resp: RawNtpPacket, recv_timestamp: u64) -> NtpPacket {
+    let mut packet = NtpPacket::from(resp);
+
+    convert_from_network(&mut packet);
+
+    cfg_if!(
+        if #[cfg(any(feature = "log", feature = "defmt"))] {
+            let debug_packet = DebugNtpPacket::new(&packet, recv_timestamp);
+            debug!("{:#?}", debug_packet);
+        } else {
+            let _ = recv_timestamp;
+        }
+    );
+
+    packet
+}
+
+/// Validate response headers and return the leap indicator on success
+fn validate_response<T: NtpTimestampGenerator>(
+    packet: &NtpPacket,
+    expected_mode: u8,
+    req_version: u8,
+    context: &NtpContext<T>,
+) -> Result<LeapIndicator> {
+    // Shift is 0
+    let mode = shifter(packet.li_vn_mode, MODE_MASK, MODE_SHIFT);
+    let li = shifter(packet.li_vn_mode, LI_MASK, LI_SHIFT);
+    let resp_version = shifter(packet.li_vn_mode, VERSION_MASK, VERSION_SHIFT);
+
+    if mode != expected_mode {
+        return Err(Error::IncorrectMode);
+    }
+
+    let leap_indicator = LeapIndicator::try_from(li)?;
+
+    if req_version != resp_version
+        && !context.accepted_versions.contains(resp_version)
+    {
+        return Err(Error::IncorrectResponseVersion);
+    }
+
+    if packet.stratum == 0 {
+        #[cfg(any(feature = "log", feature = "defmt"))]
+        debug!("Kiss-o'-Death response: {:?}", packet.ref_id.to_be_bytes());
+        return Err(Error::KissOfDeath(KissCode::from(
+            packet.ref_id.to_be_bytes(),
+        )));
+    }
+
+    if packet.stratum > context.max_stratum {
+        return Err(Error::IncorrectStratumHeaders);
+    }
+
+    if context
+        .client_config
+        .max_root_dispersion
+        .is_some_and(|limit| packet.root_dispersion > limit)
+    {
+        return Err(Error::RootDispersionExceeded);
+    }
+
+    if packet.tx_timestamp == 0 {
+        return Err(Error::InvalidTransmitTimestamp);
+    }
+
+    Ok(leap_indicator)
+}
+
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn build_result(
+    packet: &NtpPacket,
+    timestamp: u64,
+    roundtrip: u64,
+    offset: i64,
+    leap_indicator: LeapIndicator,
+    units: Units,
+) -> NtpResult {
+    let timestamp = NtpTimestamp::from(timestamp);
+    let mut result = NtpResult::new(
+        timestamp.seconds as u32,
+        timestamp.seconds_fraction as u32,
+        roundtrip,
+        offset,
+        packet.stratum,
+        packet.precision,
+    );
+    result.root_delay = ntp_short_to_micros(packet.root_delay);
+    result.root_dispersion = ntp_short_to_micros(packet.root_dispersion);
+    result.poll = packet.poll;
+    result.ref_id = parse_ref_id(packet.ref_id, packet.stratum);
+    result.leap_indicator = leap_indicator;
+    result.units = units;
+
+    result
+}
+
+fn shifter(val: u8, mask: u8, shift: u8) -> u8 {
+    (val & mask) >> shift
+}
+
+fn convert_from_network(packet: &mut NtpPacket) {
+    fn ntohl<T: NtpNum>(val: &T) -> T::Type {
+        val.ntohl()
+    }
+
+    packet.root_delay = ntohl(&packet.root_delay);
+    packet.root_dispersion = ntohl(&packet.root_dispersion);
+    packet.ref_id = ntohl(&packet.ref_id);
+    packet.ref_timestamp = ntohl(&packet.ref_timestamp);
+    packet.origin_timestamp = ntohl(&packet.origin_timestamp);
+    packet.recv_timestamp = ntohl(&packet.recv_timestamp);
+    packet.tx_timestamp = ntohl(&packet.tx_timestamp);
+}
+
+fn convert_delays(sec: u64, fraction: u64, units: u64) -> u64 {
+    sec * units + fraction * units / u64::from(u32::MAX)
+}
+
+fn roundtrip_calculate(
+    t1: u64,
+    t2: u64,
+    t3: u64,
+    t4: u64,
+    units: Units,
+) -> u64 {
+    let delta = t4.wrapping_sub(t1).saturating_sub(t3.wrapping_sub(t2));
+    let delta_sec = (delta & SECONDS_MASK) >> 32;
+    let delta_sec_fraction = delta & SECONDS_FRAC_MASK;
+
+    match units {
+        Units::Milliseconds => convert_delays(
+            delta_sec,
+            delta_sec_fraction,
+            u64::from(MSEC_IN_SEC),
+        ),
+        Units::Microseconds => convert_delays(
+            delta_sec,
+            delta_sec_fraction,
+            u64::from(USEC_IN_SEC),
+        ),
+    }
+}
+
+/// Clock offset in the NTP timestamp format
+#[allow(clippy::cast_possible_wrap)]
+fn theta(t1: u64, t2: u64, t3: u64, t4: u64) -> i64 {
+    (t2.wrapping_sub(t1) as i64 / 2)
+        .saturating_add(t3.wrapping_sub(t4) as i64 / 2)
+}
+
+#[allow(clippy::cast_possible_wrap)]
+fn offset_calculate(t1: u64, t2: u64, t3: u64, t4: u64, units: Units) -> i64 {
+    let theta = theta(t1, t2, t3, t4);
+    let theta_sec = (theta.unsigned_abs() & SECONDS_MASK) >> 32;
+    let theta_sec_fraction = theta.unsigned_abs() & SECONDS_FRAC_MASK;
+
+    match units {
+        Units::Milliseconds => {
+            convert_delays(
+                theta_sec,
+                theta_sec_fraction,
+                u64::from(MSEC_IN_SEC),
+            ) as i64
+                * theta.signum()
+        }
+        Units::Microseconds => {
+            convert_delays(
+                theta_sec,
+                theta_sec_fraction,
+                u64::from(USEC_IN_SEC),
+            ) as i64
+                * theta.signum()
+        }
+    }
+}
+
+/// Convert the current time of the timestamp generator to the NTP timestamp format
+///
+/// The result has seconds since the NTP epoch (1900) in the upper 32 bits and the fraction of a
+/// second in the lower 32 bits. The library stamps the transmit (T1) and receive (T4) moments
+/// the same way, so the function may be used to supply T4 to
+/// [`sntp_process_response_with_recv_ts`] in custom request flows. The generator is not
+/// initialized by the function, call [`NtpTimestampGenerator::init`] beforehand.
+///
+/// # Example
+///
+/// ```rust
+/// use sntpc::{ntp_timestamp_from_gen, NtpTimestampGenerator};
+///
+/// #[derive(Copy, Clone)]
+/// struct Timestamp;
+///
+/// impl NtpTimestampGenerator for Timestamp {
+///     fn init(&mut self) {}
+///     fn timestamp_sec(&self) -> u64 { 0 }
+///     fn timestamp_subsec_micros(&self) -> u32 { 500_000 }
+/// }
+///
+/// let timestamp = ntp_timestamp_from_gen(&Timestamp);
+/// // the UNIX epoch in the NTP era 0 with half a second fraction
+/// assert_eq!(2_208_988_800, timestamp >> 32);
+/// assert_eq!(u32::MAX / 2, timestamp as u32);
+/// ```
+#[must_use]
+pub fn ntp_timestamp_from_gen<T: NtpTimestampGenerator>(
+    timestamp_gen: &T,
+) -> u64 {
+    ((timestamp_gen.timestamp_sec()
+        + (u64::from(NtpPacket::NTP_TIMESTAMP_DELTA)))
+        << 32)
+        + u64::from(timestamp_gen.timestamp_subsec_micros())
+            * u64::from(u32::MAX)
+            / u64::from(USEC_IN_SEC)
+}
+
+/// Current time of the timestamp generator in microseconds since the UNIX epoch
+pub(crate) fn timestamp_micros<T: NtpTimestampGenerator>(
+    timestamp_gen: &mut T,
+) -> u64 {
+    timestamp_gen.init();
+    timestamp_gen
+        .timestamp_sec()
+        .saturating_mul(u64::from(USEC_IN_SEC))
+        .saturating_add(u64::from(timestamp_gen.timestamp_subsec_micros()))
+}
+
+/// Convert second fraction value to milliseconds value
+#[allow(clippy::cast_possible_truncation)]
+#[must_use]
+pub fn fraction_to_milliseconds(sec_fraction: u32) -> u32 {
+    (u64::from(sec_fraction) * u64::from(MSEC_IN_SEC) / u64::from(u32::MAX))
+        as u32
+}
+
+/// Convert second fraction value to microseconds value
+#[allow(clippy::cast_possible_truncation)]
+#[must_use]
+pub fn fraction_to_microseconds(sec_fraction: u32) -> u32 {
+    (u64::from(sec_fraction) * u64::from(USEC_IN_SEC) / u64::from(u32::MAX))
+        as u32
+}
+
+/// Convert second fraction value to nanoseconds value
+#[allow(clippy::cast_possible_truncation)]
+#[must_use]
+pub fn fraction_to_nanoseconds(sec_fraction: u32) -> u32 {
+    (u64::from(sec_fraction) * u64::from(NSEC_IN_SEC) / u64::from(u32::MAX))
+        as u32
+}
+
+/// Convert second fraction value to picoseconds value
+#[allow(clippy::cast_possible_truncation)]
+#[must_use]
+pub fn fraction_to_picoseconds(sec_fraction: u32) -> u64 {
+    (u128::from(sec_fraction) * u128::from(PSEC_IN_SEC) / u128::from(u32::MAX))
+        as u64
+}
+
+/// Convert second fraction value to [`core::time::Duration`] value representing
+/// sub-second part with nanosecond resolution
+#[must_use]
+pub fn fraction_to_duration(sec_fraction: u32) -> core::time::Duration {
+    core::time::Duration::from_nanos(u64::from(fraction_to_nanoseconds(
+        sec_fraction,
+    )))
+}
+
+/// Convert NTP result time to [`core::time::Duration`] value since UNIX epoch
+#[must_use]
+pub fn ntp_result_to_duration(result: &NtpResult) -> core::time::Duration {
+    core::time::Duration::from_secs(u64::from(result.seconds))
+        + fraction_to_duration(result.seconds_fraction)
+}
+
+/// Decode reference identifier value according to the server's stratum
+///
+/// - stratum 0 (Kiss-o'-Death) and stratum 1 identifiers are decoded as [`RefId::Ascii`]
+/// - other strata identifiers are decoded as [`RefId::Ipv4`], except for `127.127.x.x`
+///   addresses of local reference clock drivers, which are decoded as [`RefId::LocalClock`]
+///
+/// Secondary servers reached over IPv6 use the first four bytes of the MD5 digest of the
+/// upstream server address instead, so the value can not be distinguished from IPv4 address
+/// without knowing the server's address family. [`sntp_process_response`] converts such
+/// identifiers to [`RefId::Ipv6Hash`] for responses received from IPv6 addresses.
+///
+/// `ref_id` is expected in host byte order
+#[must_use]
+pub fn parse_ref_id(ref_id: u32, stratum: u8) -> RefId {
+    let bytes = ref_id.to_be_bytes();
+
+    match stratum {
+        0 | 1 => RefId::Ascii(bytes),
+        _ if bytes[..2] == [127, 127] => {
+            RefId::LocalClock(core::net::Ipv4Addr::from(bytes))
+        }
+        _ => RefId::Ipv4(core::net::Ipv4Addr::from(bytes)),
+    }
+}
+
+/// Convert NTP short format value (16.16 fixed point seconds, used by root delay and
+/// root dispersion fields) to microseconds value
+#[must_use]
+pub fn ntp_short_to_micros(val: u32) -> u64 {
+    (u64::from(val) * u64::from(USEC_IN_SEC)) >> 16
+}
+
+#[cfg(test)]
+mod sntpc_ntp_result_tests {
+    use crate::types::Units;
+    use crate::{
+        fraction_to_duration, fraction_to_microseconds,
+        fraction_to_milliseconds, fraction_to_nanoseconds,
+        fraction_to_picoseconds, ntp_result_to_duration, ntp_short_to_micros,
+        offset_calculate, parse_ref_id, NtpMetrics, NtpResult, RefId,
+    };
+    use core::net::Ipv4Addr;
+    use core::time::Duration;
+
+    struct Timestamps(u64, u64, u64, u64);
+    struct OffsetCalcTestCase {
+        timestamp: Timestamps,
+        expected: i64,
+    }
+
+    impl OffsetCalcTestCase {
+        fn new(t1: u64, t2: u64, t3: u64, t4: u64, expected: i64) -> Self {
+            OffsetCalcTestCase {
+                timestamp: Timestamps(t1, t2, t3, t4),
+                expected,
+            }
+        }
+
+        fn t1(&self) -> u64 {
+            self.timestamp.0
+        }
+
+        fn t2(&self) -> u64 {
+            self.timestamp.1
+        }
+
+        fn t3(&self) -> u64 {
+            self.timestamp.2
+        }
+
+        fn t4(&self) -> u64 {
+            self.timestamp.3
+        }
+    }
+
+    #[test]
+    fn test_ntp_result() {
+        let result1 = NtpResult::new(0, 0, 0, 0, 1, -2);
+
+        assert_eq!(0, result1.sec());
+        assert_eq!(0, result1.sec_fraction());
+        assert_eq!(0, result1.roundtrip());
+        assert_eq!(0, result1.offset());
+        assert_eq!(1, result1.stratum());
+        assert_eq!(-2, result1.precision());
+
+        let result2 = NtpResult::new(1, 2, 3, 4, 5, -23);
+
+        assert_eq!(1, result2.sec());
+        assert_eq!(2, result2.sec_fraction());
+        assert_eq!(3, result2.roundtrip());
+        assert_eq!(4, result2.offset());
+        assert_eq!(5, result2.stratum());
+        assert_eq!(-23, result2.precision());
+
+        let result3 =
+            NtpResult::new(u32::MAX - 1, u32::MAX, u64::MAX, i64::MAX, 1, -127);
+
+        assert_eq!(u32::MAX, result3.sec());
+        assert_eq!(0, result3.sec_fraction());
+        assert_eq!(u64::MAX, result3.roundtrip());
+        assert_eq!(i64::MAX, result3.offset());
+        assert_eq!(-127, result3.precision());
+    }
+
+    #[test]
+    fn test_ntp_fraction_overflow_result() {
+        let result = NtpResult::new(0, u32::MAX, 0, 0, 1, -19);
+        assert_eq!(1, result.sec());
+        assert_eq!(0, result.sec_fraction());
+        assert_eq!(0, result.roundtrip());
+        assert_eq!(0, result.offset());
+
+        let result = NtpResult::new(u32::MAX - 1, u32::MAX, 0, 0, 1, -17);
+        assert_eq!(u32::MAX, result.sec());
+        assert_eq!(0, result.sec_fraction());
+        assert_eq!(0, result.roundtrip());
+        assert_eq!(0, result.offset());
+    }
+
+    #[test]
+    fn test_conversion_to_ms() {
+        let result = NtpResult::new(0, u32::MAX - 1, 0, 0, 1, 0);
+        let milliseconds = fraction_to_milliseconds(result.seconds_fraction);
+        assert_eq!(999u32, milliseconds);
+
+        let result = NtpResult::new(0, 0, 0, 0, 1, 0);
+        let milliseconds = fraction_to_milliseconds(result.seconds_fraction);
+        assert_eq!(0u32, milliseconds);
+    }
+
+    #[test]
+    fn test_conversion_to_us() {
+        let result = NtpResult::new(0, u32::MAX - 1, 0, 0, 1, 0);
+        let microseconds = fraction_to_microseconds(result.seconds_fraction);
+        assert_eq!(999_999u32, microseconds);
+
+        let result = NtpResult::new(0, 0, 0, 0, 1, 0);
+        let microseconds = fraction_to_microseconds(result.seconds_fraction);
+        assert_eq!(0u32, microseconds);
+    }
+
+    #[test]
+    fn test_conversion_to_ns() {
+        let result = NtpResult::new(0, u32::MAX - 1, 0, 0, 1, 0);
+        let nanoseconds = fraction_to_nanoseconds(result.seconds_fraction);
+        assert_eq!(999_999_999u32, nanoseconds);
+
+        let result = NtpResult::new(0, 0, 0, 0, 1, 0);
+        let nanoseconds = fraction_to_nanoseconds(result.seconds_fraction);
+        assert_eq!(0u32, nanoseconds);
+    }
+
+    #[test]
+    fn test_conversion_to_ps() {
+        let result = NtpResult::new(0, u32::MAX - 1, 0, 0, 1, 0);
+        let picoseconds = fraction_to_picoseconds(result.seconds_fraction);
+        assert_eq!(999_999_999_767u64, picoseconds);
+
+        let result = NtpResult::new(0, 1, 0, 0, 1, 0);
+        let picoseconds = fraction_to_picoseconds(result.seconds_fraction);
+        assert_eq!(232u64, picoseconds);
+
+        let result = NtpResult::new(0, 0, 0, 0, 1, 0);
+        let picoseconds = fraction_to_picoseconds(result.seconds_fraction);
+        assert_eq!(0u64, picoseconds);
+    }
+
+    #[test]
+    fn test_conversion_to_duration() {
+        let duration = fraction_to_duration(u32::MAX - 1);
+        assert!(duration < Duration::from_secs(1));
+        assert_eq!(Duration::from_nanos(999_999_999), duration);
+
+        assert_eq!(Duration::ZERO, fraction_to_duration(0));
+        assert_eq!(Duration::from_millis(500), fraction_to_duration(1 << 31));
+    }
+
+    #[test]
+    fn test_ntp_result_to_duration() {
+        let result = NtpResult::new(1_700_000_000, 1 << 31, 0, 0, 1, 0);
+        assert_eq!(
+            Duration::from_secs(1_700_000_000) + Duration::from_millis(500),
+            ntp_result_to_duration(&result)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "chrono")]
+    fn test_ntp_result_to_datetime_utc() {
+        use crate::types::NtpTimestamp;
+        use chrono::{DateTime, TimeZone, Utc};
+
+        // 2024-01-01T00:00:00.5Z in the NTP timestamp format
+        let timestamp = NtpTimestamp::from((3_913_056_000u64 << 32) | 1 << 31);
+        let result = NtpResult::new(
+            u32::try_from(timestamp.seconds).unwrap(),
+            u32::try_from(timestamp.seconds_fraction).unwrap(),
+            0,
+            0,
+            1,
+            0,
+        );
+        let expected = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap()
+            + chrono::Duration::milliseconds(500);
+
+        assert_eq!(expected, result.to_datetime_utc());
+        assert_eq!(expected, DateTime::<Utc>::from(result));
+    }
+
+    #[test]
+    #[cfg(feature = "time")]
+    fn test_ntp_result_to_offset_date_time() {
+        use crate::types::NtpTimestamp;
+        use time::{Month, OffsetDateTime};
+
+        // 2024-01-01T00:00:00.5Z in the NTP timestamp format
+        let timestamp = NtpTimestamp::from((3_913_056_000u64 << 32) | 1 << 31);
+        let result = NtpResult::new(
+            u32::try_from(timestamp.seconds).unwrap(),
+            u32::try_from(timestamp.seconds_fraction).unwrap(),
+            0,
+            0,
+            1,
+            0,
+        );
+        let time = result.to_offset_date_time().unwrap();
+
+        assert_eq!((2024, Month::January, 1), time.to_calendar_date());
+        assert_eq!((0, 0, 0, 500), time.to_hms_milli());
+        assert_eq!(Ok(time), OffsetDateTime::try_from(result));
+
+        #[cfg(feature = "chrono")]
+        {
+            use chrono::Datelike;
+
+            let datetime = result.to_datetime_utc();
+            assert_eq!(
+                (datetime.year(), datetime.month(), datetime.day()),
+                (
+                    time.year(),
+                    u32::from(u8::from(time.month())),
+                    u32::from(time.day())
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_ref_id() {
+        let gps = parse_ref_id(0x4750_5300, 1);
+        assert_eq!(RefId::Ascii(*b"GPS\0"), gps);
+        assert_eq!(Some("GPS"), gps.as_str());
+
+        let pps = parse_ref_id(0x5050_5300, 1);
+        assert_eq!(Some("PPS"), pps.as_str());
+
+        let goes = parse_ref_id(0x474F_4553, 1);
+        assert_eq!(Some("GOES"), goes.as_str());
+
+        let upstream = parse_ref_id(0xC0A8_0101, 2);
+        assert_eq!(RefId::Ipv4(Ipv4Addr::new(192, 168, 1, 1)), upstream);
+        assert_eq!([192, 168, 1, 1], upstream.octets());
+        assert_eq!(None, upstream.as_str());
+
+        let deny = parse_ref_id(0x4445_4E59, 0);
+        assert_eq!(Some("DENY"), deny.as_str());
+
+        let local = parse_ref_id(0x7F7F_0100, 2);
+        assert_eq!(RefId::LocalClock(Ipv4Addr::new(127, 127, 1, 0)), local);
+        assert_eq!([127, 127, 1, 0], local.octets());
+        assert_eq!(
+            RefId::Ipv4(Ipv4Addr::LOCALHOST),
+            parse_ref_id(0x7F00_0001, 2)
+        );
+    }
+
+    #[test]
+    fn test_reference_clock() {
+        use crate::ReferenceClock;
+
+        assert_eq!(
+            Some(ReferenceClock::Gps),
+            parse_ref_id(u32::from_be_bytes(*b"GPS\0"), 1).reference_clock()
+        );
+        assert_eq!(
+            Some(ReferenceClock::Pps),
+            parse_ref_id(u32::from_be_bytes(*b"PPS\0"), 1).reference_clock()
+        );
+        assert_eq!(
+            Some(ReferenceClock::Goes),
+            parse_ref_id(u32::from_be_bytes(*b"GOES"), 1).reference_clock()
+        );
+        assert_eq!(
+            Some(ReferenceClock::Other(*b"XFAC")),
+            parse_ref_id(u32::from_be_bytes(*b"XFAC"), 1).reference_clock()
+        );
+        // the identifier is zero padded, so a code with trailing spaces is unknown
+        assert_eq!(
+            ReferenceClock::Other(*b"GPS "),
+            ReferenceClock::from(*b"GPS ")
+        );
+        assert_eq!(None, parse_ref_id(0xC0A8_0101, 2).reference_clock());
+    }
+
+    #[test]
+    fn test_ntp_short_to_micros() {
+        assert_eq!(0u64, ntp_short_to_micros(0));
+        assert_eq!(15u64, ntp_short_to_micros(0x0000_0001));
+        assert_eq!(500_000u64, ntp_short_to_micros(0x0000_8000));
+        assert_eq!(1_000_000u64, ntp_short_to_micros(0x0001_0000));
+        assert_eq!(1_500_000u64, ntp_short_to_micros(0x0001_8000));
+        assert_eq!(65_535_999_984u64, ntp_short_to_micros(u32::MAX));
+    }
+
+    #[test]
+    fn test_offset_calculate() {
+        let tests = [
+            OffsetCalcTestCase::new(
+                16_893_142_954_672_769_962,
+                16_893_142_959_053_084_959,
+                16_893_142_959_053_112_968,
+                16_893_142_954_793_063_406,
+                1_005_870,
+            ),
+            OffsetCalcTestCase::new(
+                16_893_362_966_131_575_843,
+                16_893_362_966_715_800_791,
+                16_893_362_966_715_869_584,
+                16_893_362_967_084_349_913,
+                25115,
+            ),
+            OffsetCalcTestCase::new(
+                16_893_399_716_399_327_198,
+                16_893_399_716_453_045_029,
+                16_893_399_716_453_098_083,
+                16_893_399_716_961_924_964,
+                -52981,
+            ),
+            OffsetCalcTestCase::new(
+                9_487_534_663_484_046_772u64,
+                16_882_120_099_581_835_046u64,
+                16_882_120_099_583_884_144u64,
+                9_487_534_663_651_464_597u64,
+                1_721_686_086_620_926,
+            ),
+        ];
+
+        for t in tests {
+            let offset = offset_calculate(
+                t.t1(),
+                t.t2(),
+                t.t3(),
+                t.t4(),
+                Units::Microseconds,
+            );
+            let expected = t.expected;
+            assert_eq!(offset, expected);
+        }
+    }
+
+    #[test]
+    fn test_ntp_result_durations() {
+        use core::time::Duration;
+
+        // offsets from `test_offset_calculate` cases
+        let result = NtpResult::new(0, 0, 2_000, -52_981, 1, 0);
+        assert_eq!(
+            (Duration::from_micros(52_981), false),
+            result.offset_duration()
+        );
+        assert_eq!(Duration::from_millis(2), result.roundtrip_duration());
+
+        let result = NtpResult::new(0, 0, 0, 1_721_686_086_620_926, 1, 0);
+        assert_eq!(
+            (Duration::from_micros(1_721_686_086_620_926), true),
+            result.offset_duration()
+        );
+        assert_eq!(Duration::ZERO, result.roundtrip_duration());
+
+        let result = NtpResult::new(0, 0, 0, i64::MIN, 1, 0);
+        assert_eq!(
+            (Duration::from_micros(i64::MIN.unsigned_abs()), false),
+            result.offset_duration()
+        );
+    }
+
+    #[test]
+    fn test_ntp_result_precision_duration() {
+        use core::time::Duration;
+
+        let precision =
+            |p: i8| NtpResult::new(0, 0, 0, 0, 1, p).precision_duration();
+
+        assert_eq!(Duration::from_nanos(953), precision(-20));
+        assert_eq!(Duration::from_micros(15_625), precision(-6));
+        assert_eq!(Duration::from_secs(1), precision(0));
+        assert_eq!(Duration::from_secs(8), precision(3));
+        assert_eq!(Duration::ZERO, precision(-30));
+        assert_eq!(Duration::ZERO, precision(i8::MIN));
+        assert_eq!(Duration::from_secs(u64::MAX), precision(i8::MAX));
+    }
+
+    #[test]
+    fn test_ntp_result_local_reference_instant() {
+        const NTP_UNIX_DELTA: u64 = 2_208_988_800;
+        let ntp = |sec: u64, fraction: u32| {
+            ((sec + NTP_UNIX_DELTA) << 32) | u64::from(fraction)
+        };
+        let result = |t1: u64, t4: u64| NtpResult {
+            originate_timestamp: t1,
+            destination_timestamp: t4,
+            ..NtpResult::new(0, 0, 0, 0, 1, 0)
+        };
+
+        // T1 = 1_700_000_000.75, T4 = 1_700_000_003.5, midpoint is 1_700_000_002.125
+        assert_eq!(
+            1_700_000_002,
+            result(ntp(1_700_000_000, 3 << 30), ntp(1_700_000_003, 1 << 31))
+                .local_reference_instant_unix()
+        );
+        // T4 = T1
+        assert_eq!(
+            1_700_000_000,
+            result(ntp(1_700_000_000, 0), ntp(1_700_000_000, 0))
+                .local_reference_instant_unix()
+        );
+        // no overflow for timestamps at the end of the NTP era
+        assert_eq!(
+            (u64::from(u32::MAX) - 1) - NTP_UNIX_DELTA,
+            result(u64::MAX - (3 << 32), u64::MAX)
+                .local_reference_instant_unix()
+        );
+        assert_eq!(
+            0,
+            NtpResult::new(1_700_000_000, 0, 0, 0, 1, 0)
+                .local_reference_instant_unix()
+        );
+    }
+
+    #[test]
+    fn test_ntp_result_corrected_time() {
+        use core::time::Duration;
+
+        // fraction conversions truncate, so the time is compared within 1us
+        let assert_corrected = |expected: Duration, result: NtpResult| {
+            let (sec, fraction) = result.corrected_time();
+            let corrected = Duration::from_secs(u64::from(sec))
+                + fraction_to_duration(fraction);
+
+            assert!(expected.abs_diff(corrected) < Duration::from_micros(1));
+        };
+        let time =
+            |sec, ms| Duration::from_secs(sec) + Duration::from_millis(ms);
+
+        // 0.25s fraction plus half of 100ms roundtrip
+        assert_corrected(
+            time(1_700_000_000, 300),
+            NtpResult::new(1_700_000_000, u32::MAX / 4, 100_000, 0, 1, 0),
+        );
+        // the fraction carries into seconds
+        assert_corrected(
+            time(1_700_000_001, 250),
+            NtpResult::new(1_700_000_000, u32::MAX / 4 * 3, 1_000_000, 0, 1, 0),
+        );
+        assert_corrected(
+            time(1_700_000_001, 500),
+            NtpResult {
+                units: Units::Milliseconds,
+                ..NtpResult::new(1_700_000_000, 0, 3_000_000, 0, 1, 0)
+            },
+        );
+        assert_eq!(
+            (1_700_000_000, 0),
+            NtpResult::new(1_700_000_000, 0, 0, 0, 1, 0).corrected_time()
+        );
+    }
+
+    #[test]
+    fn test_ntp_result_arithmetic() {
+        use core::time::Duration;
+
+        // fraction conversions truncate, so sub-second parts are compared within 1ms
+        let assert_close = |expected: Duration, actual: Duration| {
+            assert!(expected.abs_diff(actual) < Duration::from_millis(1));
+        };
+        let result = NtpResult::new(1_700_000_000, u32::MAX / 4, 0, 0, 1, -20);
+
+        let later = result + Duration::from_secs(1);
+        assert_eq!(1_700_000_001, later.sec());
+        assert_eq!(u32::MAX / 4, later.sec_fraction());
+
+        // fraction carry into seconds
+        let later = result + Duration::from_millis(1_900);
+        assert_eq!(1_700_000_002, later.sec());
+        assert_close(
+            Duration::from_millis(150),
+            crate::fraction_to_duration(later.sec_fraction()),
+        );
+
+        // fraction borrow from seconds
+        let earlier = result - Duration::from_millis(500);
+        assert_eq!(1_699_999_999, earlier.sec());
+        assert_close(
+            Duration::from_millis(750),
+            crate::fraction_to_duration(earlier.sec_fraction()),
+        );
+        assert_close(
+            Duration::ZERO,
+            earlier + Duration::from_millis(500) - result,
+        );
+
+        let half_second = result + Duration::from_millis(500);
+        assert_close(Duration::from_millis(500), half_second - result);
+        assert_close(Duration::from_millis(500), result - half_second);
+        assert_eq!(
+            Duration::from_secs(3),
+            (result + Duration::from_secs(3)) - result
+        );
+    }
+
+    #[test]
+    fn test_ntp_result_metrics() {
+        let mut result = NtpResult::new(0, 0, 3_001, -52_981, 2, -23);
+
+        assert_eq!(
+            NtpMetrics {
+                offset_us: -52_981,
+                roundtrip_us: 3_001,
+                stratum: 2,
+                precision: -23,
+                one_way_us: 1_500,
+            },
+            result.metrics()
+        );
+
+        // the reported units do not affect the metrics
+        let metrics = result.metrics();
+        result.units = Units::Milliseconds;
+        assert_eq!(metrics, result.metrics());
+
+        result.offset = i64::MIN;
+        result.roundtrip = u64::MAX;
+        let metrics = result.metrics();
+        assert_eq!(i64::MIN, metrics.offset_us);
+        assert_eq!(u64::MAX, metrics.roundtrip_us);
+    }
+
+    #[test]
+    fn test_ntp_result_is_plausible() {
+        // 1970-01-01, e.g. a zeroed response
+        assert!(!NtpResult::new(0, 0, 0, 0, 1, 0).is_plausible());
+        // 2024-06-01
+        assert!(NtpResult::new(1_717_200_000, 0, 0, 0, 1, 0).is_plausible());
+        assert!(NtpResult::new(1_577_836_800, 0, 0, 0, 1, 0).is_plausible());
+        assert!(!NtpResult::new(1_577_836_799, 0, 0, 0, 1, 0).is_plausible());
+        assert!(!NtpResult::new(u32::MAX, 0, 0, 0, 1, 0).is_plausible());
+    }
+}
+
+#[cfg(test)]
+mod sntpc_offset_proptests {
+    use crate::types::Units;
+    use crate::{offset_calculate, roundtrip_calculate};
+    use proptest::prelude::*;
+
+    const NTP_SEC: u64 = 1 << 32;
+    const NTP_DAY: i64 = 86_400 << 32;
+    const USEC_IN_SEC: i128 = 1_000_000;
+
+    /// NTP timestamps from ~2014 to ~2033, i.e. the same NTP era
+    fn era_timestamp() -> impl Strategy<Value = u64> {
+        (3_600_000_000 * NTP_SEC)..(4_200_000_000 * NTP_SEC)
+    }
+
+    /// Convert an NTP fixed point interval to microseconds rounding towards zero
+    fn to_micros(value: i128) -> i128 {
+        ((value.abs() * USEC_IN_SEC) >> 32) * value.signum()
+    }
+
+    proptest! {
+        #[test]
+        fn test_roundtrip_never_panics(
+            t1 in any::<u64>(),
+            t2 in any::<u64>(),
+            t3 in any::<u64>(),
+            t4 in any::<u64>(),
+        ) {
+            roundtrip_calculate(t1, t2, t3, t4, Units::Microseconds);
+            roundtrip_calculate(t1, t2, t3, t4, Units::Milliseconds);
+            offset_calculate(t1, t2, t3, t4, Units::Microseconds);
+            offset_calculate(t1, t2, t3, t4, Units::Milliseconds);
+        }
+
+        #[test]
+        fn test_roundtrip_symmetry(
+            t1 in era_timestamp(),
+            d1 in 0..10 * NTP_SEC,
+            server in 0..NTP_SEC,
+            d2 in 0..10 * NTP_SEC,
+        ) {
+            let t2 = t1 + d1;
+            let t3 = t2 + server;
+            let t4 = t3 + d2;
+
+            prop_assert_eq!(
+                roundtrip_calculate(t1, t2, t3, t4, Units::Microseconds),
+                roundtrip_calculate(t3, t4, t1, t2, Units::Microseconds)
+            );
+        }
+
+        #[test]
+        fn test_offset_sign(
+            t1 in era_timestamp(),
+            t2 in era_timestamp(),
+            t3 in era_timestamp(),
+            t4 in era_timestamp(),
+        ) {
+            let offset = offset_calculate(t1, t2, t3, t4, Units::Microseconds);
+            #[allow(clippy::cast_possible_wrap)]
+            let theta = t2.wrapping_sub(t1).wrapping_add(t3.wrapping_sub(t4)) as i64;
+
+            prop_assert!(
+                offset == 0 || offset.signum() == theta.signum(),
+                "offset {} theta {}",
+                offset,
+                theta
+            );
+        }
+
+        #[test]
+        fn test_plausible_ranges(
+            t1 in era_timestamp(),
+            clock_offset in -NTP_DAY..NTP_DAY,
+            d1 in 0..10 * NTP_SEC,
+            server in 0..NTP_SEC,
+            d2 in 0..10 * NTP_SEC,
+        ) {
+            // the server clock is `clock_offset` ahead, the request travels `d1` and the
+            // response travels `d2`
+            let t2 = (t1 + d1).wrapping_add_signed(clock_offset);
+            let t3 = t2 + server;
+            let t4 = t1 + d1 + server + d2;
+
+            let roundtrip = roundtrip_calculate(t1, t2, t3, t4, Units::Microseconds);
+            let expected = to_micros(i128::from(d1 + d2));
+            prop_assert!((i128::from(roundtrip) - expected).abs() <= 1);
+
+            let offset = offset_calculate(t1, t2, t3, t4, Units::Microseconds);
+            let expected = to_micros(
+                i128::from(clock_offset) + (i128::from(d1) - i128::from(d2)) / 2,
+            );
+            // halving both intervals and converting the fraction may round off by a microsecond
+            prop_assert!(
+                (i128::from(offset) - expected).abs() <= 2,
+                "offset {} expected {}",
+                offset,
+                expected
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod sntpc_process_response_tests {
+    use crate::types::{NtpPacket, RawNtpPacket};
+    use crate::{
+        get_time_any, get_time_any_with_timeout, get_time_burst,
+        get_time_by_name, get_time_by_srv, get_time_multi,
+        get_time_with_kod_cache, get_time_with_retry,
+        get_time_with_retry_and_delay, listen_broadcast,
+        ntp_timestamp_from_gen, process_response, sntp_listen_broadcast_from,
+        sntp_process_in_flight_response, sntp_process_response_with_recv_ts,
+        sntp_send_request, Error, InFlight, KissCode, KodCache, LeapIndicator,
+        NtpClientConfig, NtpContext, NtpHostResolver, NtpRecvSocket,
+        NtpRequest, NtpSendSocket, NtpSrvResolver, NtpTimestampGenerator,
+        NtpUdpSocket, NtpUdpSocketMut, NtpVersion, PendingRequest, RefId,
+        SendRequestResult, SrvTarget, TimeSource, Units, VersionMask,
+    };
+    use core::cell::{Cell, RefCell};
+    use core::net::{IpAddr, Ipv4Addr, SocketAddr};
+    use core::time::Duration;
+    use miniloop::executor::Executor;
+
+    #[derive(Copy, Clone, Default)]
+    struct FixedTimestampGen {
+        sec: u64,
+        usec: u32,
+    }
+
+    impl FixedTimestampGen {
+        fn new(sec: u64, usec: u32) -> Self {
+            Self { sec, usec }
+        }
+    }
+
+    impl NtpTimestampGenerator for FixedTimestampGen {
+        fn init(&mut self) {}
+
+        fn timestamp_sec(&self) -> u64 {
+            self.sec
+        }
+
+        fn timestamp_subsec_micros(&self) -> u32 {
+            self.usec
+        }
+    }
+
+    const SERVER: SocketAddr =
+        SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), 123);
+
+    struct MockSocket {
+        addr: SocketAddr,
+        response: RawNtpPacket,
+    }
+
+    impl NtpUdpSocket for MockSocket {
+        async fn send_to(
+            &self,
+            buf: &[u8],
+            _addr: SocketAddr,
+        ) -> crate::Result<usize> {
+            Ok(buf.len())
+        }
+
+        async fn recv_from(
+            &self,
+            buf: &mut [u8],
+        ) -> crate::Result<(usize, SocketAddr)> {
+            buf[..self.response.0.len()].copy_from_slice(&self.response.0);
+
+            Ok((self.response.0.len(), self.addr))
+        }
+    }
+
+    /// Build a server response to the given request in host byte order
+    fn response_packet(request: &NtpPacket) -> NtpPacket {
+        const SNTP_SERVER_MODE: u8 = 4;
+        const SNTP_VERSION_4: u8 = 4 << 3;
+
+        NtpPacket {
+            li_vn_mode: SNTP_SERVER_MODE | SNTP_VERSION_4,
+            stratum: 1,
+            poll: 6,
+            precision: -20,
+            root_delay: 0,
+            root_dispersion: 0,
+            ref_id: 0,
+            ref_timestamp: request.tx_timestamp,
+            origin_timestamp: request.tx_timestamp,
+            recv_timestamp: request.tx_timestamp,
+            tx_timestamp: request.tx_timestamp,
+        }
+    }
+
+    fn request_packet(
+    ) -> (NtpContext<FixedTimestampGen>, SendRequestResult, NtpPacket) {
+        let context = NtpContext::new(FixedTimestampGen::new(1_700_000_000, 0));
+        let request = NtpPacket::new(context.timestamp_gen, context.version);
+        let response = response_packet(&request);
+
+        (context, SendRequestResult::from(request), response)
+    }
+
+    fn process(
+        context: &NtpContext<FixedTimestampGen>,
+        request: SendRequestResult,
+        response: &NtpPacket,
+    ) -> crate::Result<crate::NtpResult> {
+        let recv_timestamp = ntp_timestamp_from_gen(&context.timestamp_gen);
+
+        process_response(
+            request,
+            RawNtpPacket::from(response),
+            recv_timestamp,
+            context,
+        )
+    }
+
+    #[test]
+    fn test_ntp_timestamp_from_gen() {
+        let timestamp = ntp_timestamp_from_gen(&FixedTimestampGen::new(
+            1_700_000_000,
+            250_000,
+        ));
+
+        assert_eq!(1_700_000_000 + 2_208_988_800, timestamp >> 32);
+        assert_eq!(u64::from(u32::MAX / 4), timestamp & 0xffff_ffff);
+        // the request transmit timestamp is stamped the same way
+        let (context, request, _) = request_packet();
+        assert_eq!(
+            ntp_timestamp_from_gen(&context.timestamp_gen),
+            request.originate_timestamp
+        );
+    }
+
+    #[test]
+    fn test_rtc_timestamp_gen() {
+        use core::cell::Cell;
+
+        let reads = Cell::new(0u32);
+        let mut timestamp_gen = crate::RtcTimestampGen::new(|| {
+            reads.set(reads.get() + 1);
+            (1_700_000_000 + u64::from(reads.get()), 250_000)
+        });
+
+        assert_eq!(0, reads.get());
+        timestamp_gen.init();
+        assert_eq!(1_700_000_001, timestamp_gen.timestamp_sec());
+        assert_eq!(250_000, timestamp_gen.timestamp_subsec_micros());
+        assert_eq!(1, reads.get());
+
+        timestamp_gen.init();
+        assert_eq!(
+            ((1_700_000_002 + 2_208_988_800) << 32) | u64::from(u32::MAX / 4),
+            ntp_timestamp_from_gen(&timestamp_gen)
+        );
+        assert_eq!(2, reads.get());
+    }
+
+    #[test]
+    fn test_process_response_local_reference_instant() {
+        let (context, request, response) = request_packet();
+        let result = process(&context, request, &response).unwrap();
+
+        assert_eq!(request.originate_timestamp, result.originate_timestamp);
+        assert_eq!(
+            ntp_timestamp_from_gen(&context.timestamp_gen),
+            result.destination_timestamp
+        );
+        assert_eq!(1_700_000_000, result.local_reference_instant_unix());
+    }
+
+    #[test]
+    fn test_packet_codec_roundtrip() {
+        let context = NtpContext::new(FixedTimestampGen::new(1_700_000_000, 0));
+        let (request, request_result) = crate::packet::encode_request(context);
+        assert_eq!(
+            RawNtpPacket::from(&crate::build_request(context)).0,
+            request
+        );
+        let response = RawNtpPacket::from(&response_packet(
+            &crate::build_request(context),
+        ));
+        let recv_timestamp = ntp_timestamp_from_gen(&context.timestamp_gen);
+
+        let result = crate::packet::decode_response(
+            request_result,
+            &response.0,
+            recv_timestamp,
+            &context,
+        )
+        .unwrap();
+        assert_eq!(1_700_000_000, result.sec());
+
+        // extension fields past the fixed header are ignored
+        let mut extended = [0u8; 68];
+        extended[..crate::packet::PACKET_SIZE].copy_from_slice(&response.0);
+        assert!(crate::packet::decode_response(
+            request_result,
+            &extended,
+            recv_timestamp,
+            &context,
+        )
+        .is_ok());
+        assert_eq!(
+            Error::IncorrectPayload,
+            crate::packet::decode_response(
+                request_result,
+                &response.0[..40],
+                recv_timestamp,
+                &context,
+            )
+            .unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_process_response_time_source() {
+        // 0.2 s in the NTP fraction format
+        const RECV_FRACTION: u64 = 858_993_460;
+
+        let (context, request, mut response) = request_packet();
+        // the server clock is 100 s ahead and has a one second resolution
+        response.recv_timestamp += 100 << 32;
+        response.tx_timestamp += 100 << 32;
+        let recv_timestamp = response.origin_timestamp + RECV_FRACTION;
+
+        let server_tx = process_response(
+            request,
+            RawNtpPacket::from(&response),
+            recv_timestamp,
+            &context,
+        )
+        .unwrap();
+        let local = process_response(
+            request,
+            RawNtpPacket::from(&response),
+            recv_timestamp,
+            &context.with_time_source(TimeSource::LocalPlusOffset),
+        )
+        .unwrap();
+
+        assert_eq!(server_tx.offset(), local.offset());
+        assert_eq!(server_tx.roundtrip(), local.roundtrip());
+        assert_eq!(1_700_000_100, server_tx.sec());
+        assert_eq!(0, server_tx.sec_fraction());
+        // T4 + theta = 0.2 s + (100 s + 100 s - 0.2 s) / 2 = 100.1 s
+        assert_eq!(1_700_000_100, local.sec());
+        assert_eq!(RECV_FRACTION / 2, u64::from(local.sec_fraction()));
+    }
+
+    /// Socket that has a response only after the network stack has been polled `polls_left`
+    /// times
+    struct PolledSocket {
+        response: RawNtpPacket,
+        polls_left: Cell<usize>,
+    }
+
+    impl NtpUdpSocket for PolledSocket {
+        async fn send_to(
+            &self,
+            buf: &[u8],
+            _addr: SocketAddr,
+        ) -> crate::Result<usize> {
+            Ok(buf.len())
+        }
+
+        async fn recv_from(
+            &self,
+            buf: &mut [u8],
+        ) -> crate::Result<(usize, SocketAddr)> {
+            core::future::poll_fn(|_| {
+                if self.polls_left.get() == 0 {
+                    core::task::Poll::Ready(())
+                } else {
+                    core::task::Poll::Pending
+                }
+            })
+            .await;
+            buf[..self.response.0.len()].copy_from_slice(&self.response.0);
+
+            Ok((self.response.0.len(), SERVER))
+        }
+    }
+
+    #[test]
+    fn test_get_time_with_poll() {
+        let (context, _, response) = request_packet();
+        let socket = PolledSocket {
+            response: RawNtpPacket::from(&response),
+            polls_left: Cell::new(3),
+        };
+        let polls = Cell::new(0);
+
+        let result = Executor::new()
+            .block_on(crate::get_time_with_poll(
+                SERVER,
+                &socket,
+                context,
+                || {
+                    polls.set(polls.get() + 1);
+                    socket.polls_left.set(socket.polls_left.get() - 1);
+                },
+            ))
+            .unwrap();
+
+        assert_eq!(1_700_000_000, result.sec());
+        assert_eq!(3, polls.get());
+    }
+
+    #[cfg(feature = "minimal-sync")]
+    #[test]
+    fn test_minimal_executor_get_time() {
+        let (context, _, response) = request_packet();
+        let socket = PolledSocket {
+            response: RawNtpPacket::from(&response),
+            polls_left: Cell::new(0),
+        };
+
+        let result = crate::executor::Executor::new()
+            .block_on(crate::get_time(SERVER, &socket, context))
+            .unwrap();
+        assert_eq!(1_700_000_000, result.sec());
+    }
+
+    #[test]
+    fn test_process_response_resume_after_cancel() {
+        let (context, request, response) = request_packet();
+        let socket = PolledSocket {
+            response: RawNtpPacket::from(&response),
+            polls_left: Cell::new(1),
+        };
+        let mut executor = Executor::new();
+
+        // the response is not there yet and the wait is cancelled right away
+        assert!(executor
+            .block_on(crate::with_timeout(
+                crate::sntp_process_response(SERVER, &socket, context, request),
+                async {},
+            ))
+            .is_none());
+
+        socket.polls_left.set(0);
+        let result = executor
+            .block_on(crate::sntp_process_response(
+                SERVER, &socket, context, request,
+            ))
+            .unwrap();
+        assert_eq!(1_700_000_000, result.sec());
+    }
+
+    #[test]
+    fn test_pending_request() {
+        let (context, request, response) = request_packet();
+        let socket = MockSocket {
+            addr: SERVER,
+            response: RawNtpPacket::from(&response),
+        };
+        let mut executor = Executor::new();
+
+        let pending = executor
+            .block_on(PendingRequest::send(SERVER, &socket, context))
+            .unwrap();
+        assert_eq!(SERVER, pending.dest());
+        assert_eq!(
+            request.originate_timestamp,
+            pending.request().originate_timestamp
+        );
+
+        let result = executor.block_on(pending.complete(context)).unwrap();
+        assert_eq!(1_700_000_000, result.sec());
+    }
+
+    #[test]
+    fn test_process_response_raw() {
+        let (context, request, mut response) = request_packet();
+        response.root_delay = 0x0001_8000;
+        response.root_dispersion = 0x0000_4000;
+        response.ref_id = u32::from_be_bytes(*b"GPS\0");
+        response.ref_timestamp = request.originate_timestamp - (16 << 32);
+        response.recv_timestamp = request.originate_timestamp + 1;
+        let socket = MockSocket {
+            addr: SERVER,
+            response: RawNtpPacket::from(&response),
+        };
+
+        let raw = Executor::new()
+            .block_on(crate::sntp_process_response_raw(
+                SERVER, &socket, context, request,
+            ))
+            .unwrap();
+
+        assert_eq!(4, raw.version);
+        assert_eq!(4, raw.mode);
+        assert_eq!(LeapIndicator::NoWarning, raw.leap_indicator);
+        assert_eq!(1, raw.stratum);
+        assert_eq!(6, raw.poll);
+        assert_eq!(-20, raw.precision);
+        assert_eq!(0x0001_8000, raw.root_delay);
+        assert_eq!(0x0000_4000, raw.root_dispersion);
+        assert_eq!(u32::from_be_bytes(*b"GPS\0"), raw.ref_id);
+        assert_eq!(response.ref_timestamp, raw.ref_timestamp);
+        assert_eq!(request.originate_timestamp, raw.origin_timestamp);
+        assert_eq!(response.recv_timestamp, raw.recv_timestamp);
+        assert_eq!(response.tx_timestamp, raw.tx_timestamp);
+        assert_eq!(
+            ntp_timestamp_from_gen(&context.timestamp_gen),
+            raw.destination_timestamp
+        );
+        assert_eq!(1_700_000_000, raw.result.sec());
+        assert_eq!(1_500_000, raw.result.root_delay);
+        assert_eq!(Some(SERVER), raw.result.source());
+
+        // the response is validated the same way
+        response.stratum = 0;
+        let socket = MockSocket {
+            addr: SERVER,
+            response: RawNtpPacket::from(&response),
+        };
+        assert!(Executor::new()
+            .block_on(crate::get_time_raw(SERVER, &socket, context))
+            .is_err());
+    }
+
+    #[test]
+    fn test_packet_inspector() {
+        use crate::PacketDirection;
+        use core::sync::atomic::{
+            AtomicBool, AtomicU64, AtomicU8, AtomicUsize, Ordering,
+        };
+
+        static SENT: AtomicUsize = AtomicUsize::new(0);
+        static RECEIVED: AtomicUsize = AtomicUsize::new(0);
+        static SENT_TX_TIMESTAMP: AtomicU64 = AtomicU64::new(0);
+        static RECEIVED_STRATUM: AtomicU8 = AtomicU8::new(u8::MAX);
+        static WRONG_ADDR: AtomicBool = AtomicBool::new(false);
+
+        fn record(direction: PacketDirection, packet: &[u8], addr: SocketAddr) {
+            WRONG_ADDR.fetch_or(addr != SERVER, Ordering::Relaxed);
+
+            match direction {
+                PacketDirection::Sent => {
+                    let tx_timestamp = packet[40..48].try_into().unwrap();
+                    SENT_TX_TIMESTAMP.store(
+                        u64::from_be_bytes(tx_timestamp),
+                        Ordering::Relaxed,
+                    );
+                    SENT.fetch_add(1, Ordering::Relaxed);
+                }
+                PacketDirection::Received => {
+                    RECEIVED_STRATUM.store(packet[1], Ordering::Relaxed);
+                    RECEIVED.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+
+        let (context, request, mut response) = request_packet();
+        let socket = MockSocket {
+            addr: SERVER,
+            response: RawNtpPacket::from(&response),
+        };
+        let context = NtpContext::builder(context.timestamp_gen)
+            .inspector(record)
+            .build();
+
+        let result =
+            Executor::new().block_on(crate::get_time(SERVER, &socket, context));
+        assert!(result.is_ok());
+        assert_eq!(1, SENT.load(Ordering::Relaxed));
+        assert_eq!(1, RECEIVED.load(Ordering::Relaxed));
+        assert_eq!(
+            request.originate_timestamp,
+            SENT_TX_TIMESTAMP.load(Ordering::Relaxed)
+        );
+        assert_eq!(1, RECEIVED_STRATUM.load(Ordering::Relaxed));
+        assert!(!WRONG_ADDR.load(Ordering::Relaxed));
+
+        // the received datagram is inspected before the validation
+        response.stratum = 0;
+        let socket = MockSocket {
+            addr: SERVER,
+            response: RawNtpPacket::from(&response),
+        };
+        let result =
+            Executor::new().block_on(crate::get_time(SERVER, &socket, context));
+        assert!(result.is_err());
+        assert_eq!(2, SENT.load(Ordering::Relaxed));
+        assert_eq!(2, RECEIVED.load(Ordering::Relaxed));
+        assert_eq!(0, RECEIVED_STRATUM.load(Ordering::Relaxed));
+    }
+
+    /// Socket that holds a late response to a cancelled request ahead of the response to the
+    /// current request
+    struct StaleResponseSocket {
+        stale: Cell<Option<RawNtpPacket>>,
+        response: RawNtpPacket,
+    }
+
+    impl NtpUdpSocket for StaleResponseSocket {
+        async fn send_to(
+            &self,
+            buf: &[u8],
+            _addr: SocketAddr,
+        ) -> crate::Result<usize> {
+            Ok(buf.len())
+        }
+
+        async fn recv_from(
+            &self,
+            buf: &mut [u8],
+        ) -> crate::Result<(usize, SocketAddr)> {
+            let response = self.stale.take().unwrap_or(self.response);
+            buf[..response.0.len()].copy_from_slice(&response.0);
+
+            Ok((response.0.len(), SERVER))
+        }
+    }
+
+    #[test]
+    fn test_process_response_drops_stale_response() {
+        let (context, request, response) = request_packet();
+        let cancelled = NtpPacket::new(
+            FixedTimestampGen::new(1_699_999_999, 0),
+            NtpPacket::SNTP_VERSION,
+        );
+        let stale = RawNtpPacket::from(&response_packet(&cancelled));
+        let socket = StaleResponseSocket {
+            stale: Cell::new(Some(stale)),
+            response: RawNtpPacket::from(&response),
+        };
+        let mut executor = Executor::new();
+
+        let result = executor
+            .block_on(crate::sntp_process_response(
+                SERVER, &socket, context, request,
+            ))
+            .unwrap();
+        assert_eq!(1_700_000_000, result.sec());
+        assert!(socket.stale.get().is_none());
+
+        socket.stale.set(Some(stale));
+        let result = executor
+            .block_on(crate::get_time(SERVER, &socket, context))
+            .unwrap();
+        assert_eq!(1_700_000_000, result.sec());
+        assert!(socket.stale.get().is_none());
+    }
+
+    #[test]
+    fn test_process_response_stale_flood() {
+        let (context, request, _) = request_packet();
+        let cancelled = NtpPacket::new(
+            FixedTimestampGen::new(1_699_999_999, 0),
+            NtpPacket::SNTP_VERSION,
+        );
+        let socket = MockSocket {
+            addr: SERVER,
+            response: RawNtpPacket::from(&response_packet(&cancelled)),
+        };
+
+        assert_eq!(
+            Error::IncorrectOriginTimestamp,
+            Executor::new()
+                .block_on(crate::sntp_process_response(
+                    SERVER, &socket, context, request,
+                ))
+                .unwrap_err()
+        );
+    }
+
+    /// Socket that never receives anything and records the requested receive timeout
+    struct DeadlineSocket {
+        timeout: Cell<Option<Duration>>,
+    }
+
+    impl NtpUdpSocket for DeadlineSocket {
+        async fn send_to(
+            &self,
+            buf: &[u8],
+            _addr: SocketAddr,
+        ) -> crate::Result<usize> {
+            Ok(buf.len())
+        }
+
+        async fn recv_from(
+            &self,
+            _buf: &mut [u8],
+        ) -> crate::Result<(usize, SocketAddr)> {
+            Err(Error::Network)
+        }
+
+        async fn recv_from_timeout(
+            &self,
+            _buf: &mut [u8],
+            timeout: Duration,
+        ) -> crate::Result<(usize, SocketAddr)> {
+            self.timeout.set(Some(timeout));
+            Err(Error::Timeout)
+        }
+    }
+
+    #[test]
+    fn test_recv_timeout() {
+        let (context, _, _) = request_packet();
+        let socket = DeadlineSocket {
+            timeout: Cell::new(None),
+        };
+        let mut executor = Executor::new();
+
+        assert_eq!(
+            Error::Network,
+            executor
+                .block_on(crate::get_time(SERVER, &socket, context))
+                .unwrap_err()
+        );
+        assert_eq!(None, socket.timeout.get());
+
+        let context = context.with_recv_timeout(Duration::from_millis(300));
+        assert_eq!(
+            Error::Timeout,
+            executor
+                .block_on(crate::get_time(SERVER, &socket, context))
+                .unwrap_err()
+        );
+        assert_eq!(Some(Duration::from_millis(300)), socket.timeout.get());
+    }
+
+    fn assert_send<T: Send>(_: T) {}
+
+    #[test]
+    fn test_futures_are_send() {
+        let (context, request, response) = request_packet();
+        let socket = MockSocket {
+            addr: SERVER,
+            response: RawNtpPacket::from(&response),
+        };
+
+        assert_send(crate::get_time(SERVER, &socket, context));
+        assert_send(sntp_send_request(SERVER, &socket, context));
+        assert_send(crate::sntp_process_response(
+            SERVER, &socket, context, request,
+        ));
+        assert_send(get_time_any(&[SERVER], &socket, context));
+        assert_send(get_time_multi(&[SERVER], &socket, context));
+        assert_send(get_time_with_retry(SERVER, &socket, context, 3));
+    }
+
+    #[test]
+    fn test_process_response_units() {
+        let (context, request, mut response) = request_packet();
+        // the server clock is 100 s ahead, the response takes 0.2 s to arrive
+        response.recv_timestamp += 100 << 32;
+        response.tx_timestamp += 100 << 32;
+        let recv_timestamp = response.origin_timestamp + 858_993_460;
+
+        let micros = process_response(
+            request,
+            RawNtpPacket::from(&response),
+            recv_timestamp,
+            &context,
+        )
+        .unwrap();
+        let millis = process_response(
+            request,
+            RawNtpPacket::from(&response),
+            recv_timestamp,
+            &NtpContext::builder(context.timestamp_gen)
+                .units(Units::Milliseconds)
+                .build(),
+        )
+        .unwrap();
+
+        assert_eq!(Units::Microseconds, micros.units());
+        assert_eq!(Units::Milliseconds, millis.units());
+        assert_eq!(200, millis.roundtrip());
+        assert_eq!(micros.roundtrip() / 1000, millis.roundtrip());
+        assert_eq!(micros.offset() / 1000, millis.offset());
+        // the stored values stay in microseconds
+        assert_eq!(micros.roundtrip, millis.roundtrip);
+        assert_eq!(micros.offset, millis.offset);
+        assert_eq!(micros.offset_duration(), millis.offset_duration());
+        assert_eq!(micros.roundtrip_duration(), millis.roundtrip_duration());
+        assert_eq!(micros.metrics(), millis.metrics());
+    }
+
+    #[test]
+    fn test_process_response() {
+        let (context, request, response) = request_packet();
+        let result = process(&context, request, &response);
+
+        assert!(result.is_ok(), "{result:?}");
+        let result = result.unwrap();
+        assert_eq!(1_700_000_000, result.sec());
+        assert_eq!(0, result.offset());
+        assert_eq!(0, result.roundtrip());
+        assert_eq!(1, result.stratum());
+        assert_eq!(6, result.poll());
+    }
+
+    #[test]
+    fn test_process_response_leap_indicator() {
+        let (context, request, mut response) = request_packet();
+
+        let result = process(&context, request, &response).unwrap();
+        assert_eq!(LeapIndicator::NoWarning, result.leap_indicator());
+
+        response.li_vn_mode |= 1 << 6;
+        let result = process(&context, request, &response).unwrap();
+        assert_eq!(LeapIndicator::LastMinute61, result.leap_indicator());
+
+        response.li_vn_mode |= 3 << 6;
+        let result = process(&context, request, &response).unwrap();
+        assert_eq!(LeapIndicator::Unsynchronized, result.leap_indicator());
+    }
+
+    #[test]
+    fn test_process_response_kiss_of_death() {
+        let (context, request, mut response) = request_packet();
+        response.stratum = 0;
+        response.ref_id = u32::from_be_bytes(*b"RATE");
+
+        assert_eq!(
+            Error::KissOfDeath(KissCode::RateExceeded),
+            process(&context, request, &response).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_get_time_with_kod_cache() {
+        let (context, _, mut response) = request_packet();
+        let mut cache = KodCache::<1>::new(Duration::from_secs(30));
+        let mut executor = Executor::new();
+        let valid = MockSocket {
+            addr: SERVER,
+            response: RawNtpPacket::from(&response),
+        };
+        response.stratum = 0;
+        response.ref_id = u32::from_be_bytes(*b"DENY");
+        let denied = MockSocket {
+            addr: SERVER,
+            response: RawNtpPacket::from(&response),
+        };
+
+        assert_eq!(
+            Error::KissOfDeath(KissCode::Deny),
+            executor
+                .block_on(get_time_with_kod_cache(
+                    SERVER, &denied, context, &mut cache
+                ))
+                .unwrap_err()
+        );
+        // the server is not queried anymore, even though it would respond properly now
+        assert_eq!(
+            Error::KissOfDeath(KissCode::Deny),
+            executor
+                .block_on(get_time_with_kod_cache(
+                    SERVER, &valid, context, &mut cache
+                ))
+                .unwrap_err()
+        );
+    }
+
+    /// Socket that responds with the packet followed by `size - 48` bytes of trailing data
+    struct SizedResponseSocket {
+        response: RawNtpPacket,
+        size: usize,
+    }
+
+    impl NtpUdpSocket for SizedResponseSocket {
+        async fn send_to(
+            &self,
+            buf: &[u8],
+            _addr: SocketAddr,
+        ) -> crate::Result<usize> {
+            Ok(buf.len())
+        }
+
+        async fn recv_from(
+            &self,
+            buf: &mut [u8],
+        ) -> crate::Result<(usize, SocketAddr)> {
+            let header = self.response.0.len().min(self.size);
+
+            buf[..header].copy_from_slice(&self.response.0[..header]);
+            buf[header..self.size].fill(0xa5);
+
+            Ok((self.size, SERVER))
+        }
+    }
+
+    #[test]
+    fn test_response_with_extension_fields() {
+        let (context, _, response) = request_packet();
+        let mut executor = Executor::new();
+        // 48 bytes of the header followed by a 20-byte extension
+        let extended = SizedResponseSocket {
+            response: RawNtpPacket::from(&response),
+            size: 68,
+        };
+        let short = SizedResponseSocket {
+            response: RawNtpPacket::from(&response),
+            size: 47,
+        };
+
+        let result = executor
+            .block_on(crate::get_time(SERVER, &extended, context))
+            .unwrap();
+        assert_eq!(1_700_000_000, result.sec());
+        assert_eq!(1, result.stratum());
+        assert_eq!(
+            Error::IncorrectPayload,
+            executor
+                .block_on(crate::get_time(SERVER, &short, context))
+                .unwrap_err()
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_dyn_sockets() {
+        use crate::DynNtpUdpSocket;
+
+        let (context, _, response) = request_packet();
+        let mut executor = Executor::new();
+        let sockets: Vec<Box<dyn DynNtpUdpSocket>> = vec![
+            Box::new(MockSocket {
+                addr: SERVER,
+                response: RawNtpPacket::from(&response),
+            }),
+            Box::new(SizedResponseSocket {
+                response: RawNtpPacket::from(&response),
+                size: 68,
+            }),
+        ];
+
+        for socket in &sockets {
+            let result = executor
+                .block_on(crate::get_time(SERVER, socket.as_ref(), context))
+                .unwrap();
+
+            assert_eq!(1_700_000_000, result.sec());
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_dyn_socket_recv_timeout() {
+        use crate::DynNtpUdpSocket;
+
+        let (context, _, _) = request_packet();
+        let socket = DeadlineSocket {
+            timeout: Cell::new(None),
+        };
+        let dyn_socket: &dyn DynNtpUdpSocket = &&socket;
+        let context = context.with_recv_timeout(Duration::from_millis(300));
+
+        assert_eq!(
+            Error::Timeout,
+            Executor::new()
+                .block_on(crate::get_time(SERVER, dyn_socket, context))
+                .unwrap_err()
+        );
+        assert_eq!(Some(Duration::from_millis(300)), socket.timeout.get());
+    }
+
+    /// Socket that requires mutable access and counts the datagrams passed through it
+    struct MutOnlySocket {
+        response: RawNtpPacket,
+        sent: usize,
+        received: usize,
+    }
+
+    impl NtpUdpSocketMut for MutOnlySocket {
+        async fn send_to(
+            &mut self,
+            buf: &[u8],
+            _addr: SocketAddr,
+        ) -> crate::Result<usize> {
+            self.sent += 1;
+
+            Ok(buf.len())
+        }
+
+        async fn recv_from(
+            &mut self,
+            buf: &mut [u8],
+        ) -> crate::Result<(usize, SocketAddr)> {
+            self.received += 1;
+            buf[..self.response.0.len()].copy_from_slice(&self.response.0);
+
+            Ok((self.response.0.len(), SERVER))
+        }
+    }
+
+    #[test]
+    fn test_get_time_mut() {
+        let (context, _, response) = request_packet();
+        let mut executor = Executor::new();
+        let mut socket = MutOnlySocket {
+            response: RawNtpPacket::from(&response),
+            sent: 0,
+            received: 0,
+        };
+
+        let result = executor
+            .block_on(crate::get_time_mut(SERVER, &mut socket, context))
+            .unwrap();
+        assert_eq!(1_700_000_000, result.sec());
+
+        let request = executor
+            .block_on(crate::sntp_send_request_mut(
+                SERVER,
+                &mut socket,
+                context,
+            ))
+            .unwrap();
+        let result = executor
+            .block_on(crate::sntp_process_response_mut(
+                SERVER,
+                &mut socket,
+                context,
+                request,
+            ))
+            .unwrap();
+        assert_eq!(1, result.stratum());
+        assert_eq!((2, 2), (socket.sent, socket.received));
+    }
+
+    /// Socket that is only able to send requests
+    struct SendOnlySocket(Cell<usize>);
+
+    impl NtpSendSocket for SendOnlySocket {
+        async fn send_to(
+            &self,
+            buf: &[u8],
+            _addr: SocketAddr,
+        ) -> crate::Result<usize> {
+            self.0.set(self.0.get() + 1);
+
+            Ok(buf.len())
+        }
+    }
+
+    /// Socket that is only able to receive responses
+    struct RecvOnlySocket(RawNtpPacket);
+
+    impl NtpRecvSocket for RecvOnlySocket {
+        async fn recv_from(
+            &self,
+            buf: &mut [u8],
+        ) -> crate::Result<(usize, SocketAddr)> {
+            buf[..self.0 .0.len()].copy_from_slice(&self.0 .0);
+
+            Ok((self.0 .0.len(), SERVER))
+        }
+    }
+
+    #[test]
+    fn test_split_socket() {
+        let (context, _, response) = request_packet();
+        let mut executor = Executor::new();
+        let tx = SendOnlySocket(Cell::new(0));
+        let rx = RecvOnlySocket(RawNtpPacket::from(&response));
+
+        let request = executor
+            .block_on(sntp_send_request(SERVER, &tx, context))
+            .unwrap();
+        let result = executor
+            .block_on(crate::sntp_process_response(
+                SERVER, &rx, context, request,
+            ))
+            .unwrap();
+
+        assert_eq!(1, tx.0.get());
+        assert_eq!(1_700_000_000, result.sec());
+    }
+
+    struct PartialSendSocket(usize);
+
+    impl NtpUdpSocket for PartialSendSocket {
+        async fn send_to(
+            &self,
+            _buf: &[u8],
+            _addr: SocketAddr,
+        ) -> crate::Result<usize> {
+            Ok(self.0)
+        }
+
+        async fn recv_from(
+            &self,
+            _buf: &mut [u8],
+        ) -> crate::Result<(usize, SocketAddr)> {
+            Err(Error::Network)
+        }
+    }
+
+    #[test]
+    fn test_send_request_partial_send() {
+        let (context, _, _) = request_packet();
+        let mut executor = Executor::new();
+
+        for sent in [0, 1, 47] {
+            let socket = PartialSendSocket(sent);
+
+            assert_eq!(
+                Error::PartialSend,
+                executor
+                    .block_on(sntp_send_request(SERVER, &socket, context))
+                    .unwrap_err()
+            );
+        }
+
+        let socket = PartialSendSocket(48);
+        assert!(executor
+            .block_on(sntp_send_request(SERVER, &socket, context))
+            .is_ok());
+    }
+
+    /// Socket that fails to send with the given error
+    struct FailingSendSocket(Error);
+
+    impl NtpUdpSocket for FailingSendSocket {
+        async fn send_to(
+            &self,
+            _buf: &[u8],
+            _addr: SocketAddr,
+        ) -> crate::Result<usize> {
+            Err(self.0)
+        }
+
+        async fn recv_from(
+            &self,
+            _buf: &mut [u8],
+        ) -> crate::Result<(usize, SocketAddr)> {
+            Err(Error::Network)
+        }
+    }
+
+    #[test]
+    fn test_send_request_error_kind_preserved() {
+        let (context, _, _) = request_packet();
+        let mut executor = Executor::new();
+
+        for error in [Error::NoRoute, Error::SocketNotBound, Error::Network] {
+            let socket = FailingSendSocket(error);
+
+            assert_eq!(
+                error,
+                executor
+                    .block_on(sntp_send_request(SERVER, &socket, context))
+                    .unwrap_err()
+            );
+        }
+
+        // unreachable servers are skipped by the multi-server helpers
+        assert_eq!(
+            Error::NoRoute,
+            executor
+                .block_on(get_time_multi(
+                    &servers(),
+                    &FailingSendSocket(Error::NoRoute),
+                    context
+                ))
+                .unwrap_err()
+        );
+    }
+
+    /// Socket that times out for all servers except the `alive` one
+    struct MultiServerSocket {
+        alive: SocketAddr,
+        response: RawNtpPacket,
+        last_dest: Cell<Option<SocketAddr>>,
+    }
+
+    impl NtpUdpSocket for MultiServerSocket {
+        async fn send_to(
+            &self,
+            buf: &[u8],
+            addr: SocketAddr,
+        ) -> crate::Result<usize> {
+            self.last_dest.set(Some(addr));
+
+            Ok(buf.len())
+        }
+
+        async fn recv_from(
+            &self,
+            buf: &mut [u8],
+        ) -> crate::Result<(usize, SocketAddr)> {
+            match self.last_dest.get() {
+                Some(addr) if addr == self.alive => {
+                    buf[..self.response.0.len()]
+                        .copy_from_slice(&self.response.0);
+
+                    Ok((self.response.0.len(), addr))
+                }
+                _ => Err(Error::Timeout),
+            }
+        }
+    }
+
+    fn servers() -> [SocketAddr; 3] {
+        [1, 2, 3].map(|octet| {
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 1, octet)), 123)
+        })
+    }
+
+    #[test]
+    fn test_get_time_multi() {
+        let (context, _, response) = request_packet();
+        let servers = servers();
+        let socket = MultiServerSocket {
+            alive: servers[2],
+            response: RawNtpPacket::from(&response),
+            last_dest: Cell::new(None),
+        };
+        let mut executor = Executor::new();
+
+        let (addr, result) = executor
+            .block_on(get_time_multi(&servers, &socket, context))
+            .unwrap();
+        assert_eq!(servers[2], addr);
+        assert_eq!(1, result.stratum());
+
+        assert_eq!(
+            Error::Timeout,
+            executor
+                .block_on(get_time_multi(&servers[..2], &socket, context))
+                .unwrap_err()
+        );
+        assert_eq!(
+            Error::AddressResolve,
+            executor
+                .block_on(get_time_multi(&[], &socket, context))
+                .unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_get_time_multi_stops_on_invalid_response() {
+        let (context, _, mut response) = request_packet();
+        let servers = servers();
+        response.stratum = 0;
+        response.ref_id = u32::from_be_bytes(*b"DENY");
+        let socket = MultiServerSocket {
+            alive: servers[0],
+            response: RawNtpPacket::from(&response),
+            last_dest: Cell::new(None),
+        };
+
+        assert_eq!(
+            Error::KissOfDeath(KissCode::Deny),
+            Executor::new()
+                .block_on(get_time_multi(&servers, &socket, context))
+                .unwrap_err()
+        );
+        assert_eq!(Some(servers[0]), socket.last_dest.get());
+    }
+
+    #[test]
+    fn test_get_time_any() {
+        let (context, _, response) = request_packet();
+        let servers = servers();
+        let mut executor = Executor::new();
+        let socket = MultiServerSocket {
+            alive: servers[2],
+            response: RawNtpPacket::from(&response),
+            last_dest: Cell::new(None),
+        };
+
+        let result = executor
+            .block_on(get_time_any(&servers, &socket, context))
+            .unwrap();
+        assert_eq!(1, result.stratum());
+        assert_eq!(Some(servers[2]), socket.last_dest.get());
+
+        assert_eq!(
+            Error::Timeout,
+            executor
+                .block_on(get_time_any(&servers[..2], &socket, context))
+                .unwrap_err()
+        );
+        assert_eq!(
+            Error::AddressResolve,
+            executor
+                .block_on(get_time_any(&[], &socket, context))
+                .unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_get_time_any_skips_invalid_response() {
+        let (context, _, mut response) = request_packet();
+        let servers = servers();
+        response.stratum = 0;
+        response.ref_id = u32::from_be_bytes(*b"DENY");
+        let socket = MultiServerSocket {
+            alive: servers[0],
+            response: RawNtpPacket::from(&response),
+            last_dest: Cell::new(None),
+        };
+
+        // the first server denies access, the rest time out
+        assert_eq!(
+            Error::Timeout,
+            Executor::new()
+                .block_on(get_time_any(&servers, &socket, context))
+                .unwrap_err()
+        );
+        assert_eq!(Some(servers[2]), socket.last_dest.get());
+    }
+
+    /// Socket that accepts requests to every server, but only `alive` ever responds
+    struct SilentServerSocket {
+        alive: SocketAddr,
+        response: RawNtpPacket,
+        last_dest: Cell<Option<SocketAddr>>,
+    }
+
+    impl NtpUdpSocket for SilentServerSocket {
+        async fn send_to(
+            &self,
+            buf: &[u8],
+            addr: SocketAddr,
+        ) -> crate::Result<usize> {
+            self.last_dest.set(Some(addr));
+
+            Ok(buf.len())
+        }
+
+        async fn recv_from(
+            &self,
+            buf: &mut [u8],
+        ) -> crate::Result<(usize, SocketAddr)> {
+            match self.last_dest.get() {
+                Some(addr) if addr == self.alive => {
+                    buf[..self.response.0.len()]
+                        .copy_from_slice(&self.response.0);
+
+                    Ok((self.response.0.len(), addr))
+                }
+                _ => core::future::pending().await,
+            }
+        }
+    }
+
+    #[test]
+    fn test_get_time_any_with_timeout() {
+        let (context, _, response) = request_packet();
+        let servers = servers();
+        let mut executor = Executor::new();
+        let socket = SilentServerSocket {
+            alive: servers[1],
+            response: RawNtpPacket::from(&response),
+            last_dest: Cell::new(None),
+        };
+        let timeouts = Cell::new(0);
+        let timeout = || {
+            timeouts.set(timeouts.get() + 1);
+            async {}
+        };
+
+        // the first server accepts the request, but never answers
+        let result = executor
+            .block_on(get_time_any_with_timeout(
+                &servers, &socket, context, timeout,
+            ))
+            .unwrap();
+        assert_eq!(1, result.stratum());
+        assert_eq!(Some(servers[1]), socket.last_dest.get());
+        assert_eq!(Some(servers[1]), result.source());
+        assert_eq!(2, timeouts.get());
+
+        assert_eq!(
+            Error::Timeout,
+            executor
+                .block_on(get_time_any_with_timeout(
+                    &servers[..1],
+                    &socket,
+                    context,
+                    || async {}
+                ))
+                .unwrap_err()
+        );
+        assert_eq!(
+            Error::AddressResolve,
+            executor
+                .block_on(get_time_any_with_timeout(
+                    &[],
+                    &socket,
+                    context,
+                    || async {}
+                ))
+                .unwrap_err()
+        );
+    }
+
+    /// Resolver that resolves every host to the given addresses
+    struct MockResolver<'a>(&'a [SocketAddr]);
+
+    impl NtpHostResolver for MockResolver<'_> {
+        async fn resolve(
+            &self,
+            host: &str,
+            port: u16,
+        ) -> crate::Result<impl Iterator<Item = SocketAddr>> {
+            if host.is_empty() {
+                return Err(Error::AddressResolve);
+            }
+
+            Ok(self
+                .0
+                .iter()
+                .map(move |addr| SocketAddr::new(addr.ip(), port)))
+        }
+    }
+
+    #[test]
+    fn test_get_time_by_name() {
+        let (context, _, response) = request_packet();
+        let servers = servers();
+        let mut executor = Executor::new();
+        let socket = MultiServerSocket {
+            alive: servers[1],
+            response: RawNtpPacket::from(&response),
+            last_dest: Cell::new(None),
+        };
+        let resolver = MockResolver(&servers);
+
+        let result = executor
+            .block_on(get_time_by_name(
+                "pool.ntp.org",
+                &resolver,
+                &socket,
+                context,
+            ))
+            .unwrap();
+        assert_eq!(1, result.stratum());
+        assert_eq!(Some(servers[1]), socket.last_dest.get());
+
+        assert_eq!(
+            Error::AddressResolve,
+            executor
+                .block_on(get_time_by_name("", &resolver, &socket, context))
+                .unwrap_err()
+        );
+        assert_eq!(
+            Error::AddressResolve,
+            executor
+                .block_on(get_time_by_name(
+                    "pool.ntp.org",
+                    &MockResolver(&[]),
+                    &socket,
+                    context
+                ))
+                .unwrap_err()
+        );
+    }
+
+    /// Resolver that publishes the given SRV targets for `example.com` and resolves every host
+    /// name to the given addresses
+    struct MockSrvResolver<'a> {
+        targets: &'a [SrvTarget],
+        hosts: MockResolver<'a>,
+    }
+
+    impl NtpHostResolver for MockSrvResolver<'_> {
+        async fn resolve(
+            &self,
+            host: &str,
+            port: u16,
+        ) -> crate::Result<impl Iterator<Item = SocketAddr>> {
+            self.hosts.resolve(host, port).await
+        }
+    }
+
+    impl NtpSrvResolver for MockSrvResolver<'_> {
+        async fn resolve_srv(
+            &self,
+            name: &str,
+        ) -> crate::Result<impl Iterator<Item = SrvTarget>> {
+            if name != "_ntp._udp.example.com" {
+                return Err(Error::AddressResolve);
+            }
+
+            Ok(self.targets.iter().copied())
+        }
+    }
+
+    #[test]
+    fn test_get_time_by_srv_order() {
+        let (context, _, response) = request_packet();
+        let servers = servers();
+        let mut executor = Executor::new();
+        let target = |priority, weight, addr| SrvTarget {
+            priority,
+            weight,
+            addr,
+        };
+        let resolver = MockSrvResolver {
+            targets: &[
+                target(20, 0, servers[0]),
+                target(10, 5, servers[1]),
+                target(10, 50, servers[2]),
+            ],
+            hosts: MockResolver(&[]),
+        };
+        let socket = MultiServerSocket {
+            alive: servers[1],
+            response: RawNtpPacket::from(&response),
+            last_dest: Cell::new(None),
+        };
+
+        let result = executor
+            .block_on(get_time_by_srv(
+                "example.com",
+                &resolver,
+                &socket,
+                context,
+            ))
+            .unwrap();
+        assert_eq!(1, result.stratum());
+        assert_eq!(Some(servers[1]), socket.last_dest.get());
+
+        // the target with the highest priority value is tried last
+        let socket = MultiServerSocket {
+            alive: SERVER,
+            response: RawNtpPacket::from(&response),
+            last_dest: Cell::new(None),
+        };
+        assert_eq!(
+            Error::Timeout,
+            executor
+                .block_on(get_time_by_srv(
+                    "example.com",
+                    &resolver,
+                    &socket,
+                    context
+                ))
+                .unwrap_err()
+        );
+        assert_eq!(Some(servers[0]), socket.last_dest.get());
+    }
+
+    #[test]
+    fn test_get_time_by_srv_fallback() {
+        let (context, _, response) = request_packet();
+        let servers = servers();
+        let mut executor = Executor::new();
+        let resolver = MockSrvResolver {
+            targets: &[],
+            hosts: MockResolver(&servers[..1]),
+        };
+        let socket = MultiServerSocket {
+            alive: servers[0],
+            response: RawNtpPacket::from(&response),
+            last_dest: Cell::new(None),
+        };
+
+        // no SRV records are published
+        assert!(executor
+            .block_on(get_time_by_srv(
+                "example.com",
+                &resolver,
+                &socket,
+                context
+            ))
+            .is_ok());
+        // SRV lookup fails
+        assert!(executor
+            .block_on(get_time_by_srv(
+                "example.org",
+                &resolver,
+                &socket,
+                context
+            ))
+            .is_ok());
+        // the SRV name does not fit into a DNS name
+        assert_eq!(
+            Error::AddressResolve,
+            executor
+                .block_on(get_time_by_srv(
+                    &"a".repeat(250),
+                    &resolver,
+                    &socket,
+                    context
+                ))
+                .unwrap_err()
+        );
+    }
+
+    /// Socket that never receives a response
+    #[cfg(feature = "embassy-socket")]
+    struct SilentSocket;
+
+    #[cfg(feature = "embassy-socket")]
+    impl NtpUdpSocket for SilentSocket {
+        async fn send_to(
+            &self,
+            buf: &[u8],
+            _addr: SocketAddr,
+        ) -> crate::Result<usize> {
+            Ok(buf.len())
+        }
+
+        async fn recv_from(
+            &self,
+            _buf: &mut [u8],
+        ) -> crate::Result<(usize, SocketAddr)> {
+            core::future::pending().await
+        }
+    }
+
+    #[cfg(feature = "embassy-socket")]
+    #[test]
+    fn test_embassy_get_time_with_timeout() {
+        use crate::embassy::get_time_with_timeout;
+
+        let (context, _, response) = request_packet();
+        let servers = servers();
+        let mut executor = Executor::new();
+        let socket = MultiServerSocket {
+            alive: servers[1],
+            response: RawNtpPacket::from(&response),
+            last_dest: Cell::new(None),
+        };
+        let timeout = embassy_time::Duration::from_millis(10);
+
+        let result = executor
+            .block_on(get_time_with_timeout(
+                &servers, &socket, context, timeout,
+            ))
+            .unwrap();
+        assert_eq!(1, result.stratum());
+        assert_eq!(Some(servers[1]), socket.last_dest.get());
+
+        assert_eq!(
+            Error::Timeout,
+            executor
+                .block_on(get_time_with_timeout(
+                    &servers,
+                    &SilentSocket,
+                    context,
+                    timeout
+                ))
+                .unwrap_err()
+        );
+    }
+
+    #[cfg(feature = "embassy-socket")]
+    #[test]
+    fn test_embassy_timestamp_gen() {
+        use crate::embassy::EmbassyTimestampGen;
+
+        let mut timestamp_gen = EmbassyTimestampGen::default();
+        let before = embassy_time::Instant::now().as_micros();
+        timestamp_gen.init();
+        let after = embassy_time::Instant::now().as_micros();
+
+        let micros = timestamp_gen.timestamp_sec() * 1_000_000
+            + u64::from(timestamp_gen.timestamp_subsec_micros());
+        assert!((before..=after).contains(&micros));
+        assert!(timestamp_gen.timestamp_subsec_micros() < 1_000_000);
+    }
+
+    #[cfg(feature = "sync")]
+    #[test]
+    fn test_sync_get_time_multi() {
+        let (context, _, response) = request_packet();
+        let servers = servers();
+        let socket = MultiServerSocket {
+            alive: servers[1],
+            response: RawNtpPacket::from(&response),
+            last_dest: Cell::new(None),
+        };
+
+        let (addr, _) =
+            crate::sync::get_time_multi(&servers, &socket, context).unwrap();
+        assert_eq!(servers[1], addr);
+    }
+
+    /// Socket that times out for the first `failures` responses
+    struct FlakySocket {
+        failures: Cell<u32>,
+        response: RawNtpPacket,
+    }
+
+    impl NtpUdpSocket for FlakySocket {
+        async fn send_to(
+            &self,
+            buf: &[u8],
+            _addr: SocketAddr,
+        ) -> crate::Result<usize> {
+            Ok(buf.len())
+        }
+
+        async fn recv_from(
+            &self,
+            buf: &mut [u8],
+        ) -> crate::Result<(usize, SocketAddr)> {
+            if self.failures.get() > 0 {
+                self.failures.set(self.failures.get() - 1);
+                return Err(Error::Timeout);
+            }
+
+            buf[..self.response.0.len()].copy_from_slice(&self.response.0);
+
+            Ok((self.response.0.len(), SERVER))
+        }
+    }
+
+    #[test]
+    fn test_get_time_with_retry() {
+        let (context, _, response) = request_packet();
+        let socket = FlakySocket {
+            failures: Cell::new(3),
+            response: RawNtpPacket::from(&response),
+        };
+        let delays = RefCell::new([0u32; 4]);
+        let attempts = Cell::new(0usize);
+        let delay = |ms| {
+            delays.borrow_mut()[attempts.get()] = ms;
+            attempts.set(attempts.get() + 1);
+
+            async {}
+        };
+        let mut executor = Executor::new();
+
+        let result = executor.block_on(get_time_with_retry_and_delay(
+            SERVER, &socket, context, 4, 100, delay,
+        ));
+        assert!(result.is_ok());
+        assert_eq!([100, 200, 400, 0], *delays.borrow());
+
+        socket.failures.set(2);
+        assert_eq!(
+            Error::Timeout,
+            executor
+                .block_on(get_time_with_retry(SERVER, &socket, context, 2))
+                .unwrap_err()
+        );
+        assert!(executor
+            .block_on(get_time_with_retry(SERVER, &socket, context, 0))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_get_time_with_retry_kiss_of_death() {
+        let (context, _, mut response) = request_packet();
+        response.stratum = 0;
+        response.ref_id = u32::from_be_bytes(*b"RSTR");
+        let socket = FlakySocket {
+            failures: Cell::new(0),
+            response: RawNtpPacket::from(&response),
+        };
+        let attempts = Cell::new(0);
+
+        assert_eq!(
+            Error::KissOfDeath(KissCode::Restricted),
+            Executor::new()
+                .block_on(get_time_with_retry_and_delay(
+                    SERVER,
+                    &socket,
+                    context,
+                    5,
+                    100,
+                    |_| {
+                        attempts.set(attempts.get() + 1);
+                        async {}
+                    }
+                ))
+                .unwrap_err()
+        );
+        assert_eq!(0, attempts.get());
+    }
+
+    #[test]
+    fn test_process_response_v3_rejected_by_default() {
+        let (context, request, mut response) = request_packet();
+        response.li_vn_mode = 4 | (3 << 3);
+
+        assert_eq!(
+            Error::IncorrectResponseVersion,
+            process(&context, request, &response).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_process_response_v3_accepted() {
+        let (context, request, mut response) = request_packet();
+        let context =
+            context.with_accepted_versions(VersionMask::V3 | VersionMask::V4);
+        response.li_vn_mode = 4 | (3 << 3);
+
+        assert!(process(&context, request, &response).is_ok());
+
+        response.li_vn_mode = 4 | (2 << 3);
+        assert_eq!(
+            Error::IncorrectResponseVersion,
+            process(&context, request, &response).unwrap_err()
+        );
+    }
+
+    #[cfg(feature = "request-parts")]
+    #[test]
+    fn test_send_request_result_from_parts() {
+        let (context, request, response) = request_packet();
+        let restored = SendRequestResult::from_parts(
+            request.originate_timestamp(),
+            request.version(),
+        );
+
+        assert_eq!(request, restored);
+        assert!(process(&context, restored, &response).is_ok());
+        assert_eq!(
+            Error::IncorrectOriginTimestamp,
+            process(
+                &context,
+                SendRequestResult::from_parts(
+                    request.originate_timestamp() + 1,
+                    request.version()
+                ),
+                &response
+            )
+            .unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_process_buffer() {
+        const NTP_SEC: u64 = 1 << 32;
+
+        let (context, request, response) = request_packet();
+        let mut buf = [0u8; 60];
+        buf[..48].copy_from_slice(&RawNtpPacket::from(&response).0);
+        let other =
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)), 123);
+
+        // extension fields past the header are ignored
+        let result =
+            crate::sntp_process_buffer(SERVER, SERVER, &buf, context, request)
+                .unwrap();
+        assert_eq!(1_700_000_000, result.sec());
+
+        let result = crate::sntp_process_buffer_with_recv_ts(
+            SERVER,
+            SERVER,
+            &buf[..48],
+            context,
+            request,
+            response.tx_timestamp + NTP_SEC,
+        )
+        .unwrap();
+        assert_eq!(1_000_000, result.roundtrip());
+
+        assert_eq!(
+            Error::ResponseAddressMismatch,
+            crate::sntp_process_buffer(SERVER, other, &buf, context, request)
+                .unwrap_err()
+        );
+        assert!(crate::sntp_process_buffer(
+            SERVER,
+            other,
+            &buf,
+            context.with_address_verification(false),
+            request
+        )
+        .is_ok());
+        assert_eq!(
+            Error::IncorrectPayload,
+            crate::sntp_process_buffer(
+                SERVER,
+                SERVER,
+                &buf[..47],
+                context,
+                request
+            )
+            .unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_process_response_zero_tx_timestamp() {
+        let (context, request, mut response) = request_packet();
+        response.tx_timestamp = 0;
+
+        assert_eq!(
+            Error::InvalidTransmitTimestamp,
+            process(&context, request, &response).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_process_response_mode() {
+        let (context, request, mut response) = request_packet();
+        response.li_vn_mode = 5 | (4 << 3);
+
+        assert_eq!(
+            Error::IncorrectMode,
+            process(&context, request, &response).unwrap_err()
+        );
+
+        response.li_vn_mode = 4 | (4 << 3);
+        assert!(process(&context, request, &response).is_ok());
+    }
+
+    #[test]
+    fn test_process_response_with_recv_ts() {
+        const NTP_SEC: u64 = 1 << 32;
+
+        let (context, request, mut response) = request_packet();
+        let t1 = response.tx_timestamp;
+        response.recv_timestamp = t1 + NTP_SEC;
+        response.tx_timestamp = t1 + NTP_SEC;
+
+        let socket = MockSocket {
+            addr: SERVER,
+            response: RawNtpPacket::from(&response),
+        };
+        let result = Executor::new()
+            .block_on(sntp_process_response_with_recv_ts(
+                SERVER,
+                &socket,
+                context,
+                request,
+                t1 + 2 * NTP_SEC,
+            ))
+            .unwrap();
+
+        assert_eq!(0, result.offset());
+        assert_eq!(2_000_000, result.roundtrip());
+    }
+
+    #[test]
+    fn test_process_response_v3_request() {
+        let context = NtpContext::new(FixedTimestampGen::new(1_700_000_000, 0))
+            .with_version(3);
+        let request = NtpPacket::new(context.timestamp_gen, context.version);
+        let mut response = response_packet(&request);
+        assert_eq!(3 | (3 << 3), request.li_vn_mode);
+        let tx_timestamp = request.tx_timestamp;
+        let request = SendRequestResult::from(request);
+        assert_eq!(3, request.version());
+        assert_eq!(tx_timestamp, request.originate_timestamp());
+
+        response.li_vn_mode = 4 | (3 << 3);
+        assert!(process(&context, request, &response).is_ok());
+
+        response.li_vn_mode = 4 | (2 << 3);
+        assert_eq!(
+            Error::IncorrectResponseVersion,
+            process(&context, request, &response).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_listen_broadcast() {
+        const NTP_SEC: u64 = 1 << 32;
+        let (context, _, mut packet) = request_packet();
+        let mut executor = Executor::new();
+        let recv_ts = ntp_timestamp_from_gen(&context.timestamp_gen);
+        // unsolicited packet has no origin timestamp, the server clock is 2 seconds ahead
+        packet.li_vn_mode = 5 | (4 << 3);
+        packet.origin_timestamp = 0;
+        packet.tx_timestamp = recv_ts + 2 * NTP_SEC;
+        let socket = MockSocket {
+            addr: SERVER,
+            response: RawNtpPacket::from(&packet),
+        };
+
+        let result = executor
+            .block_on(listen_broadcast(&socket, context))
+            .unwrap();
+        assert_eq!(2_000_000, result.offset());
+        assert_eq!(0, result.roundtrip());
+        assert_eq!(1_700_000_002, result.sec());
+
+        // unicast responses are not accepted
+        packet.li_vn_mode = 4 | (4 << 3);
+        let socket = MockSocket {
+            addr: SERVER,
+            response: RawNtpPacket::from(&packet),
+        };
+        assert_eq!(
+            Error::IncorrectMode,
+            executor
+                .block_on(listen_broadcast(&socket, context))
+                .unwrap_err()
+        );
+
+        packet.li_vn_mode = 5 | (2 << 3);
+        let socket = MockSocket {
+            addr: SERVER,
+            response: RawNtpPacket::from(&packet),
+        };
+        assert_eq!(
+            Error::IncorrectResponseVersion,
+            executor
+                .block_on(listen_broadcast(&socket, context))
+                .unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_listen_broadcast_from() {
+        let (context, _, mut packet) = request_packet();
+        let mut executor = Executor::new();
+        packet.li_vn_mode = 5 | (4 << 3);
+        packet.origin_timestamp = 0;
+        let socket = MockSocket {
+            addr: SERVER,
+            response: RawNtpPacket::from(&packet),
+        };
+        let other = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+
+        let result = executor.block_on(sntp_listen_broadcast_from(
+            &socket,
+            context,
+            &[other, SERVER.ip()],
+        ));
+        assert_eq!(1_700_000_000, result.unwrap().sec());
+        assert_eq!(
+            Error::ResponseAddressMismatch,
+            executor
+                .block_on(sntp_listen_broadcast_from(
+                    &socket,
+                    context,
+                    &[other]
+                ))
+                .unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_request_packet_version() {
+        let context = NtpContext::new(FixedTimestampGen::new(1_700_000_000, 0));
+        let request = NtpPacket::new(context.timestamp_gen, context.version);
+        assert_eq!(0x23, RawNtpPacket::from(&request).0[0]);
+
+        let context = context.with_version(NtpVersion::V3);
+        let request = NtpPacket::new(context.timestamp_gen, context.version);
+        assert_eq!(0x1b, RawNtpPacket::from(&request).0[0]);
+    }
+
+    /// Socket that remembers the last sent packet
+    #[derive(Default)]
+    struct CaptureSocket {
+        sent: RefCell<RawNtpPacket>,
+    }
+
+    impl NtpUdpSocket for CaptureSocket {
+        async fn send_to(
+            &self,
+            buf: &[u8],
+            _addr: SocketAddr,
+        ) -> crate::Result<usize> {
+            self.sent.borrow_mut().0.copy_from_slice(buf);
+
+            Ok(buf.len())
+        }
+
+        async fn recv_from(
+            &self,
+            _buf: &mut [u8],
+        ) -> crate::Result<(usize, SocketAddr)> {
+            Err(Error::Network)
+        }
+    }
+
+    #[test]
+    fn test_send_request_ref_id() {
+        let (context, _, _) = request_packet();
+        let mut executor = Executor::new();
+        let socket = CaptureSocket::default();
+
+        executor
+            .block_on(sntp_send_request(SERVER, &socket, context))
+            .unwrap();
+        assert_eq!([0; 4], socket.sent.borrow().0[12..16]);
+
+        let context = context.with_ref_id(RefId::Ascii(*b"TEST"));
+        executor
+            .block_on(sntp_send_request(SERVER, &socket, context))
+            .unwrap();
+        assert_eq!(*b"TEST", socket.sent.borrow().0[12..16]);
+
+        let context =
+            context.with_ref_id(RefId::Ipv4(Ipv4Addr::new(192, 168, 1, 10)));
+        executor
+            .block_on(sntp_send_request(SERVER, &socket, context))
+            .unwrap();
+        assert_eq!([192, 168, 1, 10], socket.sent.borrow().0[12..16]);
+    }
+
+    #[test]
+    fn test_send_custom_request() {
+        let (context, _, _) = request_packet();
+        let mut executor = Executor::new();
+        let socket = CaptureSocket::default();
+        let request = NtpRequest::builder()
+            .version(NtpVersion::V3)
+            .poll(6)
+            .precision(-20)
+            .ref_id(RefId::Ascii(*b"TEST"))
+            .build();
+
+        let send_req_result = executor
+            .block_on(crate::sntp_send_custom_request(
+                SERVER, &request, &socket, context,
+            ))
+            .unwrap();
+        let sent = *socket.sent.borrow();
+        // NTPv3 client request
+        assert_eq!([0x1b, 0, 6, 0xec], sent.0[..4]);
+        assert_eq!(*b"TEST", sent.0[12..16]);
+
+        let mut response = response_packet(&NtpPacket::new(
+            context.timestamp_gen,
+            NtpPacket::SNTP_VERSION,
+        ));
+        response.li_vn_mode = 4 | (3 << 3);
+        assert!(process(&context, send_req_result, &response).is_ok());
+    }
+
+    #[cfg(feature = "test-utils")]
+    #[test]
+    fn test_send_custom_request_mode() {
+        let (context, _, _) = request_packet();
+        let socket = CaptureSocket::default();
+        let request = NtpRequest::builder()
+            .raw_version(7)
+            .mode(1)
+            .stratum(2)
+            .build();
+
         Executor::new()
-            .block_on(crate::sntp_send_request(dest, socket, context))
+            .block_on(crate::sntp_send_custom_request(
+                SERVER, &request, &socket, context,
+            ))
+            .unwrap();
+        assert_eq!([7 << 3 | 1, 2], socket.sent.borrow().0[..2]);
     }
 
-    /// Processes the response from an SNTP server and calculates the NTP offset and round-trip delay.
-    ///
-    /// This is a synchronous wrapper around the asynchronous SNTP response processing function.
-    /// It uses an executor to block the current thread while waiting for the underlying
-    /// asynchronous operation to complete.
-    ///
-    /// # Arguments
-    ///
-    /// - `dest` - The destination NTP server's socket address from which the response was received.
-    /// - `socket` - A reference to an object implementing the [`NtpUdpSocket`] trait used for network communication.
-    /// - `context` - The SNTP client context (implementing [`NtpTimestampGenerator`]) responsible for generating and validating timestamps.
-    /// - `send_req_result` - The result obtained from sending the SNTP request, including the originate timestamp.
-    ///
-    /// # Errors
-    ///
-    /// Returns an `Err` if the underlying async SNTP response processing fails for any reason,
-    /// such as:
-    /// - Incorrect origin timestamp in the response,
-    /// - An invalid mode in the response (`SNTP_UNICAST` or `SNTP_BROADCAST`),
-    /// - A mismatch between the request and response versions,
-    /// - Errors in the response headers (e.g., incorrect stratum, leap indicator),
-    /// - Network errors during processing.
-    ///
-    /// # Examples
-    ///
-    /// ```
-    /// use sntpc::{self, NtpContext, NtpTimestampGenerator, Result, SendRequestResult};
-    /// # use core::future::Future;
-    /// # use std::time::Duration;
-    /// # use std::str::FromStr;
-    /// # #[cfg(feature = "std")]
-    /// # use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
-    /// # #[derive(Debug)]
-    /// # struct UdpSocketWrapper(UdpSocket);
-    /// #
-    /// # impl sntpc::NtpUdpSocket for UdpSocketWrapper {
-    /// #     async fn send_to(
-    /// #         &self,
-    /// #         buf: &[u8],
-    /// #         addr: SocketAddr,
-    /// #     ) -> Result<usize> {
-    /// #         self.0.send_to(buf, addr).map_err(|_| sntpc::Error::Network)
-    /// #     }
-    /// #
-    /// #     async fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddr)> {
-    /// #         self.0.recv_from(buf).map_err(|_| sntpc::Error::Network)
-    /// #     }
-    /// # }
-    /// # #[derive(Copy, Clone, Default)]
-    /// # struct StdTimestampGen {
-    /// #     duration: Duration,
-    /// # }
-    /// #
-    /// # impl NtpTimestampGenerator for StdTimestampGen {
-    /// #     fn init(&mut self) {
-    /// #         self.duration = std::time::SystemTime::now()
-    /// #             .duration_since(std::time::SystemTime::UNIX_EPOCH)
-    /// #             .unwrap();
-    /// #     }
-    /// #
-    /// #     fn timestamp_sec(&self) -> u64 {
-    /// #         self.duration.as_secs()
-    /// #     }
-    /// #
-    /// #     fn timestamp_subsec_micros(&self) -> u32 {
-    /// #         self.duration.subsec_micros()
-    /// #     }
-    /// # }
-    /// let socket = UdpSocketWrapper(UdpSocket::bind("0.0.0.0:0").unwrap());
-    /// let context = NtpContext::new(StdTimestampGen::default());
-    /// // "time.google.com:123" string here used for the sake of simplicity. In the real app
-    /// // you would want to fix destination address, since string hostname may resolve to
-    /// // different IP addresses
-    /// let addr = "time.google.com:123".to_socket_addrs().unwrap().filter(SocketAddr::is_ipv4).next().unwrap();
-    /// let send_request_result = sntpc::sync::sntp_send_request(addr, &socket, context).unwrap();
-    /// let result = sntpc::sync::sntp_process_response(addr, &socket, context, send_request_result);
-    ///
-    /// match result {
-    ///     Ok(ntp_result) => println!("NTP Result: {:?}", ntp_result),
-    ///     Err(e) => eprintln!("Failed to process response: {:?}", e),
-    /// }
-    /// ```
-    pub fn sntp_process_response<U, T>(
-        dest: net::SocketAddr,
-        socket: &U,
-        context: NtpContext<T>,
-        send_req_result: SendRequestResult,
-    ) -> Result<NtpResult>
-    where
-        U: NtpUdpSocket,
-        T: NtpTimestampGenerator + Copy,
-    {
-        Executor::new().block_on(crate::sntp_process_response(
-            dest,
-            socket,
+    struct QueueSocket<const N: usize> {
+        responses: [(RawNtpPacket, SocketAddr); N],
+        next: Cell<usize>,
+    }
+
+    impl<const N: usize> NtpUdpSocket for QueueSocket<N> {
+        async fn send_to(
+            &self,
+            buf: &[u8],
+            _addr: SocketAddr,
+        ) -> crate::Result<usize> {
+            Ok(buf.len())
+        }
+
+        async fn recv_from(
+            &self,
+            buf: &mut [u8],
+        ) -> crate::Result<(usize, SocketAddr)> {
+            let idx = self.next.get();
+            let (response, addr) =
+                self.responses.get(idx).ok_or(Error::Network)?;
+            self.next.set(idx + 1);
+            buf[..response.0.len()].copy_from_slice(&response.0);
+
+            Ok((response.0.len(), *addr))
+        }
+    }
+
+    /// Responses to the request of [`request_packet`] reporting the given clock offsets
+    fn offset_responses<const N: usize>(offsets: [i64; N]) -> QueueSocket<N> {
+        const NTP_SEC: i64 = 1 << 32;
+
+        QueueSocket {
+            responses: offsets.map(|offset| {
+                let (_, _, mut response) = request_packet();
+                response.recv_timestamp = response
+                    .recv_timestamp
+                    .wrapping_add_signed(offset * NTP_SEC);
+                response.tx_timestamp = response.recv_timestamp;
+
+                (RawNtpPacket::from(&response), SERVER)
+            }),
+            next: Cell::new(0),
+        }
+    }
+
+    #[test]
+    fn test_get_time_burst() {
+        let (context, _, _) = request_packet();
+        let mut executor = Executor::new();
+
+        let socket = offset_responses([0, 1, 1]);
+        let result = executor
+            .block_on(get_time_burst(
+                SERVER,
+                &socket,
+                context,
+                3,
+                Some(1_000_000),
+            ))
+            .unwrap();
+        assert_eq!(0, result.offset());
+        assert_eq!(3, socket.next.get());
+
+        // wildly varying offsets are rejected only if the spread is limited
+        let socket = offset_responses([0, 5, -3]);
+        assert_eq!(
+            Error::InconsistentSamples,
+            executor
+                .block_on(get_time_burst(
+                    SERVER,
+                    &socket,
+                    context,
+                    3,
+                    Some(1_000_000),
+                ))
+                .unwrap_err()
+        );
+
+        let socket = offset_responses([0, 5, -3]);
+        assert!(executor
+            .block_on(get_time_burst(SERVER, &socket, context, 3, None))
+            .is_ok());
+
+        // a failed request fails the burst
+        let socket = offset_responses([0]);
+        assert_eq!(
+            Error::Network,
+            executor
+                .block_on(get_time_burst(SERVER, &socket, context, 2, None))
+                .unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_process_in_flight_responses_out_of_order() {
+        const SERVER_B: SocketAddr =
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)), 123);
+
+        let context = NtpContext::new(FixedTimestampGen::new(1_700_000_000, 0));
+        let request_a =
+            NtpPacket::new(context.timestamp_gen, NtpPacket::SNTP_VERSION);
+        let request_b = NtpPacket::new(
+            FixedTimestampGen::new(1_700_000_000, 500_000),
+            NtpPacket::SNTP_VERSION,
+        );
+        let mut response_a = response_packet(&request_a);
+        response_a.stratum = 1;
+        let mut response_b = response_packet(&request_b);
+        response_b.stratum = 2;
+
+        let mut in_flight = InFlight::<2>::new();
+        assert!(in_flight.insert(SERVER, SendRequestResult::from(request_a)));
+        assert!(in_flight.insert(SERVER_B, SendRequestResult::from(request_b)));
+        assert_eq!(2, in_flight.len());
+
+        let socket = QueueSocket {
+            responses: [
+                (RawNtpPacket::from(&response_b), SERVER_B),
+                (RawNtpPacket::from(&response_a), SERVER),
+            ],
+            next: Cell::new(0),
+        };
+        let mut executor = Executor::new();
+
+        let (addr, result) = executor
+            .block_on(sntp_process_in_flight_response(
+                &socket,
+                context,
+                &mut in_flight,
+            ))
+            .unwrap();
+        assert_eq!(SERVER_B, addr);
+        assert_eq!(2, result.stratum());
+        assert_eq!(1, in_flight.len());
+
+        let (addr, result) = executor
+            .block_on(sntp_process_in_flight_response(
+                &socket,
+                context,
+                &mut in_flight,
+            ))
+            .unwrap();
+        assert_eq!(SERVER, addr);
+        assert_eq!(1, result.stratum());
+        assert!(in_flight.is_empty());
+    }
+
+    #[test]
+    fn test_process_in_flight_response_mismatch() {
+        let (context, request, response) = request_packet();
+        let mut in_flight = InFlight::<1>::new();
+        assert!(in_flight.insert(SERVER, request));
+        assert!(!in_flight.insert(SERVER, request));
+
+        let unknown = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 123);
+        let socket = QueueSocket {
+            responses: [(RawNtpPacket::from(&response), unknown)],
+            next: Cell::new(0),
+        };
+        let result = Executor::new().block_on(sntp_process_in_flight_response(
+            &socket,
             context,
-            send_req_result,
+            &mut in_flight,
+        ));
+
+        assert_eq!(Error::ResponseAddressMismatch, result.unwrap_err());
+        assert_eq!(Some(SERVER), in_flight.get(request.originate_timestamp));
+    }
+
+    #[test]
+    fn test_context_builder() {
+        let (context, request, mut response) = request_packet();
+        let default = NtpContext::builder(context.timestamp_gen).build();
+
+        assert_eq!(context.accepted_versions, default.accepted_versions);
+        assert_eq!(context.max_stratum, default.max_stratum);
+        assert!(default.verify_address);
+
+        let context = NtpContext::builder(context.timestamp_gen)
+            .accept_version(NtpVersion::V3)
+            .max_stratum(15)
+            .verify_address(false)
+            .build();
+        assert!(context.accepted_versions.contains(3));
+        assert!(context.accepted_versions.contains(4));
+
+        response.stratum = 15;
+        assert!(process(&context, request, &response).is_ok());
+        response.stratum = 16;
+        assert_eq!(
+            Error::IncorrectStratumHeaders,
+            process(&context, request, &response).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_max_stratum() {
+        let (context, request, mut response) = request_packet();
+        let mut process_stratum = |stratum, context: &NtpContext<_>| {
+            response.stratum = stratum;
+            process(context, request, &response)
+        };
+
+        assert_eq!(1, process_stratum(1, &context).unwrap().stratum);
+        assert_eq!(15, process_stratum(15, &context).unwrap().stratum);
+        assert_eq!(
+            Error::IncorrectStratumHeaders,
+            process_stratum(16, &context).unwrap_err()
+        );
+        assert_eq!(
+            Error::IncorrectStratumHeaders,
+            process_stratum(255, &context).unwrap_err()
+        );
+
+        let context = context.with_max_stratum(u8::MAX);
+        assert_eq!(255, process_stratum(255, &context).unwrap().stratum);
+    }
+
+    #[test]
+    fn test_stale_request() {
+        let (context, request, response) = request_packet();
+        let process_at = |recv_timestamp, context: &NtpContext<_>| {
+            process_response(
+                request,
+                RawNtpPacket::from(&response),
+                recv_timestamp,
+                context,
+            )
+        };
+
+        assert!(
+            process_at(response.origin_timestamp + (5 << 32), &context).is_ok()
+        );
+        // 5 s and 1 ms in the NTP timestamp format
+        assert_eq!(
+            Error::StaleRequest,
+            process_at(
+                response.origin_timestamp + (5 << 32) + 4_294_967,
+                &context
+            )
+            .unwrap_err()
+        );
+        // the local clock has been stepped back in the meantime
+        assert!(process_at(response.origin_timestamp - (60 << 32), &context)
+            .is_ok());
+
+        let context = NtpContext::builder(context.timestamp_gen)
+            .max_request_age(Duration::from_secs(30))
+            .build();
+        assert!(process_at(response.origin_timestamp + (30 << 32), &context)
+            .is_ok());
+        assert_eq!(
+            Error::StaleRequest,
+            process_at(response.origin_timestamp + (31 << 32), &context)
+                .unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_max_root_dispersion() {
+        let (context, request, mut response) = request_packet();
+
+        response.root_dispersion = 0xFFFF_FFFF;
+        assert!(process(&context, request, &response).is_ok());
+
+        let context =
+            context.with_client_config(NtpClientConfig::recommended());
+        assert_eq!(
+            Error::RootDispersionExceeded,
+            process(&context, request, &response).unwrap_err()
+        );
+
+        response.root_dispersion = 0x0001_0000;
+        assert!(process(&context, request, &response).is_ok());
+
+        let context = NtpContext::builder(context.timestamp_gen)
+            .client_config(NtpClientConfig {
+                max_root_dispersion: Some(0x0000_8000),
+            })
+            .build();
+        assert_eq!(
+            Error::RootDispersionExceeded,
+            process(&context, request, &response).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_context_without_address_verification() {
+        let (context, _, response) = request_packet();
+        let other = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 123);
+        let socket = MockSocket {
+            addr: other,
+            response: RawNtpPacket::from(&response),
+        };
+        let mut executor = Executor::new();
+
+        assert_eq!(
+            Error::ResponseAddressMismatch,
+            executor
+                .block_on(crate::get_time(SERVER, &socket, context))
+                .unwrap_err()
+        );
+
+        let context = NtpContext::builder(context.timestamp_gen)
+            .verify_address(false)
+            .build();
+        assert!(executor
+            .block_on(crate::get_time(SERVER, &socket, context))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_process_response_from_translated_address() {
+        let (context, request, response) = request_packet();
+        let context = context.with_address_verification(false);
+        let translated = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 1123);
+        let mut executor = Executor::new();
+        let socket = QueueSocket {
+            responses: [(RawNtpPacket::from(&response), translated)],
+            next: Cell::new(0),
+        };
+
+        let result = executor
+            .block_on(crate::sntp_process_response(
+                SERVER, &socket, context, request,
+            ))
+            .unwrap();
+        assert_eq!(1_700_000_000, result.sec());
+        assert_eq!(Some(translated), result.source());
+
+        let mut in_flight = InFlight::<1>::new();
+        assert!(in_flight.insert(SERVER, request));
+        socket.next.set(0);
+
+        let (src, _) = executor
+            .block_on(sntp_process_in_flight_response(
+                &socket,
+                context,
+                &mut in_flight,
+            ))
+            .unwrap();
+        assert_eq!(translated, src);
+        assert!(in_flight.is_empty());
+    }
+
+    #[test]
+    fn test_version_mask() {
+        let mask = VersionMask::V3 | VersionMask::V4;
+
+        assert!(mask.contains(3));
+        assert!(mask.contains(4));
+        assert!(!mask.contains(2));
+        assert!(!mask.contains(200));
+        assert!(VersionMask::default().contains(4));
+        assert!(!VersionMask::default().contains(3));
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod sntpc_serde_tests {
+    use crate::{Error, KissCode, NtpResult, RefId, SendRequestResult};
+    use core::net::Ipv4Addr;
+    use core::time::Duration;
+
+    #[test]
+    fn test_ntp_result_serde() {
+        let mut result = NtpResult::new(1_700_000_000, 42, 100, -5, 2, -20);
+        result.ref_id = RefId::Ipv4(Ipv4Addr::new(192, 168, 1, 1));
+        let json = serde_json::to_value(result).unwrap();
+
+        for field in [
+            "seconds",
+            "seconds_fraction",
+            "roundtrip",
+            "offset",
+            "stratum",
+            "precision",
+        ] {
+            assert!(json.get(field).is_some(), "{field} is missing");
+        }
+        assert_eq!(1_700_000_000, json["seconds"]);
+        assert_eq!(-5, json["offset"]);
+
+        let restored: NtpResult = serde_json::from_value(json).unwrap();
+        assert_eq!(result.sec(), restored.sec());
+        assert_eq!(result.sec_fraction(), restored.sec_fraction());
+        assert_eq!(result.offset(), restored.offset());
+        assert_eq!(result.ref_id(), restored.ref_id());
+        assert_eq!(result.leap_indicator(), restored.leap_indicator());
+    }
+
+    #[test]
+    fn test_ntp_result_json_round_trip() {
+        let result = NtpResult::new(3_900_000_000, 7, 250, 1_500, 1, -18);
+        let json = serde_json::to_string(&result).unwrap();
+        assert!(json.contains("\"seconds\":3900000000"), "{json}");
+
+        let restored: NtpResult = serde_json::from_str(&json).unwrap();
+        assert_eq!(result.sec(), restored.sec());
+        assert_eq!(result.roundtrip(), restored.roundtrip());
+        assert_eq!(result.offset(), restored.offset());
+    }
+
+    #[test]
+    fn test_error_serde() {
+        let json = serde_json::to_value(Error::Timeout).unwrap();
+        assert_eq!("Timeout", json["type"]);
+        assert_eq!("response has not been received in time", json["message"]);
+
+        let json = serde_json::to_value(Error::RateLimited(
+            Duration::from_millis(1500),
         ))
+        .unwrap();
+        assert_eq!("RateLimited", json["type"]);
+        assert_eq!("request rate limited, retry in 1500ms", json["message"]);
+
+        for error in [
+            Error::IncorrectOriginTimestamp,
+            Error::Network,
+            Error::PartialSend,
+            Error::InvalidTransmitTimestamp,
+            Error::InconsistentSamples,
+            Error::RootDispersionExceeded,
+            Error::StaleRequest,
+            Error::RateLimited(Duration::from_millis(1500)),
+            Error::KissOfDeath(KissCode::Deny),
+            Error::KissOfDeath(KissCode::Other(*b"XYZ0")),
+        ] {
+            let json = serde_json::to_string(&error).unwrap();
+            let restored: Error = serde_json::from_str(&json).unwrap();
+            assert_eq!(error, restored, "{json}");
+        }
+
+        // the message is informational only
+        let restored: Error =
+            serde_json::from_str(r#"{"type":"Network"}"#).unwrap();
+        assert_eq!(Error::Network, restored);
+        assert!(
+            serde_json::from_str::<Error>(r#"{"type":"KissOfDeath"}"#).is_err()
+        );
+        assert!(serde_json::from_str::<Error>(r#"{"type":"Unknown"}"#).is_err());
+    }
+
+    #[test]
+    fn test_send_request_result_serde() {
+        let request = SendRequestResult::new(0xE8B0_0A12_8000_0000, 4);
+        let json = serde_json::to_value(request).unwrap();
+
+        assert_eq!(0xE8B0_0A12_8000_0000u64, json["originate_timestamp"]);
+        assert_eq!(4, json["version"]);
+        assert_eq!(
+            request,
+            serde_json::from_value::<SendRequestResult>(json).unwrap()
+        );
     }
 }
 
-#[allow(
-    clippy::cast_possible_truncation,
-    clippy::cast_sign_loss,
-    clippy::cast_possible_wrap
-)]
-fn process_response(
-    send_req_result: SendRequestResult,
-    resp: RawNtpPacket,
-    recv_timestamp: u64,
-) -> Result<NtpResult> {
-    const SNTP_UNICAST: u8 = 4;
-    const SNTP_BROADCAST: u8 = 5;
-    const LI_MAX_VALUE: u8 = 3;
-    let mut packet = NtpPacket::from(resp);
+#[cfg(all(test, feature = "log", not(feature = "defmt")))]
+mod sntpc_log_tests {
+    use crate::{
+        sntp_send_request, NtpContext, NtpTimestampGenerator, NtpUdpSocket,
+    };
+    use core::net::{IpAddr, Ipv4Addr, SocketAddr};
+    use log::{Log, Metadata, Record};
+    use miniloop::executor::Executor;
+    use std::sync::Mutex;
 
-    convert_from_network(&mut packet);
+    struct CaptureLogger(Mutex<Vec<String>>);
 
-    cfg_if!(
-        if #[cfg(any(feature = "log", feature = "defmt"))] {
-            let debug_packet = DebugNtpPacket::new(&packet, recv_timestamp);
-            debug!("{:#?}", debug_packet);
+    impl Log for CaptureLogger {
+        fn enabled(&self, _metadata: &Metadata) -> bool {
+            true
         }
-    );
 
-    if send_req_result.originate_timestamp != packet.origin_timestamp {
-        return Err(Error::IncorrectOriginTimestamp);
-    }
-    // Shift is 0
-    let mode = shifter(packet.li_vn_mode, MODE_MASK, MODE_SHIFT);
-    let li = shifter(packet.li_vn_mode, LI_MASK, LI_SHIFT);
-    let resp_version = shifter(packet.li_vn_mode, VERSION_MASK, VERSION_SHIFT);
-    let req_version =
-        shifter(send_req_result.version, VERSION_MASK, VERSION_SHIFT);
+        fn log(&self, record: &Record) {
+            self.0.lock().unwrap().push(record.args().to_string());
+        }
 
-    if mode != SNTP_UNICAST && mode != SNTP_BROADCAST {
-        return Err(Error::IncorrectMode);
+        fn flush(&self) {}
     }
 
-    if li > LI_MAX_VALUE {
-        return Err(Error::IncorrectLeapIndicator);
-    }
+    static LOGGER: CaptureLogger = CaptureLogger(Mutex::new(Vec::new()));
 
-    if req_version != resp_version {
-        return Err(Error::IncorrectResponseVersion);
+    #[derive(Copy, Clone)]
+    struct FixedTimestampGen;
+
+    impl NtpTimestampGenerator for FixedTimestampGen {
+        fn init(&mut self) {}
+
+        fn timestamp_sec(&self) -> u64 {
+            1_700_000_000
+        }
+
+        fn timestamp_subsec_micros(&self) -> u32 {
+            0
+        }
     }
 
-    if packet.stratum == 0 {
-        return Err(Error::IncorrectStratumHeaders);
+    struct SendOnlySocket;
+
+    impl NtpUdpSocket for SendOnlySocket {
+        async fn send_to(
+            &self,
+            buf: &[u8],
+            _addr: SocketAddr,
+        ) -> crate::Result<usize> {
+            Ok(buf.len())
+        }
+
+        async fn recv_from(
+            &self,
+            _buf: &mut [u8],
+        ) -> crate::Result<(usize, SocketAddr)> {
+            Err(crate::Error::Network)
+        }
     }
-    // System clock offset:
-    // theta = T(B) - T(A) = 1/2 * [(T2-T1) + (T3-T4)]
-    // Round-trip delay:
-    // delta = T(ABA) = (T4-T1) - (T3-T2).
-    // where:
-    // - T1 = client's TX timestamp
-    // - T2 = server's RX timestamp
-    // - T3 = server's TX timestamp
-    // - T4 = client's RX timestamp
-    let t1 = packet.origin_timestamp;
-    let t2 = packet.recv_timestamp;
-    let t3 = packet.tx_timestamp;
-    let t4 = recv_timestamp;
-    let units = Units::Microseconds;
-    let roundtrip = roundtrip_calculate(t1, t2, t3, t4, units);
-    let offset = offset_calculate(t1, t2, t3, t4, units);
-    let timestamp = NtpTimestamp::from(packet.tx_timestamp);
 
-    #[cfg(any(feature = "log", feature = "defmt"))]
-    debug!(
-        "Roundtrip delay: {} {}. Offset: {} {}",
-        roundtrip, units, offset, units
-    );
+    #[test]
+    fn test_send_request_log() {
+        log::set_logger(&LOGGER).unwrap();
+        log::set_max_level(log::LevelFilter::Debug);
 
-    Ok(NtpResult::new(
-        timestamp.seconds as u32,
-        timestamp.seconds_fraction as u32,
-        roundtrip,
-        offset,
-        packet.stratum,
-        packet.precision,
-    ))
-}
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 123);
+        let context = NtpContext::new(FixedTimestampGen);
+        Executor::new()
+            .block_on(sntp_send_request(addr, &SendOnlySocket, context))
+            .unwrap();
 
-fn shifter(val: u8, mask: u8, shift: u8) -> u8 {
-    (val & mask) >> shift
-}
+        let lines = LOGGER.0.lock().unwrap();
+        let request = lines
+            .iter()
+            .find(|line| line.starts_with("Request:"))
+            .expect("request is not logged");
 
-fn convert_from_network(packet: &mut NtpPacket) {
-    fn ntohl<T: NtpNum>(val: &T) -> T::Type {
-        val.ntohl()
+        assert_eq!(
+            "Request: li_vn_mode: 0x23, version: 4, tx_timestamp: 0xe8fe6f8000000000",
+            request
+        );
     }
-
-    packet.root_delay = ntohl(&packet.root_delay);
-    packet.root_dispersion = ntohl(&packet.root_dispersion);
-    packet.ref_id = ntohl(&packet.ref_id);
-    packet.ref_timestamp = ntohl(&packet.ref_timestamp);
-    packet.origin_timestamp = ntohl(&packet.origin_timestamp);
-    packet.recv_timestamp = ntohl(&packet.recv_timestamp);
-    packet.tx_timestamp = ntohl(&packet.tx_timestamp);
 }
 
-fn convert_delays(sec: u64, fraction: u64, units: u64) -> u64 {
-    sec * units + fraction * units / u64::from(u32::MAX)
-}
+#[cfg(all(test, feature = "std"))]
+mod sntpc_std_tests {
+    use crate::types::Units;
+    use crate::{Error, NtpResult};
 
-fn roundtrip_calculate(
-    t1: u64,
-    t2: u64,
-    t3: u64,
-    t4: u64,
-    units: Units,
-) -> u64 {
-    let delta = t4.wrapping_sub(t1).saturating_sub(t3.wrapping_sub(t2));
-    let delta_sec = (delta & SECONDS_MASK) >> 32;
-    let delta_sec_fraction = delta & SECONDS_FRAC_MASK;
+    #[test]
+    #[cfg(feature = "std-socket")]
+    fn test_std_socket_futures_are_send() {
+        use crate::{NtpContext, StdTimestampGen};
+        use std::net::UdpSocket;
 
-    match units {
-        Units::Milliseconds => convert_delays(
-            delta_sec,
-            delta_sec_fraction,
-            u64::from(MSEC_IN_SEC),
-        ),
-        Units::Microseconds => convert_delays(
-            delta_sec,
-            delta_sec_fraction,
-            u64::from(USEC_IN_SEC),
-        ),
+        fn assert_send<T: Send>(_: T) {}
+
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let context = NtpContext::new(StdTimestampGen::default());
+        let addr = socket.local_addr().unwrap();
+
+        assert_send(crate::get_time(addr, &socket, context));
+        assert_send(crate::sntp_send_request(addr, &socket, context));
+        assert_send(crate::get_time_any(&[addr], &socket, context));
     }
-}
 
-#[allow(clippy::cast_possible_wrap)]
-fn offset_calculate(t1: u64, t2: u64, t3: u64, t4: u64, units: Units) -> i64 {
-    let theta = (t2.wrapping_sub(t1) as i64 / 2)
-        .saturating_add(t3.wrapping_sub(t4) as i64 / 2);
-    let theta_sec = (theta.unsigned_abs() & SECONDS_MASK) >> 32;
-    let theta_sec_fraction = theta.unsigned_abs() & SECONDS_FRAC_MASK;
+    #[test]
+    fn test_server_addr_parse() {
+        use crate::ServerAddr;
+        use core::net::{Ipv6Addr, SocketAddr, SocketAddrV6};
 
-    match units {
-        Units::Milliseconds => {
-            convert_delays(
-                theta_sec,
-                theta_sec_fraction,
-                u64::from(MSEC_IN_SEC),
-            ) as i64
-                * theta.signum()
+        let host = |name: &str, port| ServerAddr::Host {
+            name: name.to_string(),
+            port,
+        };
+
+        assert_eq!(Ok(host("time.google.com", 123)), "time.google.com".parse());
+        assert_eq!(
+            Ok(host("time.google.com", 1123)),
+            "time.google.com:1123".parse()
+        );
+        assert_eq!(
+            Ok(ServerAddr::Ip("192.0.2.1:123".parse().unwrap())),
+            "192.0.2.1".parse()
+        );
+        assert_eq!(
+            Ok(ServerAddr::Ip("192.0.2.1:1123".parse().unwrap())),
+            "192.0.2.1:1123".parse()
+        );
+
+        let v6: SocketAddr = "[2001:db8::1]:123".parse().unwrap();
+        for s in ["2001:db8::1", "[2001:db8::1]", "[2001:db8::1]:123"] {
+            assert_eq!(Ok(ServerAddr::Ip(v6)), s.parse(), "{s}");
         }
-        Units::Microseconds => {
-            convert_delays(
-                theta_sec,
-                theta_sec_fraction,
-                u64::from(USEC_IN_SEC),
-            ) as i64
-                * theta.signum()
+
+        let scoped = SocketAddr::V6(SocketAddrV6::new(
+            Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 1),
+            123,
+            0,
+            2,
+        ));
+        for s in ["fe80::1%2", "[fe80::1%2]", "[fe80::1%2]:123"] {
+            assert_eq!(Ok(ServerAddr::Ip(scoped)), s.parse(), "{s}");
+        }
+
+        for s in [
+            "",
+            ":123",
+            "time.google.com:",
+            "time.google.com:0",
+            "192.0.2.1:0",
+            "[2001:db8::1]:0",
+            "[2001:db8::1",
+            "2001:db8::1]:123",
+            "[2001:db8::1]:70000",
+            "[fe80::1%eth0]:123",
+            "time google com",
+        ] {
+            assert!(s.parse::<ServerAddr>().is_err(), "{s}");
         }
     }
-}
 
-fn get_ntp_timestamp<T: NtpTimestampGenerator>(timestamp_gen: &T) -> u64 {
-    ((timestamp_gen.timestamp_sec()
-        + (u64::from(NtpPacket::NTP_TIMESTAMP_DELTA)))
-        << 32)
-        + u64::from(timestamp_gen.timestamp_subsec_micros())
-            * u64::from(u32::MAX)
-            / u64::from(USEC_IN_SEC)
-}
+    #[test]
+    fn test_server_addr_display() {
+        use crate::ServerAddr;
 
-/// Convert second fraction value to milliseconds value
-#[allow(clippy::cast_possible_truncation)]
-#[must_use]
-pub fn fraction_to_milliseconds(sec_fraction: u32) -> u32 {
-    (u64::from(sec_fraction) * u64::from(MSEC_IN_SEC) / u64::from(u32::MAX))
-        as u32
-}
+        for s in ["time.google.com:123", "192.0.2.1:123", "[2001:db8::1]:123"] {
+            assert_eq!(s, s.parse::<ServerAddr>().unwrap().to_string());
+        }
+        assert_eq!(
+            "time.google.com:123",
+            "time.google.com".parse::<ServerAddr>().unwrap().to_string()
+        );
+    }
 
-/// Convert second fraction value to microseconds value
-#[allow(clippy::cast_possible_truncation)]
-#[must_use]
-pub fn fraction_to_microseconds(sec_fraction: u32) -> u32 {
-    (u64::from(sec_fraction) * u64::from(USEC_IN_SEC) / u64::from(u32::MAX))
-        as u32
-}
+    #[test]
+    fn test_std_resolver() {
+        use crate::{NtpHostResolver, StdResolver};
+        use core::net::{IpAddr, Ipv4Addr, SocketAddr};
 
-/// Convert second fraction value to nanoseconds value
-#[allow(clippy::cast_possible_truncation)]
-#[must_use]
-pub fn fraction_to_nanoseconds(sec_fraction: u32) -> u32 {
-    (u64::from(sec_fraction) * u64::from(NSEC_IN_SEC) / u64::from(u32::MAX))
-        as u32
-}
+        let mut executor = miniloop::executor::Executor::new();
+        let mut addrs = executor
+            .block_on(StdResolver.resolve("127.0.0.1", 123))
+            .unwrap();
+        assert_eq!(
+            Some(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 123)),
+            addrs.next()
+        );
+        assert!(executor
+            .block_on(StdResolver.resolve("invalid host name", 123))
+            .is_err());
+    }
 
-/// Convert second fraction value to picoseconds value
-#[allow(clippy::cast_possible_truncation)]
-#[must_use]
-pub fn fraction_to_picoseconds(sec_fraction: u32) -> u64 {
-    (u128::from(sec_fraction) * u128::from(PSEC_IN_SEC) / u128::from(u32::MAX))
-        as u64
-}
+    #[test]
+    fn test_resolve() {
+        // localhost usually resolves to both IPv4 and IPv6 loopback addresses
+        let addrs = crate::resolve("localhost").unwrap();
+        assert!(!addrs.is_empty());
+        assert!(addrs
+            .iter()
+            .all(|addr| addr.ip().is_loopback() && addr.port() == 123));
 
-#[cfg(test)]
-mod sntpc_ntp_result_tests {
-    use crate::types::Units;
-    use crate::{
-        fraction_to_microseconds, fraction_to_milliseconds,
-        fraction_to_nanoseconds, fraction_to_picoseconds, offset_calculate,
-        NtpResult,
-    };
+        let addrs = crate::resolve("127.0.0.1:1123").unwrap();
+        let expected: core::net::SocketAddr = "127.0.0.1:1123".parse().unwrap();
+        assert_eq!(&[expected], addrs.as_slice());
 
-    struct Timestamps(u64, u64, u64, u64);
-    struct OffsetCalcTestCase {
-        timestamp: Timestamps,
-        expected: i64,
+        assert_eq!(
+            Err(Error::AddressResolve),
+            crate::resolve("invalid host name")
+        );
     }
 
-    impl OffsetCalcTestCase {
-        fn new(t1: u64, t2: u64, t3: u64, t4: u64, expected: i64) -> Self {
-            OffsetCalcTestCase {
-                timestamp: Timestamps(t1, t2, t3, t4),
-                expected,
-            }
-        }
+    #[test]
+    fn test_resolve_cache() {
+        use crate::ResolveCache;
+        use core::time::Duration;
 
-        fn t1(&self) -> u64 {
-            self.timestamp.0
-        }
+        let mut cache = ResolveCache::new(2, Duration::from_secs(30));
+        let addrs = cache.resolve("localhost").unwrap();
+        assert_eq!(addrs, cache.resolve("localhost").unwrap());
+        assert_eq!(1, cache.len());
 
-        fn t2(&self) -> u64 {
-            self.timestamp.1
+        cache.resolve("127.0.0.1").unwrap();
+        cache.resolve("localhost").unwrap();
+        // the least recently used entry is replaced
+        cache.resolve("127.0.0.2").unwrap();
+        assert_eq!(2, cache.len());
+        assert!(cache.resolve("invalid host name").is_err());
+        assert_eq!(2, cache.len());
+
+        cache.clear();
+        assert!(cache.is_empty());
+
+        let mut cache = ResolveCache::new(0, Duration::from_secs(30));
+        assert!(cache.resolve("localhost").is_ok());
+        assert!(cache.is_empty());
+    }
+
+    #[cfg(all(feature = "dont-fragment", target_os = "linux"))]
+    #[test]
+    fn test_set_dont_fragment() {
+        use crate::net::{int_option, set_dont_fragment};
+        use std::net::UdpSocket;
+        use std::os::fd::AsFd;
+
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        set_dont_fragment(&socket).unwrap();
+        assert_eq!(
+            libc::IP_PMTUDISC_DO,
+            int_option(socket.as_fd(), libc::IPPROTO_IP, libc::IP_MTU_DISCOVER)
+                .unwrap()
+        );
+
+        // IPv6 may be disabled in the test environment
+        if let Ok(socket) = UdpSocket::bind("[::1]:0") {
+            set_dont_fragment(&socket).unwrap();
+            assert_eq!(
+                libc::IPV6_PMTUDISC_DO,
+                int_option(
+                    socket.as_fd(),
+                    libc::IPPROTO_IPV6,
+                    libc::IPV6_MTU_DISCOVER
+                )
+                .unwrap()
+            );
         }
+    }
 
-        fn t3(&self) -> u64 {
-            self.timestamp.2
+    #[test]
+    fn test_bind_for_address_family() {
+        let socket = crate::net::bind_for("192.0.2.1:123".parse().unwrap())
+            .expect("Unable to bind IPv4 socket");
+        assert!(socket.local_addr().unwrap().is_ipv4());
+
+        // IPv6 may be disabled in the test environment
+        if let Ok(socket) =
+            crate::net::bind_for("[2001:db8::1]:123".parse().unwrap())
+        {
+            assert!(socket.local_addr().unwrap().is_ipv6());
         }
+    }
 
-        fn t4(&self) -> u64 {
-            self.timestamp.3
+    #[test]
+    fn test_bind_local() {
+        let socket = crate::net::bind_local("127.0.0.1:0".parse().unwrap())
+            .expect("Unable to bind loopback socket");
+        let local = socket.local_addr().unwrap();
+
+        assert_eq!(core::net::Ipv4Addr::LOCALHOST, local.ip());
+        assert_ne!(0, local.port());
+        // TEST-NET-1 address is not assigned to any interface
+        assert!(crate::net::bind_local("192.0.2.1:0".parse().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_units_str_representation() {
+        assert_eq!(format!("{}", Units::Milliseconds), "ms");
+        assert_eq!(format!("{}", Units::Microseconds), "us");
+    }
+
+    #[test]
+    fn test_ntp_result_str_representation() {
+        let result =
+            NtpResult::new(1_700_000_000, u32::MAX / 2, 150, -42, 2, -20);
+        let output = format!("{result}");
+
+        assert!(output.contains("1700000000.499s"), "{output}");
+        assert_eq!(
+            output,
+            "NTP time: 1700000000.499s (roundtrip: 150µs, offset: -42µs, stratum: 2)"
+        );
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_ntp_result_secs_f64() {
+        let result = NtpResult::new(1_700_000_000, 0, 1_500, -250_000, 2, -20);
+
+        assert_eq!(0.0015, result.roundtrip_secs_f64());
+        assert_eq!(-0.25, result.offset_secs_f64());
+        assert_eq!(
+            result.roundtrip_duration().as_secs_f64(),
+            result.roundtrip_secs_f64()
+        );
+
+        let result = NtpResult {
+            units: Units::Milliseconds,
+            ..NtpResult::new(1_700_000_000, 0, 1_500_000, 2_000_000, 2, -20)
+        };
+        assert_eq!(1_500, result.roundtrip());
+        assert_eq!(1.5, result.roundtrip_secs_f64());
+        assert_eq!(2.0, result.offset_secs_f64());
+    }
+
+    #[test]
+    fn test_error_str_representation() {
+        assert_eq!(format!("{}", Error::Network), "network error");
+        assert_eq!(
+            format!("{}", Error::ResponseAddressMismatch),
+            "response received from an unexpected address"
+        );
+        assert_eq!(
+            format!("{}", Error::Truncated),
+            "response has been truncated"
+        );
+    }
+}
+
+#[cfg(all(test, feature = "std", feature = "std-socket"))]
+mod sntpc_client_tests {
+    use crate::testing::MockNtpServer;
+    use crate::{Error, NtpClient, NtpContext, StdTimestampGen};
+    use core::time::Duration;
+    use miniloop::executor::Executor;
+    use std::net::UdpSocket;
+
+    fn client(server: &MockNtpServer) -> NtpClient<UdpSocket, StdTimestampGen> {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket
+            .set_read_timeout(Some(Duration::from_secs(2)))
+            .expect("Unable to set up socket timeout");
+
+        NtpClient::new(
+            server.local_addr(),
+            socket,
+            NtpContext::new(StdTimestampGen::default()),
+        )
+    }
+
+    #[test]
+    fn test_client_query() {
+        let server =
+            MockNtpServer::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        server.set_time(1_700_000_000, 0);
+        let client = client(&server);
+        let mut executor = Executor::new();
+
+        assert_eq!(server.local_addr(), client.server());
+        for _ in 0..2 {
+            let result = executor.block_on(client.query()).unwrap();
+            assert_eq!(1_700_000_000, result.sec());
         }
+        assert_eq!(2, server.request_count());
     }
 
     #[test]
-    fn test_ntp_result() {
-        let result1 = NtpResult::new(0, 0, 0, 0, 1, -2);
+    fn test_client_min_interval() {
+        let server =
+            MockNtpServer::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let client = client(&server).with_min_interval(Duration::from_secs(30));
+        let mut executor = Executor::new();
 
-        assert_eq!(0, result1.sec());
-        assert_eq!(0, result1.sec_fraction());
-        assert_eq!(0, result1.roundtrip());
-        assert_eq!(0, result1.offset());
-        assert_eq!(1, result1.stratum());
-        assert_eq!(-2, result1.precision());
+        assert!(executor.block_on(client.query()).is_ok());
+        let Err(Error::RateLimited(wait)) = executor.block_on(client.query())
+        else {
+            panic!("second query is not rate limited");
+        };
+        assert!(wait > Duration::from_secs(29), "{wait:?}");
+        assert_eq!(1, server.request_count());
+    }
 
-        let result2 = NtpResult::new(1, 2, 3, 4, 5, -23);
+    #[test]
+    fn test_std_socket_recv_timeout() {
+        let server =
+            MockNtpServer::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        server.set_delay(Duration::from_millis(500));
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket
+            .set_read_timeout(Some(Duration::from_millis(50)))
+            .unwrap();
+        let read_timeout = socket.read_timeout().unwrap();
+        let context = NtpContext::new(StdTimestampGen::default());
+        let mut executor = Executor::new();
 
-        assert_eq!(1, result2.sec());
-        assert_eq!(2, result2.sec_fraction());
-        assert_eq!(3, result2.roundtrip());
-        assert_eq!(4, result2.offset());
-        assert_eq!(5, result2.stratum());
-        assert_eq!(-23, result2.precision());
+        assert_eq!(
+            Error::Timeout,
+            executor
+                .block_on(crate::get_time(
+                    server.local_addr(),
+                    &socket,
+                    context
+                ))
+                .unwrap_err()
+        );
+        // the socket configuration is left intact
+        assert_eq!(read_timeout, socket.read_timeout().unwrap());
+    }
 
-        let result3 =
-            NtpResult::new(u32::MAX - 1, u32::MAX, u64::MAX, i64::MAX, 1, -127);
+    #[test]
+    fn test_std_socket_drops_late_response() {
+        let server =
+            MockNtpServer::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        server.set_time(1_700_000_000, 0);
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let context = NtpContext::new(StdTimestampGen::default());
+        let mut executor = Executor::new();
 
-        assert_eq!(u32::MAX, result3.sec());
-        assert_eq!(0, result3.sec_fraction());
-        assert_eq!(u64::MAX, result3.roundtrip());
-        assert_eq!(i64::MAX, result3.offset());
-        assert_eq!(-127, result3.precision());
+        // emulate a request cancelled before its response arrived
+        executor
+            .block_on(crate::sntp_send_request(
+                server.local_addr(),
+                &socket,
+                context,
+            ))
+            .unwrap();
+        socket
+            .set_read_timeout(Some(Duration::from_secs(2)))
+            .unwrap();
+        socket.peek_from(&mut [0u8; 48]).unwrap();
+        socket.set_read_timeout(None).unwrap();
+
+        let result = executor
+            .block_on(crate::get_time(server.local_addr(), &socket, context))
+            .unwrap();
+        assert_eq!(1_700_000_000, result.sec());
+        assert_eq!(2, server.request_count());
+        assert_eq!(None, socket.read_timeout().unwrap());
     }
 
+    #[cfg(feature = "sync")]
     #[test]
-    fn test_ntp_fraction_overflow_result() {
-        let result = NtpResult::new(0, u32::MAX, 0, 0, 1, -19);
-        assert_eq!(1, result.sec());
-        assert_eq!(0, result.sec_fraction());
-        assert_eq!(0, result.roundtrip());
-        assert_eq!(0, result.offset());
+    fn test_client_query_sync() {
+        let server =
+            MockNtpServer::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        server.set_time(1_700_000_000, 0);
+        let client = client(&server);
 
-        let result = NtpResult::new(u32::MAX - 1, u32::MAX, 0, 0, 1, -17);
-        assert_eq!(u32::MAX, result.sec());
-        assert_eq!(0, result.sec_fraction());
-        assert_eq!(0, result.roundtrip());
-        assert_eq!(0, result.offset());
+        assert_eq!(1_700_000_000, client.query_sync().unwrap().sec());
     }
+}
 
-    #[test]
-    fn test_conversion_to_ms() {
-        let result = NtpResult::new(0, u32::MAX - 1, 0, 0, 1, 0);
-        let milliseconds = fraction_to_milliseconds(result.seconds_fraction);
-        assert_eq!(999u32, milliseconds);
+#[cfg(all(test, feature = "std", feature = "std-socket", feature = "sync"))]
+mod sntpc_sync_tests {
+    use crate::sync::get_time;
+    use crate::testing::MockNtpServer;
+    use crate::{Error, KissCode, NtpContext, StdTimestampGen};
+    use std::net::{ToSocketAddrs, UdpSocket};
+    use std::sync::Arc;
 
-        let result = NtpResult::new(0, 0, 0, 0, 1, 0);
-        let milliseconds = fraction_to_milliseconds(result.seconds_fraction);
-        assert_eq!(0u32, milliseconds);
+    fn mock_server() -> MockNtpServer {
+        MockNtpServer::bind("127.0.0.1:0".parse().unwrap())
+            .expect("Unable to start mock server")
     }
 
-    #[test]
-    fn test_conversion_to_us() {
-        let result = NtpResult::new(0, u32::MAX - 1, 0, 0, 1, 0);
-        let microseconds = fraction_to_microseconds(result.seconds_fraction);
-        assert_eq!(999_999u32, microseconds);
+    fn client_socket() -> UdpSocket {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket
+            .set_read_timeout(Some(std::time::Duration::from_secs(2)))
+            .expect("Unable to set up socket timeout");
 
-        let result = NtpResult::new(0, 0, 0, 0, 1, 0);
-        let microseconds = fraction_to_microseconds(result.seconds_fraction);
-        assert_eq!(0u32, microseconds);
+        socket
     }
 
     #[test]
-    fn test_conversion_to_ns() {
-        let result = NtpResult::new(0, u32::MAX - 1, 0, 0, 1, 0);
-        let nanoseconds = fraction_to_nanoseconds(result.seconds_fraction);
-        assert_eq!(999_999_999u32, nanoseconds);
+    fn test_ntp_request_shared_socket() {
+        let server = mock_server();
+        let socket = Arc::new(client_socket());
+        let context = NtpContext::new(StdTimestampGen::default());
 
-        let result = NtpResult::new(0, 0, 0, 0, 1, 0);
-        let nanoseconds = fraction_to_nanoseconds(result.seconds_fraction);
-        assert_eq!(0u32, nanoseconds);
+        let result =
+            get_time(server.local_addr(), &Arc::clone(&socket), context);
+
+        assert!(result.is_ok(), "{:?}", result.unwrap_err());
+        assert_eq!(1, result.unwrap().stratum());
     }
 
     #[test]
-    fn test_conversion_to_ps() {
-        let result = NtpResult::new(0, u32::MAX - 1, 0, 0, 1, 0);
-        let picoseconds = fraction_to_picoseconds(result.seconds_fraction);
-        assert_eq!(999_999_999_767u64, picoseconds);
+    fn test_ntp_request_server_addr() {
+        let server = mock_server();
+        let socket = client_socket();
+        let context = NtpContext::new(StdTimestampGen::default());
+        let server_addr = server.local_addr().to_string().parse().unwrap();
 
-        let result = NtpResult::new(0, 1, 0, 0, 1, 0);
-        let picoseconds = fraction_to_picoseconds(result.seconds_fraction);
-        assert_eq!(232u64, picoseconds);
+        let result =
+            crate::sync::get_time_by_server(&server_addr, &socket, context);
 
-        let result = NtpResult::new(0, 0, 0, 0, 1, 0);
-        let picoseconds = fraction_to_picoseconds(result.seconds_fraction);
-        assert_eq!(0u64, picoseconds);
+        assert!(result.is_ok(), "{:?}", result.unwrap_err());
     }
 
     #[test]
-    fn test_offset_calculate() {
-        let tests = [
-            OffsetCalcTestCase::new(
-                16_893_142_954_672_769_962,
-                16_893_142_959_053_084_959,
-                16_893_142_959_053_112_968,
-                16_893_142_954_793_063_406,
-                1_005_870,
-            ),
-            OffsetCalcTestCase::new(
-                16_893_362_966_131_575_843,
-                16_893_362_966_715_800_791,
-                16_893_362_966_715_869_584,
-                16_893_362_967_084_349_913,
-                25115,
-            ),
-            OffsetCalcTestCase::new(
-                16_893_399_716_399_327_198,
-                16_893_399_716_453_045_029,
-                16_893_399_716_453_098_083,
-                16_893_399_716_961_924_964,
-                -52981,
-            ),
-            OffsetCalcTestCase::new(
-                9_487_534_663_484_046_772u64,
-                16_882_120_099_581_835_046u64,
-                16_882_120_099_583_884_144u64,
-                9_487_534_663_651_464_597u64,
-                1_721_686_086_620_926,
-            ),
-        ];
+    fn test_mock_server_configured_time() {
+        let server = mock_server();
+        let socket = client_socket();
+        let context = NtpContext::new(StdTimestampGen::default());
+        server.set_time(1_700_000_000, u32::MAX / 2);
+        server.set_stratum(3);
 
-        for t in tests {
-            let offset = offset_calculate(
-                t.t1(),
-                t.t2(),
-                t.t3(),
-                t.t4(),
-                Units::Microseconds,
-            );
-            let expected = t.expected;
-            assert_eq!(offset, expected);
+        for _ in 0..2 {
+            let result =
+                get_time(server.local_addr(), &socket, context).unwrap();
+
+            assert_eq!(1_700_000_000, result.sec());
+            assert_eq!(u32::MAX / 2, result.sec_fraction());
+            assert_eq!(3, result.stratum());
         }
+        assert_eq!(2, server.request_count());
     }
-}
-
-#[cfg(all(test, feature = "std"))]
-mod sntpc_std_tests {
-    use crate::types::Units;
 
     #[test]
-    fn test_units_str_representation() {
-        assert_eq!(format!("{}", Units::Milliseconds), "ms");
-        assert_eq!(format!("{}", Units::Microseconds), "us");
-    }
-}
+    fn test_ntp_request_sntpv4_supported() {
+        let server = mock_server();
+        let socket = client_socket();
+        let context = NtpContext::new(StdTimestampGen::default());
 
-#[cfg(all(test, feature = "std", feature = "std-socket", feature = "sync"))]
-mod sntpc_sync_tests {
-    use crate::sync::get_time;
-    use crate::{Error, NtpContext, StdTimestampGen};
-    use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+        let result = get_time(server.local_addr(), &socket, context);
+
+        assert!(result.is_ok(), "{:?}", result.unwrap_err());
+        assert_ne!(result.unwrap().seconds, 0);
+    }
 
     #[test]
-    fn test_ntp_request_sntpv4_supported() {
+    fn test_ntp_request_sntpv3_not_supported() {
+        let server = mock_server();
+        let socket = client_socket();
         let context = NtpContext::new(StdTimestampGen::default());
-        let pools = [
-            "pool.ntp.org:123",
-            "time.google.com:123",
-            "time.apple.com:123",
-            "time.cloudflare.com:123",
-            "time.facebook.com:123",
-        ];
+        server.set_version(3);
 
-        for pool in &pools {
-            let socket = UdpSocket::bind("0.0.0.0:0").unwrap();
-            socket
-                .set_read_timeout(Some(std::time::Duration::from_secs(2)))
-                .expect("Unable to set up socket timeout");
-
-            for address in
-                pool.to_socket_addrs().unwrap().filter(SocketAddr::is_ipv4)
-            {
-                let result = get_time(address, &socket, context);
-
-                assert!(
-                    result.is_ok(),
-                    "{} is bad - {:?}",
-                    pool,
-                    result.unwrap_err()
-                );
-                assert_ne!(result.unwrap().seconds, 0);
-            }
-        }
+        let result = get_time(server.local_addr(), &socket, context);
+        assert_eq!(Error::IncorrectResponseVersion, result.unwrap_err());
     }
 
     #[test]
-    fn test_ntp_request_sntpv3_not_supported() {
+    fn test_spoofed_origin_timestamp() {
+        let server = mock_server();
+        let socket = client_socket();
         let context = NtpContext::new(StdTimestampGen::default());
+        socket
+            .set_read_timeout(Some(std::time::Duration::from_millis(200)))
+            .unwrap();
+        server.set_origin_timestamp(Some(0xdead_beef));
 
-        let pools = ["time.nist.gov:123", "time.windows.com:123"];
+        // the spoofed response is dropped while waiting for the response
+        let result = get_time(server.local_addr(), &socket, context);
+        assert_eq!(Error::Timeout, result.unwrap_err());
 
-        for pool in &pools {
-            let socket = UdpSocket::bind("0.0.0.0:0").unwrap();
-            socket
-                .set_read_timeout(Some(std::time::Duration::from_secs(5)))
-                .expect("Unable to set up socket timeout");
+        server.set_origin_timestamp(None);
+        assert!(get_time(server.local_addr(), &socket, context).is_ok());
+    }
 
-            for address in
-                pool.to_socket_addrs().unwrap().filter(SocketAddr::is_ipv4)
-            {
-                let result = get_time(address, &socket, context);
-                assert!(result.is_err(), "{pool} is ok");
-                assert_eq!(
-                    result.unwrap_err(),
-                    Error::IncorrectResponseVersion
-                );
-            }
-        }
+    #[test]
+    fn test_kiss_of_death_response() {
+        let server = mock_server();
+        let socket = client_socket();
+        let context = NtpContext::new(StdTimestampGen::default());
+        server.set_stratum(0);
+
+        let result = get_time(server.local_addr(), &socket, context);
+        assert_eq!(Error::KissOfDeath(KissCode::Deny), result.unwrap_err());
     }
 
     #[test]
@@ -1311,66 +6967,367 @@ mod sntpc_sync_tests {
 #[cfg(all(test, feature = "std", feature = "std-socket"))]
 mod sntpc_async_tests {
     use crate::get_time;
+    use crate::testing::MockNtpServer;
     use crate::{Error, NtpContext, StdTimestampGen};
     use miniloop::executor::Executor;
-    use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+    use std::net::UdpSocket;
+
+    fn client_socket() -> UdpSocket {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket
+            .set_read_timeout(Some(std::time::Duration::from_secs(2)))
+            .expect("Unable to set up socket timeout");
+
+        socket
+    }
 
     #[test]
     fn test_ntp_async_request_sntpv4_supported() {
+        let server =
+            MockNtpServer::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let socket = client_socket();
         let context = NtpContext::new(StdTimestampGen::default());
-        let pools = [
-            "pool.ntp.org:123",
-            "time.google.com:123",
-            "time.apple.com:123",
-            "time.cloudflare.com:123",
-            "time.facebook.com:123",
-        ];
 
-        for pool in &pools {
-            let socket = UdpSocket::bind("0.0.0.0:0").unwrap();
-            socket
-                .set_read_timeout(Some(std::time::Duration::from_secs(5)))
-                .expect("Unable to set up socket timeout");
-
-            for address in
-                pool.to_socket_addrs().unwrap().filter(SocketAddr::is_ipv4)
-            {
-                let result = Executor::new()
-                    .block_on(get_time(address, &socket, context));
-
-                assert!(
-                    result.is_ok(),
-                    "{pool} is bad - {:?}",
-                    result.unwrap_err()
-                );
-                assert_ne!(result.unwrap().seconds, 0);
-            }
-        }
+        let result = Executor::new().block_on(get_time(
+            server.local_addr(),
+            &socket,
+            context,
+        ));
+
+        assert!(result.is_ok(), "{:?}", result.unwrap_err());
+        assert_ne!(result.unwrap().seconds, 0);
     }
 
     #[test]
     fn test_ntp_async_request_sntpv3_not_supported() {
+        let server =
+            MockNtpServer::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let socket = client_socket();
+        let context = NtpContext::new(StdTimestampGen::default());
+        server.set_version(3);
+
+        let result = Executor::new().block_on(get_time(
+            server.local_addr(),
+            &socket,
+            context,
+        ));
+        assert_eq!(Error::IncorrectResponseVersion, result.unwrap_err());
+    }
+}
+
+#[cfg(all(test, feature = "smoltcp-socket"))]
+mod sntpc_smoltcp_tests {
+    use crate::smoltcp::{Buffers, UdpSocketBuffers, UdpSocketWrapper};
+    use crate::NtpUdpSocket;
+    use miniloop::executor::Executor;
+    use smoltcp::socket::udp;
+    use smoltcp::wire::{IpAddress, IpEndpoint};
+
+    #[test]
+    fn test_wrapper_into_inner() {
+        let mut buffers = Buffers::default();
+        let buffers = UdpSocketBuffers::new(&mut buffers);
+        let mut socket = udp::Socket::new(buffers.rx, buffers.tx);
+        let mut wrapper = UdpSocketWrapper::new(&mut socket);
+
+        wrapper.as_mut().bind(1123).unwrap();
+        assert!(wrapper.as_mut().is_open());
+
+        let socket = wrapper.into_inner();
+        let endpoint = IpEndpoint::new(IpAddress::v4(10, 0, 0, 1), 123);
+
+        assert_eq!(1123, socket.endpoint().port);
+        assert!(socket.can_send());
+        socket.send_slice(&[0u8; 48], endpoint).unwrap();
+        assert_eq!(48, socket.send_queue());
+    }
+
+    #[test]
+    fn test_wrapper_send_ipv6() {
+        let mut buffers = Buffers::default();
+        let buffers = UdpSocketBuffers::new(&mut buffers);
+        let mut socket = udp::Socket::new(buffers.rx, buffers.tx);
+        socket.bind(1123).unwrap();
+        let wrapper = UdpSocketWrapper::new(&mut socket);
+        let addr = "[2001:db8::123]:123".parse().unwrap();
+
+        let sent = Executor::new()
+            .block_on(NtpUdpSocket::send_to(&wrapper, &[0u8; 48], addr));
+
+        assert_eq!(Ok(48), sent);
+        assert_eq!(48, wrapper.into_inner().send_queue());
+    }
+}
+
+#[cfg(all(test, feature = "std", feature = "tokio-socket"))]
+mod sntpc_tokio_tests {
+    use crate::testing::MockNtpServer;
+    use crate::{NtpContext, StdTimestampGen, TokioSendHalf};
+    use tokio::net::UdpSocket;
+
+    #[test]
+    fn test_split_socket_halves() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_io()
+            .build()
+            .unwrap();
+        let server =
+            MockNtpServer::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        server.set_time(1_700_000_000, 0);
+        let context = NtpContext::new(StdTimestampGen::default());
+
+        let result = runtime.block_on(async {
+            let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+            let (tx, rx) = TokioSendHalf::split(socket);
+            let addr = server.local_addr();
+            let request =
+                crate::sntp_send_request(addr, &tx, context).await.unwrap();
+
+            tokio::spawn(async move {
+                crate::sntp_process_response(addr, &rx, context, request).await
+            })
+            .await
+            .unwrap()
+        });
+
+        assert_eq!(1_700_000_000, result.unwrap().sec());
+        assert_eq!(1, server.request_count());
+    }
+
+    #[test]
+    fn test_connected_socket() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_io()
+            .enable_time()
+            .build()
+            .unwrap();
+        let server =
+            MockNtpServer::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        server.set_time(1_700_000_000, 0);
+        let context = NtpContext::new(StdTimestampGen::default());
+        let addr = server.local_addr();
+
+        let result = runtime.block_on(async {
+            let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+            socket.connect(addr).await.unwrap();
+
+            // datagrams from other sources are dropped by the kernel
+            let stray = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+            stray
+                .send_to(&[0u8; 48], socket.local_addr().unwrap())
+                .await
+                .unwrap();
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            assert_eq!(
+                std::io::ErrorKind::WouldBlock,
+                socket.try_recv(&mut [0u8; 48]).unwrap_err().kind()
+            );
+
+            crate::get_time(addr, &socket, context).await
+        });
+
+        assert_eq!(1_700_000_000, result.unwrap().sec());
+        assert_eq!(1, server.request_count());
+    }
+
+    #[test]
+    fn test_get_time_after_cancelled_call() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_io()
+            .enable_time()
+            .build()
+            .unwrap();
+        let server =
+            MockNtpServer::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        server.set_time(1_700_000_000, 0);
+        server.set_delay(std::time::Duration::from_millis(100));
+        let context = NtpContext::new(StdTimestampGen::default());
+        let addr = server.local_addr();
+
+        let result = runtime.block_on(async {
+            let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+            let timeout = |ms| std::time::Duration::from_millis(ms);
+
+            assert!(tokio::time::timeout(
+                timeout(20),
+                crate::get_time(addr, &socket, context),
+            )
+            .await
+            .is_err());
+
+            // the response to the cancelled request arrives in the meantime
+            tokio::time::sleep(timeout(300)).await;
+            server.set_delay(std::time::Duration::ZERO);
+
+            tokio::time::timeout(
+                timeout(2_000),
+                crate::get_time(addr, &socket, context),
+            )
+            .await
+            .unwrap()
+        });
+
+        assert_eq!(1_700_000_000, result.unwrap().sec());
+        assert_eq!(2, server.request_count());
+    }
+
+    #[test]
+    fn test_process_response_resume_after_timeout() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_io()
+            .enable_time()
+            .build()
+            .unwrap();
+        let server =
+            MockNtpServer::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        server.set_time(1_700_000_000, 0);
+        server.set_delay(std::time::Duration::from_millis(200));
+        let context = NtpContext::new(StdTimestampGen::default());
+        let addr = server.local_addr();
+
+        let result = runtime.block_on(async {
+            let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+            let request = crate::sntp_send_request(addr, &socket, context)
+                .await
+                .unwrap();
+            let timeout = |ms| std::time::Duration::from_millis(ms);
+
+            assert!(tokio::time::timeout(
+                timeout(20),
+                crate::sntp_process_response(addr, &socket, context, request),
+            )
+            .await
+            .is_err());
+
+            tokio::time::timeout(
+                timeout(2_000),
+                crate::sntp_process_response(addr, &socket, context, request),
+            )
+            .await
+            .unwrap()
+        });
+
+        assert_eq!(1_700_000_000, result.unwrap().sec());
+        assert_eq!(1, server.request_count());
+    }
+
+    #[test]
+    fn test_recv_timeout() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_io()
+            .enable_time()
+            .build()
+            .unwrap();
+        let server =
+            MockNtpServer::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        server.set_delay(std::time::Duration::from_millis(500));
+        let context = NtpContext::new(StdTimestampGen::default())
+            .with_recv_timeout(std::time::Duration::from_millis(20));
+
+        let result = runtime.block_on(async {
+            let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+
+            crate::get_time(server.local_addr(), &socket, context).await
+        });
+
+        assert_eq!(crate::Error::Timeout, result.unwrap_err());
+    }
+
+    #[test]
+    fn test_get_time_spawn() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_io()
+            .build()
+            .unwrap();
+        let server =
+            MockNtpServer::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        server.set_time(1_700_000_000, 0);
+        let context = NtpContext::new(StdTimestampGen::default());
+        let addr = server.local_addr();
+
+        let result = runtime.block_on(async {
+            let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+
+            tokio::spawn(async move {
+                crate::get_time(addr, &socket, context).await
+            })
+            .await
+            .unwrap()
+        });
+
+        assert_eq!(1_700_000_000, result.unwrap().sec());
+    }
+
+    #[test]
+    fn test_ntp_stream() {
+        use crate::stream::ntp_stream;
+        use core::future::poll_fn;
+        use core::pin::Pin;
+        use futures_core::Stream;
+        use std::sync::Arc;
+        use std::time::{Duration, Instant};
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_io()
+            .enable_time()
+            .build()
+            .unwrap();
+        let server =
+            MockNtpServer::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        server.set_time(1_700_000_000, 0);
         let context = NtpContext::new(StdTimestampGen::default());
+        let interval = Duration::from_millis(20);
+
+        runtime.block_on(async {
+            let socket =
+                Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+            let mut stream =
+                ntp_stream(server.local_addr(), socket, context, interval);
 
-        let pools = ["time.nist.gov:123", "time.windows.com:123"];
+            for _ in 0..2 {
+                let result = poll_fn(|cx| Pin::new(&mut stream).poll_next(cx))
+                    .await
+                    .unwrap();
+                assert_eq!(1_700_000_000, result.unwrap().sec());
+            }
+            assert_eq!(2, server.request_count());
 
-        for pool in &pools {
-            let socket = UdpSocket::bind("0.0.0.0:0").unwrap();
-            socket
-                .set_read_timeout(Some(std::time::Duration::from_secs(5)))
-                .expect("Unable to set up socket timeout");
+            // timeouts are yielded as items and double the delay of the next request
+            server.set_delay(Duration::from_millis(500));
+            let start = Instant::now();
 
-            for address in
-                pool.to_socket_addrs().unwrap().filter(SocketAddr::is_ipv4)
-            {
-                let result = Executor::new()
-                    .block_on(get_time(address, &socket, context));
-                assert!(result.is_err(), "{pool} is ok");
+            for _ in 0..3 {
                 assert_eq!(
-                    result.unwrap_err(),
-                    Error::IncorrectResponseVersion
+                    crate::Error::Timeout,
+                    poll_fn(|cx| Pin::new(&mut stream).poll_next(cx))
+                        .await
+                        .unwrap()
+                        .unwrap_err()
                 );
             }
-        }
+            assert!(start.elapsed() >= interval * (1 + 1 + 2 + 1 + 4 + 1));
+        });
+    }
+}
+
+#[cfg(all(test, feature = "utils"))]
+mod sntpc_utils_tests {
+    use crate::utils::{update_system_time, UtilsError};
+    use chrono::Local;
+
+    #[test]
+    fn test_unsupported_os() {
+        assert_eq!(
+            Err(UtilsError::Unsupported),
+            crate::utils::unsupported::sync_time(Local::now())
+        );
+    }
+
+    #[test]
+    fn test_invalid_time() {
+        assert_eq!(
+            Err(UtilsError::InvalidTime),
+            update_system_time(0, u32::MAX)
+        );
     }
 }
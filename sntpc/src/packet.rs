@@ -0,0 +1,90 @@
+//! Sans-I/O SNTP packet codec
+//!
+//! The functions in this module encode SNTP requests and decode server responses without
+//! touching any socket. They are useful for platforms where the network stack does not fit
+//! the [`NtpUdpSocket`](crate::NtpUdpSocket) model, e.g. callback-driven stacks or
+//! hand-rolled DMA drivers. Sending and receiving the bytes is left to the caller:
+//!
+//! ```rust
+//! # #[cfg(all(feature = "std", feature = "test-utils"))]
+//! # fn main() -> sntpc::Result<()> {
+//! use sntpc::{packet, NtpContext, StdTimestampGen};
+//! use std::net::UdpSocket;
+//! # use sntpc::testing::MockNtpServer;
+//!
+//! # let server = MockNtpServer::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+//! # let server_addr = server.local_addr();
+//! let socket = UdpSocket::bind("0.0.0.0:0").expect("Unable to create UDP socket");
+//! let context = NtpContext::new(StdTimestampGen::default());
+//!
+//! let (request, request_result) = packet::encode_request(context);
+//! socket.send_to(&request, server_addr).expect("Unable to send request");
+//!
+//! let mut response = [0u8; packet::PACKET_SIZE];
+//! socket.recv_from(&mut response).expect("Unable to receive response");
+//! let recv_timestamp = sntpc::ntp_timestamp_from_gen(&context.timestamp_gen);
+//!
+//! let result = packet::decode_response(
+//!     request_result,
+//!     &response,
+//!     recv_timestamp,
+//!     &context,
+//! )?;
+//! println!("Got time: {}.{}", result.sec(), result.sec_fraction());
+//! # Ok(())
+//! # }
+//! # #[cfg(not(all(feature = "std", feature = "test-utils")))]
+//! # fn main() {}
+//! ```
+use crate::types::{NtpPacket, RawNtpPacket};
+use crate::{
+    build_request, process_response, Error, NtpContext, NtpResult,
+    NtpTimestampGenerator, Result, SendRequestResult,
+};
+
+/// Size of an SNTP packet without extension fields in bytes
+pub const PACKET_SIZE: usize = size_of::<NtpPacket>();
+
+/// Encodes an SNTP request.
+///
+/// Returns the request bytes to send to an NTP server along with the [`SendRequestResult`]
+/// required to decode the response with [`decode_response`].
+pub fn encode_request<T: NtpTimestampGenerator>(
+    context: NtpContext<T>,
+) -> ([u8; PACKET_SIZE], SendRequestResult) {
+    let request = build_request(context);
+
+    (
+        RawNtpPacket::from(&request).0,
+        SendRequestResult::from(request),
+    )
+}
+
+/// Decodes and validates an SNTP response.
+///
+/// `response` may be longer than [`PACKET_SIZE`] bytes, e.g. if the server appended extension
+/// fields, which are ignored. `recv_timestamp` is the NTP timestamp of the moment the response
+/// was received, see [`ntp_timestamp_from_gen`](crate::ntp_timestamp_from_gen).
+///
+/// As the server address is not known here, source address verification is up to the caller
+/// and reference identifiers are always decoded as IPv4 addresses.
+///
+/// # Errors
+///
+/// Will return `Err(Error::IncorrectPayload)` if `response` is shorter than [`PACKET_SIZE`]
+/// bytes, otherwise fails in the same cases as [`sntp_process_response`](crate::sntp_process_response)
+/// does after receiving a packet.
+pub fn decode_response<T: NtpTimestampGenerator>(
+    request: SendRequestResult,
+    response: &[u8],
+    recv_timestamp: u64,
+    context: &NtpContext<T>,
+) -> Result<NtpResult> {
+    let mut raw = RawNtpPacket::default();
+
+    raw.0.copy_from_slice(
+        response.get(..PACKET_SIZE).ok_or(Error::IncorrectPayload)?,
+    );
+
+    process_response(request, raw, recv_timestamp, context)
+}
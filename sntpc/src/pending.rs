@@ -0,0 +1,87 @@
+use crate::net::SocketAddr;
+use crate::{
+    sntp_process_response, sntp_send_request, NtpContext, NtpResult,
+    NtpTimestampGenerator, NtpUdpSocket, Result, SendRequestResult,
+};
+
+/// SNTP request that has been sent and awaits the response
+///
+/// [`crate::sntp_send_request`] and [`crate::sntp_process_response`] accept any
+/// [`SendRequestResult`], so nothing prevents processing a request with another socket or
+/// server address, which fails with confusing errors at best. The pending request borrows the
+/// socket the request has been sent over and keeps the server address, so the response is
+/// always received from the same socket and checked against the same server:
+///
+/// ```rust,no_run
+/// # use sntpc::{net::SocketAddr, NtpContext, NtpTimestampGenerator, NtpUdpSocket, Result};
+/// use sntpc::PendingRequest;
+///
+/// # async fn query<U: NtpUdpSocket, T: NtpTimestampGenerator + Copy>(
+/// #     socket: &U,
+/// #     context: NtpContext<T>,
+/// #     server: SocketAddr,
+/// # ) -> Result<()> {
+/// let pending = PendingRequest::send(server, socket, context).await?;
+/// // do something else while the request is in flight
+/// let result = pending.complete(context).await?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`PendingRequest::complete`] consumes the request, so it can not be completed twice. Use
+/// [`PendingRequest::dest`] and [`PendingRequest::request`] with
+/// [`crate::sntp_process_response`] if the wait for the response has to be resumed after a
+/// cancellation.
+#[derive(Debug)]
+#[must_use = "the response has to be received with `PendingRequest::complete`"]
+pub struct PendingRequest<'s, U: ?Sized> {
+    socket: &'s U,
+    dest: SocketAddr,
+    request: SendRequestResult,
+}
+
+impl<'s, U: NtpUdpSocket + ?Sized> PendingRequest<'s, U> {
+    /// Send an SNTP request to `dest` over `socket`, see [`crate::sntp_send_request`]
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` in the same cases as [`crate::sntp_send_request`]
+    pub async fn send<T: NtpTimestampGenerator>(
+        dest: SocketAddr,
+        socket: &'s U,
+        context: NtpContext<T>,
+    ) -> Result<Self> {
+        let request = sntp_send_request(dest, socket, context).await?;
+
+        Ok(PendingRequest {
+            socket,
+            dest,
+            request,
+        })
+    }
+
+    /// Receive and process the response to the request, see [`crate::sntp_process_response`]
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` in the same cases as [`crate::sntp_process_response`]
+    pub async fn complete<T: NtpTimestampGenerator>(
+        self,
+        context: NtpContext<T>,
+    ) -> Result<NtpResult> {
+        sntp_process_response(self.dest, self.socket, context, self.request)
+            .await
+    }
+
+    /// Returns the address of the server the request has been sent to
+    #[must_use]
+    pub fn dest(&self) -> SocketAddr {
+        self.dest
+    }
+
+    /// Returns the state of the sent request
+    #[must_use]
+    pub fn request(&self) -> SendRequestResult {
+        self.request
+    }
+}
@@ -0,0 +1,129 @@
+use crate::net::SocketAddr;
+use crate::{timestamp_micros, Error, NtpTimestampGenerator, Result};
+
+use core::time::Duration;
+
+/// Minimum headway limiter for NTP requests
+///
+/// Public NTP pools ask clients not to query the same server more often than once every few
+/// seconds. The limiter remembers when the last request has been sent to each server and
+/// rejects new requests with [`Error::RateLimited`] until `min_interval` has passed.
+///
+/// The limiter keeps up to `N` servers in a fixed-capacity table, so it does not require heap
+/// allocations. When the table is full, the server that has been queried the longest time ago
+/// is evicted. Time is taken from the [`NtpTimestampGenerator`], so no `std` is required.
+#[derive(Debug, Copy, Clone)]
+pub struct RateLimiter<const N: usize> {
+    min_interval: Duration,
+    entries: [Option<(SocketAddr, u64)>; N],
+}
+
+impl<const N: usize> RateLimiter<N> {
+    /// Create new limiter that allows one request per `min_interval` for every server
+    #[must_use]
+    pub const fn new(min_interval: Duration) -> Self {
+        Self {
+            min_interval,
+            entries: [None; N],
+        }
+    }
+
+    /// Check whether a request to `addr` is allowed at the current moment and record it
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::RateLimited`] with the remaining wait time if the previous request to
+    /// `addr` has been sent less than `min_interval` ago. In that case the request is not
+    /// recorded.
+    pub fn acquire<T: NtpTimestampGenerator>(
+        &mut self,
+        addr: SocketAddr,
+        timestamp_gen: &mut T,
+    ) -> Result<()> {
+        let now = timestamp_micros(timestamp_gen);
+
+        if let Some(entry) = self
+            .entries
+            .iter_mut()
+            .flatten()
+            .find(|(entry_addr, _)| *entry_addr == addr)
+        {
+            let elapsed = Duration::from_micros(now.saturating_sub(entry.1));
+
+            if elapsed < self.min_interval {
+                return Err(Error::RateLimited(
+                    self.min_interval.saturating_sub(elapsed),
+                ));
+            }
+
+            entry.1 = now;
+            return Ok(());
+        }
+
+        let slot = self
+            .entries
+            .iter_mut()
+            .min_by_key(|entry| entry.map(|(_, timestamp)| timestamp));
+
+        if let Some(slot) = slot {
+            *slot = Some((addr, now));
+        }
+
+        Ok(())
+    }
+
+    /// Forget all recorded requests
+    pub fn reset(&mut self) {
+        self.entries = [None; N];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RateLimiter;
+    use crate::fixtures::{server_addr, ManualTimestampGen};
+    use crate::Error;
+
+    use core::time::Duration;
+
+    #[test]
+    fn test_rate_limiter_min_interval() {
+        let mut limiter = RateLimiter::<2>::new(Duration::from_secs(4));
+        let mut clock = ManualTimestampGen { usec: 1_000_000 };
+
+        assert!(limiter.acquire(server_addr(1), &mut clock).is_ok());
+
+        clock.usec += 1_500_000;
+        assert_eq!(
+            Err(Error::RateLimited(Duration::from_millis(2_500))),
+            limiter.acquire(server_addr(1), &mut clock)
+        );
+        assert!(limiter.acquire(server_addr(2), &mut clock).is_ok());
+
+        clock.usec += 2_500_000;
+        assert!(limiter.acquire(server_addr(1), &mut clock).is_ok());
+        assert_eq!(
+            Err(Error::RateLimited(Duration::from_secs(4))),
+            limiter.acquire(server_addr(1), &mut clock)
+        );
+    }
+
+    #[test]
+    fn test_rate_limiter_evicts_oldest() {
+        let mut limiter = RateLimiter::<2>::new(Duration::from_secs(4));
+        let mut clock = ManualTimestampGen::default();
+
+        assert!(limiter.acquire(server_addr(1), &mut clock).is_ok());
+        clock.usec += 1_000_000;
+        assert!(limiter.acquire(server_addr(2), &mut clock).is_ok());
+        clock.usec += 1_000_000;
+        assert!(limiter.acquire(server_addr(3), &mut clock).is_ok());
+
+        // server_addr(1) has been evicted to make room for server_addr(3)
+        assert!(limiter.acquire(server_addr(1), &mut clock).is_ok());
+        assert!(limiter.acquire(server_addr(3), &mut clock).is_err());
+
+        limiter.reset();
+        assert!(limiter.acquire(server_addr(3), &mut clock).is_ok());
+    }
+}
@@ -0,0 +1,155 @@
+use crate::types::{NtpPacket, MODE_MASK, VERSION_MASK, VERSION_SHIFT};
+use crate::{NtpTimestampGenerator, NtpVersion, RefId};
+
+/// SNTP client request with custom header fields, see [`crate::sntp_send_custom_request`]
+///
+/// Requests sent by [`crate::sntp_send_request`] are configured with the [`NtpContext`] and
+/// leave the rest of the header fields zeroed. A custom request allows to set the fields
+/// servers may look at, e.g. for conformance testing or deployments that rely on a specific
+/// poll interval. The transmit timestamp is always filled in from the timestamp generator of
+/// the context when the request is sent, so the response is processed as usual:
+///
+/// ```rust
+/// use sntpc::{NtpRequest, NtpVersion, RefId};
+///
+/// let request = NtpRequest::builder()
+///     .version(NtpVersion::V3)
+///     .poll(6)
+///     .ref_id(RefId::Ascii(*b"TEST"))
+///     .build();
+/// ```
+///
+/// Fields that make the packet something other than a client request, like the mode, can only
+/// be set with the `test-utils` feature enabled.
+///
+/// [`NtpContext`]: crate::NtpContext
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct NtpRequest {
+    version: u8,
+    mode: u8,
+    stratum: u8,
+    poll: i8,
+    precision: i8,
+    ref_id: u32,
+}
+
+impl NtpRequest {
+    /// Create a builder of a custom request
+    #[must_use]
+    pub fn builder() -> NtpRequestBuilder {
+        NtpRequestBuilder::default()
+    }
+
+    /// Returns the protocol version of the request
+    #[must_use]
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// Returns the poll exponent of the request
+    #[must_use]
+    pub fn poll(&self) -> i8 {
+        self.poll
+    }
+
+    /// Build the request packet stamped with the current time of `timestamp_gen`
+    pub(crate) fn to_packet<T: NtpTimestampGenerator>(
+        self,
+        timestamp_gen: T,
+    ) -> NtpPacket {
+        let mut packet = NtpPacket::new(timestamp_gen, self.version);
+
+        packet.li_vn_mode = (self.mode & MODE_MASK)
+            | ((self.version << VERSION_SHIFT) & VERSION_MASK);
+        packet.stratum = self.stratum;
+        packet.poll = self.poll;
+        packet.precision = self.precision;
+        packet.ref_id = self.ref_id;
+
+        packet
+    }
+}
+
+impl Default for NtpRequest {
+    fn default() -> Self {
+        NtpRequest {
+            version: NtpPacket::SNTP_VERSION,
+            mode: NtpPacket::SNTP_CLIENT_MODE,
+            stratum: 0,
+            poll: 0,
+            precision: 0,
+            ref_id: 0,
+        }
+    }
+}
+
+/// Builder of [`NtpRequest`]
+///
+/// Fields that are not set are zeroed, the version is `NTPv4` by default.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct NtpRequestBuilder {
+    request: NtpRequest,
+}
+
+impl NtpRequestBuilder {
+    /// Set protocol version of the request
+    #[must_use]
+    pub fn version(mut self, version: NtpVersion) -> Self {
+        self.request.version = version.into();
+        self
+    }
+
+    /// Set the poll exponent, i.e. the interval between requests as a power of two seconds
+    #[must_use]
+    pub fn poll(mut self, poll: i8) -> Self {
+        self.request.poll = poll;
+        self
+    }
+
+    /// Set the precision exponent of the client clock as a power of two seconds
+    #[must_use]
+    pub fn precision(mut self, precision: i8) -> Self {
+        self.request.precision = precision;
+        self
+    }
+
+    /// Set the reference identifier of the request
+    #[must_use]
+    pub fn ref_id(mut self, ref_id: RefId) -> Self {
+        self.request.ref_id = u32::from_be_bytes(ref_id.octets());
+        self
+    }
+
+    /// Set a raw protocol version, including the values that are not valid NTP versions.
+    /// Only the lower 3 bits fit into the header.
+    #[cfg(feature = "test-utils")]
+    #[must_use]
+    pub fn raw_version(mut self, version: u8) -> Self {
+        self.request.version = version;
+        self
+    }
+
+    /// Set the mode of the request, e.g. to send a packet other than a client request. Only
+    /// the lower 3 bits fit into the header.
+    #[cfg(feature = "test-utils")]
+    #[must_use]
+    pub fn mode(mut self, mode: u8) -> Self {
+        self.request.mode = mode;
+        self
+    }
+
+    /// Set the stratum of the request, which is zero for client requests
+    #[cfg(feature = "test-utils")]
+    #[must_use]
+    pub fn stratum(mut self, stratum: u8) -> Self {
+        self.request.stratum = stratum;
+        self
+    }
+
+    /// Build the request
+    #[must_use]
+    pub fn build(self) -> NtpRequest {
+        self.request
+    }
+}
@@ -0,0 +1,398 @@
+use crate::net::SocketAddr;
+#[cfg(any(
+    feature = "std",
+    feature = "tokio-socket",
+    feature = "embassy-socket"
+))]
+use crate::Error;
+use crate::Result;
+
+use core::future::Future;
+
+/// A trait encapsulating host name resolution required by [`crate::get_time_by_name`]
+///
+/// The core crate does not depend on any specific DNS stack, so the resolution is delegated to
+/// the implementation of the trait. The crate provides implementations for:
+///
+/// - `StdResolver` with the `std` feature, which uses `std::net::ToSocketAddrs`
+/// - `TokioResolver` with the `tokio-socket` feature, which uses `tokio::net::lookup_host`
+/// - `HickoryResolver` with the `hickory-resolver` feature, which uses `hickory-resolver`
+/// - `embassy_net::Stack` with the `embassy-socket` feature, which uses `Stack::dns_query`
+pub trait NtpHostResolver {
+    /// Resolve `host` to the list of socket addresses with the given `port`
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the host name can not be resolved
+    fn resolve(
+        &self,
+        host: &str,
+        port: u16,
+    ) -> impl Future<Output = Result<impl Iterator<Item = SocketAddr>>>;
+}
+
+impl<T: NtpHostResolver + ?Sized> NtpHostResolver for &T {
+    fn resolve(
+        &self,
+        host: &str,
+        port: u16,
+    ) -> impl Future<Output = Result<impl Iterator<Item = SocketAddr>>> {
+        (**self).resolve(host, port)
+    }
+}
+
+/// Host name resolver that relies on `std::net::ToSocketAddrs`
+///
+/// The resolution blocks the calling thread, so the resolver is intended to be used with the
+/// synchronous interface or the `std` sockets.
+#[cfg(feature = "std")]
+#[derive(Debug, Copy, Clone, Default)]
+pub struct StdResolver;
+
+#[cfg(feature = "std")]
+impl NtpHostResolver for StdResolver {
+    async fn resolve(
+        &self,
+        host: &str,
+        port: u16,
+    ) -> Result<impl Iterator<Item = SocketAddr>> {
+        std::net::ToSocketAddrs::to_socket_addrs(&(host, port))
+            .map_err(|_| Error::AddressResolve)
+    }
+}
+
+/// Host name resolver that relies on `tokio::net::lookup_host`
+#[cfg(feature = "tokio-socket")]
+#[derive(Debug, Copy, Clone, Default)]
+pub struct TokioResolver;
+
+#[cfg(feature = "tokio-socket")]
+impl NtpHostResolver for TokioResolver {
+    async fn resolve(
+        &self,
+        host: &str,
+        port: u16,
+    ) -> Result<impl Iterator<Item = SocketAddr>> {
+        tokio::net::lookup_host((host, port))
+            .await
+            .map_err(|_| Error::AddressResolve)
+    }
+}
+
+/// Resolve IPv4 addresses of the host with the DNS servers the stack is configured with
+#[cfg(feature = "embassy-socket")]
+impl NtpHostResolver for embassy_net::Stack<'_> {
+    async fn resolve(
+        &self,
+        host: &str,
+        port: u16,
+    ) -> Result<impl Iterator<Item = SocketAddr>> {
+        let addrs = self
+            .dns_query(host, embassy_net::dns::DnsQueryType::A)
+            .await
+            .map_err(|_| Error::AddressResolve)?;
+
+        Ok(addrs
+            .into_iter()
+            .map(move |addr| SocketAddr::new(addr.into(), port)))
+    }
+}
+
+/// Target of a DNS SRV record with the target host name resolved to a socket address
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SrvTarget {
+    /// Priority of the target, targets with lower values are queried first
+    pub priority: u16,
+    /// Relative weight of the targets with the same priority
+    pub weight: u16,
+    /// Socket address of the target with the port from the SRV record
+    pub addr: SocketAddr,
+}
+
+/// A trait encapsulating DNS SRV record lookup required by [`crate::get_time_by_srv`]
+///
+/// SRV lookup is an extension of [`NtpHostResolver`], since servers are looked up by host name
+/// when no SRV records are published. The crate provides `HickoryResolver` implementation with
+/// the `hickory-resolver` feature.
+pub trait NtpSrvResolver: NtpHostResolver {
+    /// Resolve SRV records of `name`, e.g. `_ntp._udp.example.com`, into the list of targets
+    ///
+    /// Every target host name should be resolved to socket addresses, a target that resolves to
+    /// several addresses is reported as several targets with the same priority and weight.
+    /// The targets may be returned in any order.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the SRV lookup fails
+    fn resolve_srv(
+        &self,
+        name: &str,
+    ) -> impl Future<Output = Result<impl Iterator<Item = SrvTarget>>>;
+}
+
+impl<T: NtpSrvResolver + ?Sized> NtpSrvResolver for &T {
+    fn resolve_srv(
+        &self,
+        name: &str,
+    ) -> impl Future<Output = Result<impl Iterator<Item = SrvTarget>>> {
+        (**self).resolve_srv(name)
+    }
+}
+
+/// Host name and SRV record resolver that relies on `hickory-resolver`
+///
+/// Lookups are performed on the `tokio` runtime the resolver is used on.
+#[cfg(feature = "hickory-resolver")]
+#[derive(Clone)]
+pub struct HickoryResolver(hickory_resolver::TokioAsyncResolver);
+
+#[cfg(feature = "hickory-resolver")]
+impl HickoryResolver {
+    /// Create a resolver with the system configuration, e.g. `/etc/resolv.conf` on Unix
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the system configuration can not be read
+    pub fn from_system_conf() -> Result<Self> {
+        hickory_resolver::TokioAsyncResolver::tokio_from_system_conf()
+            .map(Self)
+            .map_err(|_| Error::AddressResolve)
+    }
+}
+
+#[cfg(feature = "hickory-resolver")]
+impl From<hickory_resolver::TokioAsyncResolver> for HickoryResolver {
+    fn from(resolver: hickory_resolver::TokioAsyncResolver) -> Self {
+        Self(resolver)
+    }
+}
+
+#[cfg(feature = "hickory-resolver")]
+impl core::fmt::Debug for HickoryResolver {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("HickoryResolver").finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "hickory-resolver")]
+impl NtpHostResolver for HickoryResolver {
+    async fn resolve(
+        &self,
+        host: &str,
+        port: u16,
+    ) -> Result<impl Iterator<Item = SocketAddr>> {
+        let lookup = self
+            .0
+            .lookup_ip(host)
+            .await
+            .map_err(|_| Error::AddressResolve)?;
+
+        Ok(lookup
+            .into_iter()
+            .map(move |addr| SocketAddr::new(addr, port)))
+    }
+}
+
+#[cfg(feature = "hickory-resolver")]
+impl NtpSrvResolver for HickoryResolver {
+    async fn resolve_srv(
+        &self,
+        name: &str,
+    ) -> Result<impl Iterator<Item = SrvTarget>> {
+        let records = self
+            .0
+            .srv_lookup(name)
+            .await
+            .map_err(|_| Error::AddressResolve)?;
+        let mut targets = std::vec::Vec::new();
+
+        for record in records.iter() {
+            // a target that does not resolve is skipped rather than failing the whole lookup
+            let Ok(addrs) = self.0.lookup_ip(record.target().clone()).await
+            else {
+                continue;
+            };
+
+            targets.extend(addrs.iter().map(|addr| SrvTarget {
+                priority: record.priority(),
+                weight: record.weight(),
+                addr: SocketAddr::new(addr, record.port()),
+            }));
+        }
+
+        Ok(targets.into_iter())
+    }
+}
+
+/// Order SRV targets as RFC 2782 specifies: by priority, then by weighted random selection
+/// among the targets with the same priority
+///
+/// A target is selected with the probability proportional to its weight, targets with zero
+/// weight have a small chance to be selected first. `seed` initializes the pseudo-random
+/// selection.
+pub(crate) fn order_srv_targets(targets: &mut [SrvTarget], seed: u64) {
+    let mut state = seed;
+
+    // targets with zero weight are placed at the beginning of every priority group
+    targets.sort_unstable_by_key(|target| (target.priority, target.weight));
+
+    let mut start = 0;
+    while start < targets.len() {
+        let priority = targets[start].priority;
+        let end = targets[start..]
+            .iter()
+            .position(|target| target.priority != priority)
+            .map_or(targets.len(), |len| start + len);
+
+        for i in start..end {
+            let total: u64 =
+                targets[i..end].iter().map(|t| u64::from(t.weight)).sum();
+            let selected = next_random(&mut state) % (total + 1);
+            let mut running = 0;
+            let pos = targets[i..end]
+                .iter()
+                .position(|target| {
+                    running += u64::from(target.weight);
+                    running >= selected
+                })
+                .unwrap_or(0);
+
+            // keep the order of the rest of the group, so zero weights stay in front
+            targets[i..=i + pos].rotate_right(1);
+        }
+
+        start = end;
+    }
+}
+
+/// `SplitMix64` pseudo-random number generator step
+fn next_random(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+
+    z ^ (z >> 31)
+}
+
+/// Maximum length of a DNS name in the text form
+const MAX_NAME_LEN: usize = 253;
+
+/// Fixed-capacity buffer to compose DNS names without heap allocations
+pub(crate) struct NameBuf {
+    buf: [u8; MAX_NAME_LEN],
+    len: usize,
+}
+
+impl NameBuf {
+    pub(crate) const fn new() -> Self {
+        Self {
+            buf: [0; MAX_NAME_LEN],
+            len: 0,
+        }
+    }
+
+    pub(crate) fn as_str(&self) -> &str {
+        // only complete `str` slices are written into the buffer
+        core::str::from_utf8(&self.buf[..self.len]).unwrap_or_default()
+    }
+}
+
+impl core::fmt::Write for NameBuf {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let end = self.len + s.len();
+        let dest = self.buf.get_mut(self.len..end).ok_or(core::fmt::Error)?;
+
+        dest.copy_from_slice(s.as_bytes());
+        self.len = end;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{order_srv_targets, SrvTarget};
+    use crate::net::SocketAddr;
+
+    fn target(priority: u16, weight: u16, port: u16) -> SrvTarget {
+        SrvTarget {
+            priority,
+            weight,
+            addr: SocketAddr::from(([127, 0, 0, 1], port)),
+        }
+    }
+
+    #[test]
+    fn test_order_srv_targets_priority() {
+        for seed in 0..100 {
+            let mut targets = [
+                target(20, 10, 1),
+                target(10, 0, 2),
+                target(30, 0, 3),
+                target(10, 50, 4),
+                target(20, 0, 5),
+            ];
+
+            order_srv_targets(&mut targets, seed);
+            let priorities = targets.map(|t| t.priority);
+            assert_eq!([10, 10, 20, 20, 30], priorities);
+        }
+    }
+
+    #[test]
+    fn test_order_srv_targets_weight() {
+        const RUNS: u64 = 10_000;
+        let mut first = [0u64; 3];
+
+        for seed in 0..RUNS {
+            let mut targets =
+                [target(1, 0, 0), target(1, 1, 1), target(1, 3, 2)];
+
+            order_srv_targets(&mut targets, seed);
+            first[usize::from(targets[0].addr.port())] += 1;
+        }
+
+        // the running sums of 0, 1 and 4 are selected with a number in 0..=4
+        let expected = [RUNS / 5, RUNS / 5, RUNS * 3 / 5];
+        for (count, expected) in first.iter().zip(expected) {
+            assert!(count.abs_diff(expected) < RUNS / 50, "{first:?}");
+        }
+    }
+
+    #[test]
+    fn test_order_srv_targets_zero_weights() {
+        let mut targets = [target(1, 0, 1), target(1, 0, 2)];
+
+        // zero weights keep the order of the records
+        order_srv_targets(&mut targets, 42);
+        assert_eq!([1, 2], targets.map(|t| t.addr.port()));
+        order_srv_targets(&mut [], 42);
+    }
+
+    #[cfg(feature = "hickory-resolver")]
+    #[test]
+    fn test_hickory_resolver_localhost() {
+        use super::{HickoryResolver, NtpHostResolver};
+        use hickory_resolver::config::{ResolverConfig, ResolverOpts};
+
+        let resolver =
+            HickoryResolver::from(hickory_resolver::TokioAsyncResolver::tokio(
+                ResolverConfig::new(),
+                ResolverOpts::default(),
+            ));
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+
+        // `localhost` is resolved locally without querying name servers
+        let addrs: std::vec::Vec<_> = runtime
+            .block_on(resolver.resolve("localhost", 123))
+            .unwrap()
+            .collect();
+        assert!(addrs.contains(&SocketAddr::from(([127, 0, 0, 1], 123))));
+    }
+}
@@ -0,0 +1,110 @@
+use crate::NtpResult;
+
+use core::time::Duration;
+
+/// Helper to schedule NTP requests according to the poll interval suggested by a server
+///
+/// The scheduler keeps track of the poll interval reported in the last response and
+/// tells how long to wait before the next request. The interval is always clamped to the
+/// `[min, max]` range the scheduler has been created with, so a misbehaving server
+/// can not make a client poll too often or stop polling at all.
+#[derive(Debug, Copy, Clone)]
+pub struct PollScheduler {
+    min: Duration,
+    max: Duration,
+    suggested: Option<Duration>,
+}
+
+impl PollScheduler {
+    /// Create new scheduler with the given interval boundaries
+    ///
+    /// If `min` is greater than `max`, the boundaries are swapped
+    #[must_use]
+    pub fn new(min: Duration, max: Duration) -> Self {
+        let (min, max) = if min <= max { (min, max) } else { (max, min) };
+
+        Self {
+            min,
+            max,
+            suggested: None,
+        }
+    }
+
+    /// Record the poll interval reported in the last NTP response
+    pub fn update(&mut self, result: &NtpResult) {
+        self.suggested = Some(Duration::from_secs(result.poll_interval()));
+    }
+
+    /// Forget the last response, e.g. after switching to another server
+    pub fn reset(&mut self) {
+        self.suggested = None;
+    }
+
+    /// Returns how long to wait before the next request
+    ///
+    /// Before any response has been recorded the minimum interval is returned
+    #[must_use]
+    pub fn next_interval(&self) -> Duration {
+        self.suggested
+            .map_or(self.min, |interval| interval.clamp(self.min, self.max))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PollScheduler;
+    use crate::fixtures::ntp_result;
+    use crate::NtpResult;
+
+    use core::time::Duration;
+
+    fn result_with_poll(poll: i8) -> NtpResult {
+        let mut result = ntp_result(0, 0, 0);
+        result.poll = poll;
+        result
+    }
+
+    #[test]
+    fn test_poll_interval() {
+        assert_eq!(1, result_with_poll(0).poll_interval());
+        assert_eq!(64, result_with_poll(6).poll_interval());
+        assert_eq!(131_072, result_with_poll(17).poll_interval());
+        assert_eq!(0, result_with_poll(-3).poll_interval());
+        assert_eq!(u64::MAX, result_with_poll(i8::MAX).poll_interval());
+    }
+
+    #[test]
+    fn test_poll_scheduler() {
+        let mut scheduler = PollScheduler::new(
+            Duration::from_secs(16),
+            Duration::from_secs(1024),
+        );
+
+        assert_eq!(Duration::from_secs(16), scheduler.next_interval());
+
+        scheduler.update(&result_with_poll(6));
+        assert_eq!(Duration::from_secs(64), scheduler.next_interval());
+
+        scheduler.update(&result_with_poll(0));
+        assert_eq!(Duration::from_secs(16), scheduler.next_interval());
+
+        scheduler.update(&result_with_poll(17));
+        assert_eq!(Duration::from_secs(1024), scheduler.next_interval());
+
+        scheduler.reset();
+        assert_eq!(Duration::from_secs(16), scheduler.next_interval());
+    }
+
+    #[test]
+    fn test_poll_scheduler_swapped_boundaries() {
+        let mut scheduler = PollScheduler::new(
+            Duration::from_secs(256),
+            Duration::from_secs(8),
+        );
+
+        scheduler.update(&result_with_poll(4));
+        assert_eq!(Duration::from_secs(16), scheduler.next_interval());
+        scheduler.update(&result_with_poll(10));
+        assert_eq!(Duration::from_secs(256), scheduler.next_interval());
+    }
+}
@@ -0,0 +1,270 @@
+use crate::{Error, NTP_PORT};
+
+use core::fmt::{Debug, Display, Formatter};
+use core::str::FromStr;
+use core::time::Duration;
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::time::Instant;
+
+/// NTP server address with the standard NTP port used by default
+///
+/// The type parses server addresses in the forms users usually type them:
+///
+/// - `host` or `host:port`, e.g. `time.google.com` or `time.google.com:123`
+/// - `ipv4` or `ipv4:port`, e.g. `192.0.2.1` or `192.0.2.1:123`
+/// - `ipv6` or `[ipv6]` or `[ipv6]:port`, e.g. `2001:db8::1` or `[2001:db8::1]:123`
+///
+/// IPv6 addresses may contain a numeric scope identifier, e.g. `[fe80::1%2]:123`. Port `123`
+/// is used if the port is omitted, port `0` is rejected.
+///
+/// ```rust
+/// use sntpc::ServerAddr;
+///
+/// let server: ServerAddr = "time.google.com".parse().unwrap();
+/// assert_eq!(123, server.port());
+///
+/// let server: ServerAddr = "[2001:db8::1]:1123".parse().unwrap();
+/// assert_eq!(1123, server.port());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServerAddr {
+    /// IP address of the server
+    Ip(SocketAddr),
+    /// Host name of the server that has to be resolved
+    Host {
+        /// Host name
+        name: String,
+        /// Port
+        port: u16,
+    },
+}
+
+impl ServerAddr {
+    /// Returns the port of the server
+    #[must_use]
+    pub fn port(&self) -> u16 {
+        match self {
+            ServerAddr::Ip(addr) => addr.port(),
+            ServerAddr::Host { port, .. } => *port,
+        }
+    }
+}
+
+impl FromStr for ServerAddr {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let addr = s
+            .parse::<SocketAddr>()
+            .or_else(|_| s.parse::<IpAddr>().map(|ip| (ip, NTP_PORT).into()))
+            .or_else(|_| {
+                // IPv6 addresses without a port, possibly with a scope identifier
+                if s.starts_with('[') {
+                    format!("{s}:{NTP_PORT}").parse()
+                } else {
+                    format!("[{s}]:{NTP_PORT}").parse()
+                }
+            });
+
+        if let Ok(addr) = addr {
+            return if addr.port() == 0 {
+                Err(Error::AddressResolve)
+            } else {
+                Ok(ServerAddr::Ip(addr))
+            };
+        }
+
+        let (name, port) = match s.rsplit_once(':') {
+            Some((name, port)) => (
+                name,
+                port.parse::<u16>().map_err(|_| Error::AddressResolve)?,
+            ),
+            None => (s, NTP_PORT),
+        };
+        let is_valid_name = !name.is_empty()
+            && name.chars().all(|c| {
+                c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '_')
+            });
+
+        if port == 0 || !is_valid_name {
+            return Err(Error::AddressResolve);
+        }
+
+        Ok(ServerAddr::Host {
+            name: name.to_string(),
+            port,
+        })
+    }
+}
+
+impl Display for ServerAddr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ServerAddr::Ip(addr) => write!(f, "{addr}"),
+            ServerAddr::Host { name, port } => write!(f, "{name}:{port}"),
+        }
+    }
+}
+
+impl ToSocketAddrs for ServerAddr {
+    type Iter = std::vec::IntoIter<SocketAddr>;
+
+    fn to_socket_addrs(&self) -> std::io::Result<Self::Iter> {
+        match self {
+            ServerAddr::Ip(addr) => Ok(vec![*addr].into_iter()),
+            ServerAddr::Host { name, port } => {
+                (name.as_str(), *port).to_socket_addrs()
+            }
+        }
+    }
+}
+
+/// Maximum number of addresses returned by [`resolve`]
+pub const MAX_RESOLVED_ADDRS: usize = 8;
+
+/// Addresses of an NTP server returned by [`resolve`]
+pub type ResolvedAddrs = heapless::Vec<SocketAddr, MAX_RESOLVED_ADDRS>;
+
+/// Resolve an NTP server address into the list of socket addresses to query
+///
+/// `host` is parsed as [`ServerAddr`], so the standard NTP port is used if it is omitted. Pool
+/// host names usually resolve to several addresses, so the first [`MAX_RESOLVED_ADDRS`] of
+/// them are returned and may be tried one by one, e.g. with [`crate::sync::get_time_multi`]:
+///
+/// ```rust,no_run
+/// let addrs = sntpc::resolve("pool.ntp.org").expect("Unable to resolve host");
+///
+/// for addr in &addrs {
+///     println!("{addr}");
+/// }
+/// ```
+///
+/// The resolution blocks the calling thread. See [`ResolveCache`] to avoid resolving the same
+/// host before every request.
+///
+/// # Errors
+///
+/// Will return `Err(Error::AddressResolve)` if `host` is not a valid server address or does
+/// not resolve to any address
+pub fn resolve(host: &str) -> Result<ResolvedAddrs, Error> {
+    let addrs = host
+        .parse::<ServerAddr>()?
+        .to_socket_addrs()
+        .map_err(|_| Error::AddressResolve)?
+        .take(MAX_RESOLVED_ADDRS)
+        .collect::<ResolvedAddrs>();
+
+    if addrs.is_empty() {
+        return Err(Error::AddressResolve);
+    }
+
+    Ok(addrs)
+}
+
+struct CacheEntry {
+    host: String,
+    addrs: ResolvedAddrs,
+    resolved_at: Instant,
+}
+
+/// Cache of [`resolve`] results
+///
+/// Entries expire after the configured time to live, so changes of DNS records are picked up
+/// eventually. If the cache is full, the least recently used entry is replaced.
+///
+/// ```rust,no_run
+/// use sntpc::ResolveCache;
+/// use std::time::Duration;
+///
+/// let mut cache = ResolveCache::new(4, Duration::from_secs(300));
+///
+/// // only the first call resolves the host
+/// for _ in 0..3 {
+///     let addrs = cache.resolve("pool.ntp.org").expect("Unable to resolve host");
+///     # let _ = addrs;
+/// }
+/// ```
+pub struct ResolveCache {
+    capacity: usize,
+    ttl: Duration,
+    /// Entries in the order of use, the least recently used one first
+    entries: Vec<CacheEntry>,
+}
+
+impl ResolveCache {
+    /// Create a cache of at most `capacity` hosts, which keeps the results for `ttl`
+    #[must_use]
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        ResolveCache {
+            capacity,
+            ttl,
+            entries: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Returns cached addresses of `host` or resolves it with [`resolve`] if there are no
+    /// cached addresses or they have expired
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` in the same cases as [`resolve`]. Failed resolutions are not cached.
+    pub fn resolve(&mut self, host: &str) -> Result<ResolvedAddrs, Error> {
+        let now = Instant::now();
+
+        if let Some(idx) = self.entries.iter().position(|e| e.host == host) {
+            let entry = self.entries.remove(idx);
+
+            if now.duration_since(entry.resolved_at) < self.ttl {
+                let addrs = entry.addrs.clone();
+                self.entries.push(entry);
+
+                return Ok(addrs);
+            }
+        }
+
+        let addrs = resolve(host)?;
+
+        if self.capacity == 0 {
+            return Ok(addrs);
+        }
+
+        if self.entries.len() == self.capacity {
+            self.entries.remove(0);
+        }
+
+        self.entries.push(CacheEntry {
+            host: host.to_string(),
+            addrs: addrs.clone(),
+            resolved_at: now,
+        });
+
+        Ok(addrs)
+    }
+
+    /// Returns the number of cached hosts, including the expired ones
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if there are no cached hosts
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Forget all cached hosts
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+impl Debug for ResolveCache {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ResolveCache")
+            .field("capacity", &self.capacity)
+            .field("ttl", &self.ttl)
+            .field("hosts", &self.entries.len())
+            .finish()
+    }
+}
@@ -14,5 +14,23 @@ cfg_socket_impl!("embassy-socket", {
     mod embassy;
 });
 cfg_socket_impl!("tokio-socket", {
-    mod tokio;
+    pub(crate) mod tokio;
 });
+cfg_socket_impl!("smoltcp-socket", {
+    pub mod smoltcp;
+});
+
+/// Map an I/O error reported by `std` based sockets to [`crate::Error`]
+#[cfg(any(feature = "std-socket", feature = "tokio-socket"))]
+fn from_io_error(e: &::std::io::Error) -> crate::Error {
+    use ::std::io::ErrorKind;
+
+    match e.kind() {
+        // read timeout is reported differently depending on the platform
+        ErrorKind::WouldBlock | ErrorKind::TimedOut => crate::Error::Timeout,
+        ErrorKind::HostUnreachable | ErrorKind::NetworkUnreachable => {
+            crate::Error::NoRoute
+        }
+        _ => crate::Error::Network,
+    }
+}
@@ -1,9 +1,11 @@
 #[cfg(any(feature = "log", feature = "defmt"))]
 use crate::log::error;
 use crate::{net::SocketAddr, Error, NtpUdpSocket, Result};
-use embassy_net::{udp::UdpSocket, IpAddress, IpEndpoint};
+use embassy_net::udp::{RecvError, SendError, UdpSocket};
+use embassy_net::{IpAddress, IpEndpoint};
 
 use core::net::IpAddr;
+use core::time::Duration;
 
 impl NtpUdpSocket for UdpSocket<'_> {
     async fn send_to(&self, buf: &[u8], addr: SocketAddr) -> Result<usize> {
@@ -21,7 +23,10 @@ impl NtpUdpSocket for UdpSocket<'_> {
             Err(e) => {
                 #[cfg(any(feature = "log", feature = "defmt"))]
                 error!("Error while sending to {}: {:?}", endpoint, e);
-                Err(Error::Network)
+                Err(match e {
+                    SendError::NoRoute => Error::NoRoute,
+                    SendError::SocketNotBound => Error::SocketNotBound,
+                })
             }
         }
     }
@@ -42,8 +47,24 @@ impl NtpUdpSocket for UdpSocket<'_> {
             Err(e) => {
                 #[cfg(any(feature = "log", feature = "defmt"))]
                 error!("Error receiving {:?}", e);
-                Err(Error::Network)
+                Err(match e {
+                    RecvError::Truncated => Error::Truncated,
+                })
             }
         }
     }
+
+    async fn recv_from_timeout(
+        &self,
+        buf: &mut [u8],
+        timeout: Duration,
+    ) -> Result<(usize, SocketAddr)> {
+        let timeout = embassy_time::Duration::from_micros(
+            u64::try_from(timeout.as_micros()).unwrap_or(u64::MAX),
+        );
+
+        embassy_time::with_timeout(timeout, NtpUdpSocket::recv_from(self, buf))
+            .await
+            .unwrap_or(Err(Error::Timeout))
+    }
 }
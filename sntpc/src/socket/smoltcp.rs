@@ -0,0 +1,175 @@
+//! [`NtpUdpSocket`] trait implementation for the [`smoltcp`](https://github.com/smoltcp-rs/smoltcp)
+//! UDP socket
+//!
+//! `smoltcp::socket::udp::Socket` requires mutable access for sending and receiving, so it
+//! implements [`NtpUdpSocketMut`] and may be passed to [`crate::get_time_mut`] and friends
+//! directly. [`UdpSocketWrapper`] provides interior mutability for the [`NtpUdpSocket`] based
+//! functions. The module also provides statically sized buffers that are enough to perform SNTP
+//! requests.
+#[cfg(any(feature = "log", feature = "defmt"))]
+use crate::log::debug;
+use crate::{Error, NtpUdpSocket, NtpUdpSocketMut, Result};
+use smoltcp::socket::udp;
+use smoltcp::socket::udp::UdpMetadata;
+use smoltcp::storage::PacketMetadata;
+use smoltcp::wire::{IpAddress, IpEndpoint};
+
+use core::cell::RefCell;
+use core::fmt::{Debug, Formatter};
+use core::net::{IpAddr, SocketAddr};
+
+/// Number of packets [`Buffers`] are able to hold in each direction
+const PACKETS_NUM: usize = 16;
+/// Size of [`Buffers`] payload storage in each direction
+const PAYLOAD_SIZE: usize = 256;
+
+/// Storage for UDP socket packet metadata and payload
+pub struct Buffers {
+    pub rx_meta: [PacketMetadata<UdpMetadata>; PACKETS_NUM],
+    pub tx_meta: [PacketMetadata<UdpMetadata>; PACKETS_NUM],
+    pub rx_buffer: [u8; PAYLOAD_SIZE],
+    pub tx_buffer: [u8; PAYLOAD_SIZE],
+}
+
+impl Default for Buffers {
+    fn default() -> Self {
+        Buffers {
+            rx_meta: [PacketMetadata::EMPTY; PACKETS_NUM],
+            tx_meta: [PacketMetadata::EMPTY; PACKETS_NUM],
+            rx_buffer: [0u8; PAYLOAD_SIZE],
+            tx_buffer: [0u8; PAYLOAD_SIZE],
+        }
+    }
+}
+
+/// UDP socket packet buffers built on top of [`Buffers`] storage
+pub struct UdpSocketBuffers<'a> {
+    pub rx: udp::PacketBuffer<'a>,
+    pub tx: udp::PacketBuffer<'a>,
+}
+
+impl<'a> UdpSocketBuffers<'a> {
+    /// Create packet buffers that use the given storage
+    pub fn new(buffers: &'a mut Buffers) -> Self {
+        UdpSocketBuffers {
+            rx: udp::PacketBuffer::new(
+                buffers.rx_meta.as_mut(),
+                buffers.rx_buffer.as_mut(),
+            ),
+            tx: udp::PacketBuffer::new(
+                buffers.tx_meta.as_mut(),
+                buffers.tx_buffer.as_mut(),
+            ),
+        }
+    }
+}
+
+/// Wrapper around `smoltcp` UDP socket that implements [`NtpUdpSocket`] trait
+pub struct UdpSocketWrapper<'a, 'b> {
+    socket: RefCell<&'b mut udp::Socket<'a>>,
+}
+
+impl<'a, 'b> UdpSocketWrapper<'a, 'b> {
+    /// Wrap the given `smoltcp` UDP socket
+    pub fn new(socket: &'b mut udp::Socket<'a>) -> Self {
+        UdpSocketWrapper {
+            socket: RefCell::new(socket),
+        }
+    }
+
+    /// Release the wrapped socket, e.g. to use it for other traffic after NTP requests
+    ///
+    /// ```rust
+    /// use sntpc::smoltcp::{Buffers, UdpSocketBuffers, UdpSocketWrapper};
+    /// use smoltcp::socket::udp;
+    ///
+    /// let mut buffers = Buffers::default();
+    /// let buffers = UdpSocketBuffers::new(&mut buffers);
+    /// let mut socket = udp::Socket::new(buffers.rx, buffers.tx);
+    /// let wrapper = UdpSocketWrapper::new(&mut socket);
+    /// // perform NTP requests with the wrapper
+    /// let socket = wrapper.into_inner();
+    ///
+    /// socket.bind(1123).unwrap();
+    /// ```
+    pub fn into_inner(self) -> &'b mut udp::Socket<'a> {
+        self.socket.into_inner()
+    }
+}
+
+impl<'a> AsMut<udp::Socket<'a>> for UdpSocketWrapper<'a, '_> {
+    fn as_mut(&mut self) -> &mut udp::Socket<'a> {
+        self.socket.get_mut()
+    }
+}
+
+impl Debug for UdpSocketWrapper<'_, '_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("UdpSocketWrapper")
+            .field("socket", &self.socket.borrow().endpoint())
+            .finish()
+    }
+}
+
+impl NtpUdpSocket for UdpSocketWrapper<'_, '_> {
+    async fn send_to(&self, buf: &[u8], addr: SocketAddr) -> Result<usize> {
+        send_slice(&mut self.socket.borrow_mut(), buf, addr)
+    }
+
+    async fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddr)> {
+        recv_slice(&mut self.socket.borrow_mut(), buf)
+    }
+}
+
+impl NtpUdpSocketMut for udp::Socket<'_> {
+    async fn send_to(&mut self, buf: &[u8], addr: SocketAddr) -> Result<usize> {
+        send_slice(self, buf, addr)
+    }
+
+    async fn recv_from(
+        &mut self,
+        buf: &mut [u8],
+    ) -> Result<(usize, SocketAddr)> {
+        recv_slice(self, buf)
+    }
+}
+
+fn send_slice(
+    socket: &mut udp::Socket<'_>,
+    buf: &[u8],
+    addr: SocketAddr,
+) -> Result<usize> {
+    // smoltcp still has its own address enum
+    let endpoint = IpEndpoint::new(
+        match addr.ip() {
+            IpAddr::V4(addr) => IpAddress::Ipv4(addr),
+            IpAddr::V6(addr) => IpAddress::Ipv6(addr),
+        },
+        addr.port(),
+    );
+
+    if socket.send_slice(buf, endpoint).is_ok() {
+        return Ok(buf.len());
+    }
+
+    #[cfg(any(feature = "log", feature = "defmt"))]
+    debug!("Unable to send request to {:?}", addr);
+    Err(Error::Network)
+}
+
+fn recv_slice(
+    socket: &mut udp::Socket<'_>,
+    buf: &mut [u8],
+) -> Result<(usize, SocketAddr)> {
+    let Ok((size, meta)) = socket.recv_slice(buf) else {
+        #[cfg(any(feature = "log", feature = "defmt"))]
+        debug!("Unable to receive response");
+        return Err(Error::Network);
+    };
+    let addr = match meta.endpoint.addr {
+        IpAddress::Ipv4(addr) => IpAddr::V4(addr),
+        IpAddress::Ipv6(addr) => IpAddr::V6(addr),
+    };
+
+    Ok((size, SocketAddr::new(addr, meta.endpoint.port)))
+}
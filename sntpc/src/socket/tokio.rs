@@ -1,14 +1,131 @@
-use crate::{Error, NtpUdpSocket, Result};
+use super::from_io_error;
+use crate::{Error, NtpRecvSocket, NtpSendSocket, NtpUdpSocket, Result};
 use tokio::net::UdpSocket;
 
 use core::net::SocketAddr;
+use core::time::Duration;
+use std::sync::Arc;
 
+/// A socket connected with [`UdpSocket::connect`] to a single NTP server is used in the
+/// connected mode: requests to the peer are sent with [`UdpSocket::send`] and responses are
+/// received with [`UdpSocket::recv`]. The kernel then drops datagrams from other sources
+/// before they reach the client, e.g. spoofed responses that would otherwise be reported
+/// as [`Error::ResponseAddressMismatch`]:
+///
+/// ```rust,no_run
+/// # async fn connected() {
+/// use sntpc::{NtpContext, StdTimestampGen};
+/// use tokio::net::UdpSocket;
+///
+/// let addr = "216.239.35.0:123".parse().unwrap();
+/// let socket = UdpSocket::bind("0.0.0.0:0").await.unwrap();
+/// socket.connect(addr).await.unwrap();
+///
+/// let context = NtpContext::new(StdTimestampGen::default());
+/// let result = sntpc::get_time(addr, &socket, context).await;
+/// # }
+/// ```
 impl NtpUdpSocket for UdpSocket {
     async fn send_to(&self, buf: &[u8], addr: SocketAddr) -> Result<usize> {
-        self.send_to(buf, addr).await.map_err(|_| Error::Network)
+        if self.peer_addr().is_ok_and(|peer| peer == addr) {
+            self.send(buf).await
+        } else {
+            self.send_to(buf, addr).await
+        }
+        .map_err(|e| from_io_error(&e))
     }
 
     async fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddr)> {
-        self.recv_from(buf).await.map_err(|_| Error::Network)
+        if let Ok(peer) = self.peer_addr() {
+            self.recv(buf).await.map(|len| (len, peer))
+        } else {
+            self.recv_from(buf).await
+        }
+        .map_err(|e| from_io_error(&e))
+    }
+
+    /// Bounds the receive with `tokio::time::timeout`, so the runtime must have the time
+    /// driver enabled
+    async fn recv_from_timeout(
+        &self,
+        buf: &mut [u8],
+        timeout: Duration,
+    ) -> Result<(usize, SocketAddr)> {
+        tokio::time::timeout(timeout, NtpUdpSocket::recv_from(self, buf))
+            .await
+            .unwrap_or(Err(Error::Timeout))
+    }
+}
+
+/// Sending half of a shared `tokio` UDP socket
+///
+/// Together with [`TokioRecvHalf`] allows to send requests with [`crate::sntp_send_request`]
+/// in one task and receive responses with [`crate::sntp_process_response`] in another:
+///
+/// ```rust,no_run
+/// # async fn split() {
+/// use sntpc::{NtpContext, StdTimestampGen, TokioRecvHalf, TokioSendHalf};
+/// use tokio::net::UdpSocket;
+///
+/// let socket = UdpSocket::bind("0.0.0.0:0").await.unwrap();
+/// let (tx, rx) = TokioSendHalf::split(socket);
+/// let context = NtpContext::new(StdTimestampGen::default());
+/// let addr = "216.239.35.0:123".parse().unwrap();
+///
+/// let request = sntpc::sntp_send_request(addr, &tx, context).await.unwrap();
+/// let receiver = tokio::spawn(async move {
+///     sntpc::sntp_process_response(addr, &rx, context, request).await
+/// });
+/// let result = receiver.await.unwrap();
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct TokioSendHalf(Arc<UdpSocket>);
+
+/// Receiving half of a shared `tokio` UDP socket, see [`TokioSendHalf`]
+#[derive(Debug, Clone)]
+pub struct TokioRecvHalf(Arc<UdpSocket>);
+
+impl TokioSendHalf {
+    /// Split the socket into the sending and the receiving halves
+    #[must_use]
+    pub fn split(socket: UdpSocket) -> (TokioSendHalf, TokioRecvHalf) {
+        let socket = Arc::new(socket);
+
+        (TokioSendHalf(Arc::clone(&socket)), TokioRecvHalf(socket))
+    }
+
+    /// Create the sending half of the shared socket
+    #[must_use]
+    pub fn new(socket: Arc<UdpSocket>) -> Self {
+        TokioSendHalf(socket)
+    }
+}
+
+impl TokioRecvHalf {
+    /// Create the receiving half of the shared socket
+    #[must_use]
+    pub fn new(socket: Arc<UdpSocket>) -> Self {
+        TokioRecvHalf(socket)
+    }
+}
+
+impl NtpSendSocket for TokioSendHalf {
+    async fn send_to(&self, buf: &[u8], addr: SocketAddr) -> Result<usize> {
+        NtpUdpSocket::send_to(&*self.0, buf, addr).await
+    }
+}
+
+impl NtpRecvSocket for TokioRecvHalf {
+    async fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddr)> {
+        NtpUdpSocket::recv_from(&*self.0, buf).await
+    }
+
+    async fn recv_from_timeout(
+        &self,
+        buf: &mut [u8],
+        timeout: Duration,
+    ) -> Result<(usize, SocketAddr)> {
+        NtpUdpSocket::recv_from_timeout(&*self.0, buf, timeout).await
     }
 }
@@ -0,0 +1,136 @@
+//! Periodic polling of an NTP server as an asynchronous stream driven by `tokio` timers
+//!
+//! The module is available with the `tokio-socket` feature enabled. The stream
+//! returned by [`ntp_stream`] implements [`futures_core::Stream`], so it can be consumed with
+//! the stream combinators of `futures` or `tokio-stream`.
+use crate::{
+    get_time, net::SocketAddr, Error, NtpContext, NtpResult, Result,
+    StdTimestampGen,
+};
+
+use futures_core::Stream;
+use tokio::net::UdpSocket;
+use tokio::time::Duration;
+
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{ready, Context, Poll};
+use std::sync::Arc;
+
+/// Maximum power of two the poll interval is multiplied by after consecutive failures
+const MAX_BACKOFF_SHIFT: u32 = 3;
+
+type Query = Pin<Box<dyn Future<Output = Result<NtpResult>> + Send>>;
+
+/// Periodically query an NTP server and yield the result of every request
+///
+/// The first request is sent as soon as the stream is polled, the following ones every
+/// `interval`. Each request is bounded by `interval` as well, so a lost response is yielded
+/// as [`Error::Timeout`] rather than stalling the stream. Errors do not terminate the stream:
+/// after [`Error::Timeout`] or [`Error::Network`] the next request is delayed by `interval`
+/// doubled for every consecutive failure, up to `interval * 2^3`. Any other result resets the
+/// delay back to `interval`. The stream never ends.
+///
+/// # Arguments
+///
+/// * `addr` - The socket address of the NTP server
+/// * `socket` - A shared `tokio` UDP socket
+/// * `context` - An SNTP context containing a timestamp generator
+/// * `interval` - Delay between two successful requests
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # async fn poll() {
+/// use core::future::poll_fn;
+/// use core::pin::Pin;
+/// use futures_core::Stream;
+/// use sntpc::{stream::ntp_stream, NtpContext, StdTimestampGen};
+/// use std::sync::Arc;
+/// use tokio::net::UdpSocket;
+/// use tokio::time::Duration;
+///
+/// let socket = Arc::new(UdpSocket::bind("0.0.0.0:0").await.unwrap());
+/// let context = NtpContext::new(StdTimestampGen::default());
+/// let addr = "216.239.35.0:123".parse().unwrap();
+/// let mut stream = ntp_stream(addr, socket, context, Duration::from_secs(64));
+///
+/// while let Some(result) = poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)).await {
+///     println!("{result:?}");
+/// }
+/// # }
+/// ```
+pub fn ntp_stream(
+    addr: SocketAddr,
+    socket: Arc<UdpSocket>,
+    context: NtpContext<StdTimestampGen>,
+    interval: Duration,
+) -> impl Stream<Item = Result<NtpResult>> + Send + Unpin {
+    NtpStream {
+        query: query(
+            addr,
+            Arc::clone(&socket),
+            context,
+            interval,
+            Duration::ZERO,
+        ),
+        addr,
+        socket,
+        context,
+        interval,
+        failures: 0,
+    }
+}
+
+struct NtpStream {
+    addr: SocketAddr,
+    socket: Arc<UdpSocket>,
+    context: NtpContext<StdTimestampGen>,
+    interval: Duration,
+    failures: u32,
+    query: Query,
+}
+
+impl Stream for NtpStream {
+    type Item = Result<NtpResult>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let result = ready!(self.query.as_mut().poll(cx));
+
+        self.failures = match result {
+            Err(Error::Timeout | Error::Network) => {
+                self.failures.saturating_add(1)
+            }
+            _ => 0,
+        };
+
+        let delay = self.interval * (1 << self.failures.min(MAX_BACKOFF_SHIFT));
+        self.query = query(
+            self.addr,
+            Arc::clone(&self.socket),
+            self.context,
+            self.interval,
+            delay,
+        );
+
+        Poll::Ready(Some(result))
+    }
+}
+
+fn query(
+    addr: SocketAddr,
+    socket: Arc<UdpSocket>,
+    context: NtpContext<StdTimestampGen>,
+    interval: Duration,
+    delay: Duration,
+) -> Query {
+    Box::pin(async move {
+        tokio::time::sleep(delay).await;
+        tokio::time::timeout(interval, get_time(addr, &*socket, context))
+            .await
+            .unwrap_or(Err(Error::Timeout))
+    })
+}
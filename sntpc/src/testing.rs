@@ -0,0 +1,220 @@
+//! Utilities to test SNTP clients without access to the Internet
+//!
+//! The module is available with the `test-utils` feature enabled and provides
+//! [`MockNtpServer`], a local SNTP server that answers requests with configurable values.
+//!
+//! ```rust
+//! # #[cfg(all(feature = "sync", feature = "std-socket"))]
+//! # fn main() {
+//! use sntpc::testing::MockNtpServer;
+//! use sntpc::{sync::get_time, NtpContext, StdTimestampGen};
+//! use std::net::UdpSocket;
+//!
+//! let server = MockNtpServer::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+//! server.set_time(1_700_000_000, 0);
+//!
+//! let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+//! let context = NtpContext::new(StdTimestampGen::default());
+//! let result = get_time(server.local_addr(), &socket, context).unwrap();
+//!
+//! assert_eq!(1_700_000_000, result.sec());
+//! assert_eq!(1, server.request_count());
+//! # }
+//! # #[cfg(not(all(feature = "sync", feature = "std-socket")))]
+//! # fn main() {}
+//! ```
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime};
+
+/// Seconds between the NTP epoch (1900) and the UNIX epoch (1970)
+const NTP_TIMESTAMP_DELTA: u64 = 2_208_988_800;
+/// How often the server thread checks whether it should stop
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+#[derive(Debug, Copy, Clone)]
+struct Settings {
+    time: Option<(u32, u32)>,
+    stratum: u8,
+    version: u8,
+    origin_override: Option<u64>,
+    delay: Duration,
+}
+
+#[derive(Debug)]
+struct Shared {
+    settings: Mutex<Settings>,
+    requests: AtomicUsize,
+    stop: AtomicBool,
+}
+
+/// Local SNTP server running in a background thread
+///
+/// By default the server answers every request as an `NTPv4` stratum 1 server with the current
+/// system time. The answers may be adjusted at any time with the setters, e.g. to report a
+/// fixed time or to emulate misbehaving servers. The server is stopped when dropped.
+#[derive(Debug)]
+pub struct MockNtpServer {
+    addr: SocketAddr,
+    shared: Arc<Shared>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl MockNtpServer {
+    /// Bind the server to `addr` and start answering requests in a background thread
+    ///
+    /// Use port `0` to bind to an ephemeral port, the actual address is available via
+    /// [`MockNtpServer::local_addr`].
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the UDP socket can not be bound or configured
+    pub fn bind(addr: SocketAddr) -> io::Result<Self> {
+        let socket = UdpSocket::bind(addr)?;
+        socket.set_read_timeout(Some(POLL_INTERVAL))?;
+        let addr = socket.local_addr()?;
+        let shared = Arc::new(Shared {
+            settings: Mutex::new(Settings {
+                time: None,
+                stratum: 1,
+                version: 4,
+                origin_override: None,
+                delay: Duration::ZERO,
+            }),
+            requests: AtomicUsize::new(0),
+            stop: AtomicBool::new(false),
+        });
+        let handle = std::thread::spawn({
+            let shared = Arc::clone(&shared);
+            move || serve(&socket, &shared)
+        });
+
+        Ok(Self {
+            addr,
+            shared,
+            handle: Some(handle),
+        })
+    }
+
+    /// Returns the address the server is bound to
+    #[must_use]
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Report the given time in seconds since the UNIX epoch and the fraction of a second
+    /// instead of the current system time
+    pub fn set_time(&self, seconds: u32, fraction: u32) {
+        self.update(|settings| settings.time = Some((seconds, fraction)));
+    }
+
+    /// Report the given stratum, `0` makes the server respond with Kiss-o'-Death packets
+    pub fn set_stratum(&self, stratum: u8) {
+        self.update(|settings| settings.stratum = stratum);
+    }
+
+    /// Report the given protocol version in responses
+    pub fn set_version(&self, version: u8) {
+        self.update(|settings| settings.version = version);
+    }
+
+    /// Report the given origin timestamp instead of the request transmit timestamp, e.g. to
+    /// emulate spoofed responses. `None` restores the default behavior.
+    pub fn set_origin_timestamp(&self, origin_timestamp: Option<u64>) {
+        self.update(|settings| settings.origin_override = origin_timestamp);
+    }
+
+    /// Delay every response by `delay`, e.g. to emulate slow networks or responses that
+    /// arrive after the client has timed out
+    pub fn set_delay(&self, delay: Duration) {
+        self.update(|settings| settings.delay = delay);
+    }
+
+    /// Returns the number of requests the server has received
+    #[must_use]
+    pub fn request_count(&self) -> usize {
+        self.shared.requests.load(Ordering::SeqCst)
+    }
+
+    fn update(&self, f: impl FnOnce(&mut Settings)) {
+        let mut settings = self
+            .shared
+            .settings
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+
+        f(&mut settings);
+    }
+}
+
+impl Drop for MockNtpServer {
+    fn drop(&mut self) {
+        self.shared.stop.store(true, Ordering::SeqCst);
+
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn serve(socket: &UdpSocket, shared: &Shared) {
+    let mut request = [0u8; 48];
+
+    while !shared.stop.load(Ordering::SeqCst) {
+        let Ok((size, client)) = socket.recv_from(&mut request) else {
+            continue;
+        };
+
+        if size != request.len() {
+            continue;
+        }
+
+        let settings = *shared
+            .settings
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        let response = response(&request, &settings);
+
+        // count the request before answering, so the client observes the updated counter
+        shared.requests.fetch_add(1, Ordering::SeqCst);
+        std::thread::sleep(settings.delay);
+        let _ = socket.send_to(&response, client);
+    }
+}
+
+fn response(request: &[u8; 48], settings: &Settings) -> [u8; 48] {
+    const SNTP_SERVER_MODE: u8 = 4;
+
+    let timestamp = if let Some((seconds, fraction)) = settings.time {
+        ((u64::from(seconds) + NTP_TIMESTAMP_DELTA) << 32) | u64::from(fraction)
+    } else {
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+        let fraction = (u64::from(now.subsec_nanos()) << 32) / 1_000_000_000;
+
+        ((now.as_secs() + NTP_TIMESTAMP_DELTA) << 32) | fraction
+    };
+    let mut response = [0u8; 48];
+
+    response[0] = ((settings.version & 0x7) << 3) | SNTP_SERVER_MODE;
+    response[1] = settings.stratum;
+    response[12..16].copy_from_slice(if settings.stratum == 0 {
+        b"DENY"
+    } else {
+        b"LOCL"
+    });
+    response[16..24].copy_from_slice(&timestamp.to_be_bytes());
+    match settings.origin_override {
+        Some(origin) => response[24..32].copy_from_slice(&origin.to_be_bytes()),
+        // the origin timestamp is the client transmit timestamp
+        None => response[24..32].copy_from_slice(&request[40..48]),
+    }
+    response[32..40].copy_from_slice(&timestamp.to_be_bytes());
+    response[40..48].copy_from_slice(&timestamp.to_be_bytes());
+
+    response
+}
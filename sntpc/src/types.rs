@@ -1,14 +1,19 @@
-use crate::get_ntp_timestamp;
 #[cfg(any(feature = "log", feature = "defmt"))]
 use crate::log::debug;
 use crate::net::SocketAddr;
+use crate::{
+    fraction_to_milliseconds, ntp_result_to_duration, ntp_timestamp_from_gen,
+};
 
 use cfg_if::cfg_if;
 
+use core::cell::RefCell;
 use core::fmt::Formatter;
 use core::fmt::{Debug, Display};
 use core::future::Future;
 use core::mem;
+use core::ops::{Add, BitOr, Sub};
+use core::time::Duration;
 
 /// SNTP mode value bit mask
 pub(crate) const MODE_MASK: u8 = 0b0000_0111;
@@ -30,6 +35,8 @@ pub(crate) const NSEC_IN_SEC: u32 = 1_000_000_000;
 pub(crate) const USEC_IN_SEC: u32 = 1_000_000;
 /// SNTP milliseconds in second constant
 pub(crate) const MSEC_IN_SEC: u32 = 1_000;
+/// Standard NTP server port
+pub(crate) const NTP_PORT: u16 = 123;
 /// SNTP seconds mask
 pub(crate) const SECONDS_MASK: u64 = 0xffff_ffff_0000_0000;
 /// SNTP seconds fraction mask
@@ -137,15 +144,37 @@ impl From<u64> for NtpTimestamp {
     }
 }
 
-/// Helper enum for specification delay units
-#[derive(Copy, Clone, Debug)]
+/// Units of the roundtrip delay and clock offset reported in [`NtpResult`], see
+/// [`NtpContext::with_units`]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
-pub(crate) enum Units {
-    #[allow(dead_code)]
+pub enum Units {
+    /// Milliseconds
     Milliseconds,
+    /// Microseconds
+    #[default]
     Microseconds,
 }
 
+impl Units {
+    /// Unit symbol used in human-readable output
+    fn symbol(self) -> &'static str {
+        match self {
+            Units::Microseconds => "µs",
+            Units::Milliseconds => "ms",
+        }
+    }
+
+    /// Number of microseconds in one unit
+    fn micros(self) -> u32 {
+        match self {
+            Units::Microseconds => 1,
+            Units::Milliseconds => MSEC_IN_SEC,
+        }
+    }
+}
+
 impl Display for Units {
     fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         let unit = match self {
@@ -160,7 +189,6 @@ impl Display for Units {
 /// The error type for SNTP client
 /// Errors originate on network layer or during processing response from a NTP server
 #[derive(Debug, PartialEq, Copy, Clone)]
-#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[non_exhaustive]
 pub enum Error {
     /// Origin timestamp value in a NTP response differs from the value
@@ -170,7 +198,8 @@ pub enum Error {
     IncorrectMode,
     /// Incorrect Leap Indicator (LI) value in a NTP response
     IncorrectLeapIndicator,
-    /// Incorrect version in a NTP response. Currently, `SNTPv4` is supported
+    /// Incorrect version in a NTP response. The response version must match the request version
+    /// or be one of the versions accepted by the context
     IncorrectResponseVersion,
     /// Incorrect stratum headers in a NTP response
     IncorrectStratumHeaders,
@@ -183,11 +212,494 @@ pub enum Error {
     /// A NTP server address response has been received from does not match
     /// to the address the request was sent to
     ResponseAddressMismatch,
+    /// A request to the NTP server has been sent too recently. Contains the time left
+    /// until the next request is allowed
+    RateLimited(core::time::Duration),
+    /// A NTP server responded with a Kiss-o'-Death packet
+    KissOfDeath(KissCode),
+    /// A NTP server response has not been received in time
+    Timeout,
+    /// A NTP request has not been sent completely. Zero bytes sent usually means the socket
+    /// is disconnected
+    PartialSend,
+    /// A NTP response has not fit into the receive buffer and has been truncated
+    Truncated,
+    /// There is no route to the NTP server
+    NoRoute,
+    /// The socket is not bound to a local port
+    SocketNotBound,
+    /// Transmit timestamp in a NTP response is zero, which means the server clock has never
+    /// been synchronized
+    InvalidTransmitTimestamp,
+    /// Clock offsets reported by a burst of requests vary too much to be trusted
+    InconsistentSamples,
+    /// Root dispersion in a NTP response exceeds the limit set with
+    /// [`NtpContext::with_client_config`]
+    RootDispersionExceeded,
+    /// A NTP response has been processed too long after the request has been sent, so the
+    /// calculated offset can not be trusted, see [`NtpContext::with_max_request_age`]
+    StaleRequest,
+}
+
+impl Error {
+    /// Short description of the error, `None` for the variants with a payload
+    fn description(self) -> Option<&'static str> {
+        let description = match self {
+            Error::IncorrectOriginTimestamp => {
+                "origin timestamp in the response does not match the request"
+            }
+            Error::IncorrectMode => "incorrect mode in the response",
+            Error::IncorrectLeapIndicator => {
+                "incorrect leap indicator in the response"
+            }
+            Error::IncorrectResponseVersion => {
+                "incorrect version in the response"
+            }
+            Error::IncorrectStratumHeaders => {
+                "incorrect stratum headers in the response"
+            }
+            Error::IncorrectPayload => "incorrect response payload size",
+            Error::Network => "network error",
+            Error::AddressResolve => "unable to resolve NTP server address",
+            Error::ResponseAddressMismatch => {
+                "response received from an unexpected address"
+            }
+            Error::Timeout => "response has not been received in time",
+            Error::PartialSend => "request has not been sent completely",
+            Error::Truncated => "response has been truncated",
+            Error::NoRoute => "no route to NTP server",
+            Error::SocketNotBound => "socket is not bound",
+            Error::InvalidTransmitTimestamp => {
+                "zero transmit timestamp in the response"
+            }
+            Error::InconsistentSamples => {
+                "clock offsets of samples vary too much"
+            }
+            Error::RootDispersionExceeded => {
+                "root dispersion in the response exceeds the limit"
+            }
+            Error::StaleRequest => "request is too old to process the response",
+            Error::KissOfDeath(_) | Error::RateLimited(_) => return None,
+        };
+
+        Some(description)
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Error::KissOfDeath(code) => {
+                write!(f, "kiss-o'-death response: {code:?}")
+            }
+            Error::RateLimited(remaining) => write!(
+                f,
+                "request rate limited, retry in {}ms",
+                remaining.as_millis()
+            ),
+            _ => write!(f, "{}", self.description().unwrap_or_default()),
+        }
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for Error {
+    #[allow(clippy::cast_possible_truncation)]
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            Error::KissOfDeath(code) => {
+                defmt::write!(f, "kiss-o'-death response: {}", code);
+            }
+            Error::RateLimited(remaining) => defmt::write!(
+                f,
+                "request rate limited, retry in {=u64}ms",
+                remaining.as_millis() as u64
+            ),
+            _ => defmt::write!(
+                f,
+                "{=str}",
+                self.description().unwrap_or_default()
+            ),
+        }
+    }
+}
+
+/// Serialized representation of [`Error`]
+///
+/// `Error` is serialized as a `{"type": "...", "message": "..."}` map, where `type` is the
+/// variant name and `message` is the human readable description. Variants carrying data
+/// additionally contain `retry_after` or `code` fields. `message` is ignored on
+/// deserialization.
+#[cfg(feature = "serde")]
+mod error_serde {
+    use super::{Error, KissCode};
+    use core::time::Duration;
+    use serde::ser::SerializeStruct;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    enum ErrorType {
+        IncorrectOriginTimestamp,
+        IncorrectMode,
+        IncorrectLeapIndicator,
+        IncorrectResponseVersion,
+        IncorrectStratumHeaders,
+        IncorrectPayload,
+        Network,
+        AddressResolve,
+        ResponseAddressMismatch,
+        RateLimited,
+        KissOfDeath,
+        Timeout,
+        PartialSend,
+        Truncated,
+        NoRoute,
+        SocketNotBound,
+        InvalidTransmitTimestamp,
+        InconsistentSamples,
+        RootDispersionExceeded,
+        StaleRequest,
+    }
+
+    #[derive(Deserialize)]
+    struct ErrorRepr {
+        #[serde(rename = "type")]
+        error_type: ErrorType,
+        #[serde(default)]
+        retry_after: Option<Duration>,
+        #[serde(default)]
+        code: Option<KissCode>,
+    }
+
+    /// Serializes the error description without intermediate allocations
+    struct Message<'a>(&'a Error);
+
+    impl Serialize for Message<'_> {
+        fn serialize<S: Serializer>(
+            &self,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            serializer.collect_str(self.0)
+        }
+    }
+
+    impl Serialize for Error {
+        fn serialize<S: Serializer>(
+            &self,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            let error_type = match self {
+                Error::IncorrectOriginTimestamp => {
+                    ErrorType::IncorrectOriginTimestamp
+                }
+                Error::IncorrectMode => ErrorType::IncorrectMode,
+                Error::IncorrectLeapIndicator => {
+                    ErrorType::IncorrectLeapIndicator
+                }
+                Error::IncorrectResponseVersion => {
+                    ErrorType::IncorrectResponseVersion
+                }
+                Error::IncorrectStratumHeaders => {
+                    ErrorType::IncorrectStratumHeaders
+                }
+                Error::IncorrectPayload => ErrorType::IncorrectPayload,
+                Error::Network => ErrorType::Network,
+                Error::AddressResolve => ErrorType::AddressResolve,
+                Error::ResponseAddressMismatch => {
+                    ErrorType::ResponseAddressMismatch
+                }
+                Error::RateLimited(_) => ErrorType::RateLimited,
+                Error::KissOfDeath(_) => ErrorType::KissOfDeath,
+                Error::Timeout => ErrorType::Timeout,
+                Error::PartialSend => ErrorType::PartialSend,
+                Error::Truncated => ErrorType::Truncated,
+                Error::NoRoute => ErrorType::NoRoute,
+                Error::SocketNotBound => ErrorType::SocketNotBound,
+                Error::InvalidTransmitTimestamp => {
+                    ErrorType::InvalidTransmitTimestamp
+                }
+                Error::InconsistentSamples => ErrorType::InconsistentSamples,
+                Error::RootDispersionExceeded => {
+                    ErrorType::RootDispersionExceeded
+                }
+                Error::StaleRequest => ErrorType::StaleRequest,
+            };
+            let has_payload =
+                matches!(self, Error::RateLimited(_) | Error::KissOfDeath(_));
+            let mut state = serializer
+                .serialize_struct("Error", if has_payload { 3 } else { 2 })?;
+
+            state.serialize_field("type", &error_type)?;
+            state.serialize_field("message", &Message(self))?;
+
+            match self {
+                Error::RateLimited(remaining) => {
+                    state.serialize_field("retry_after", remaining)?;
+                }
+                Error::KissOfDeath(code) => {
+                    state.serialize_field("code", code)?;
+                }
+                _ => {}
+            }
+
+            state.end()
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Error {
+        fn deserialize<D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Self, D::Error> {
+            use serde::de::Error as _;
+
+            let repr = ErrorRepr::deserialize(deserializer)?;
+            let error = match repr.error_type {
+                ErrorType::IncorrectOriginTimestamp => {
+                    Error::IncorrectOriginTimestamp
+                }
+                ErrorType::IncorrectMode => Error::IncorrectMode,
+                ErrorType::IncorrectLeapIndicator => {
+                    Error::IncorrectLeapIndicator
+                }
+                ErrorType::IncorrectResponseVersion => {
+                    Error::IncorrectResponseVersion
+                }
+                ErrorType::IncorrectStratumHeaders => {
+                    Error::IncorrectStratumHeaders
+                }
+                ErrorType::IncorrectPayload => Error::IncorrectPayload,
+                ErrorType::Network => Error::Network,
+                ErrorType::AddressResolve => Error::AddressResolve,
+                ErrorType::ResponseAddressMismatch => {
+                    Error::ResponseAddressMismatch
+                }
+                ErrorType::RateLimited => {
+                    Error::RateLimited(repr.retry_after.ok_or_else(|| {
+                        D::Error::missing_field("retry_after")
+                    })?)
+                }
+                ErrorType::KissOfDeath => Error::KissOfDeath(
+                    repr.code.ok_or_else(|| D::Error::missing_field("code"))?,
+                ),
+                ErrorType::Timeout => Error::Timeout,
+                ErrorType::PartialSend => Error::PartialSend,
+                ErrorType::Truncated => Error::Truncated,
+                ErrorType::NoRoute => Error::NoRoute,
+                ErrorType::SocketNotBound => Error::SocketNotBound,
+                ErrorType::InvalidTransmitTimestamp => {
+                    Error::InvalidTransmitTimestamp
+                }
+                ErrorType::InconsistentSamples => Error::InconsistentSamples,
+                ErrorType::RootDispersionExceeded => {
+                    Error::RootDispersionExceeded
+                }
+                ErrorType::StaleRequest => Error::StaleRequest,
+            };
+
+            Ok(error)
+        }
+    }
+}
+
+/// Leap second warning reported by an NTP server
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum LeapIndicator {
+    /// No leap second pending
+    #[default]
+    NoWarning,
+    /// Last minute of the day has 61 seconds
+    LastMinute61,
+    /// Last minute of the day has 59 seconds
+    LastMinute59,
+    /// Server clock is not synchronized, so the response should not be trusted
+    Unsynchronized,
+}
+
+impl TryFrom<u8> for LeapIndicator {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self> {
+        match value {
+            0 => Ok(LeapIndicator::NoWarning),
+            1 => Ok(LeapIndicator::LastMinute61),
+            2 => Ok(LeapIndicator::LastMinute59),
+            3 => Ok(LeapIndicator::Unsynchronized),
+            _ => Err(Error::IncorrectLeapIndicator),
+        }
+    }
+}
+
+/// Kiss code carried in the reference identifier of a Kiss-o'-Death response
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum KissCode {
+    /// `DENY` - access denied by the server, the client must stop querying it
+    Deny,
+    /// `RSTR` - access restricted by the server, the client must stop querying it
+    Restricted,
+    /// `RATE` - the client exceeded the rate limit and must reduce its polling rate
+    RateExceeded,
+    /// Any other kiss code
+    Other([u8; 4]),
+}
+
+impl From<[u8; 4]> for KissCode {
+    fn from(code: [u8; 4]) -> Self {
+        match &code {
+            b"DENY" => KissCode::Deny,
+            b"RSTR" => KissCode::Restricted,
+            b"RATE" => KissCode::RateExceeded,
+            _ => KissCode::Other(code),
+        }
+    }
+}
+
+/// Reference clock of a stratum 1 server carried in its reference identifier, see RFC 5905,
+/// figure 12
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ReferenceClock {
+    /// `GOES` - Geosynchronous Orbit Environment Satellite
+    Goes,
+    /// `GPS` - Global Position System
+    Gps,
+    /// `GAL` - Galileo Positioning System
+    Gal,
+    /// `PPS` - Generic pulse-per-second
+    Pps,
+    /// `IRIG` - Inter-Range Instrumentation Group
+    Irig,
+    /// `WWVB` - LF Radio WWVB Ft. Collins, CO 60 kHz
+    Wwvb,
+    /// `DCF` - LF Radio DCF77 Mainflingen, DE 77.5 kHz
+    Dcf,
+    /// `HBG` - LF Radio HBG Prangins, HB 75 kHz
+    Hbg,
+    /// `MSF` - LF Radio MSF Anthorn, UK 60 kHz
+    Msf,
+    /// `JJY` - LF Radio JJY Fukushima, JP 40 kHz, Saga, JP 60 kHz
+    Jjy,
+    /// `LORC` - MF Radio LORAN C station, 100 kHz
+    Lorc,
+    /// `TDF` - MF Radio Allouis, FR 162 kHz
+    Tdf,
+    /// `CHU` - HF Radio CHU Ottawa, Ontario
+    Chu,
+    /// `WWV` - HF Radio WWV Ft. Collins, CO
+    Wwv,
+    /// `WWVH` - HF Radio WWVH Kauai, HI
+    Wwvh,
+    /// `NIST` - NIST telephone modem
+    Nist,
+    /// `ACTS` - NIST telephone modem
+    Acts,
+    /// `USNO` - USNO telephone modem
+    Usno,
+    /// `PTB` - European telephone modem
+    Ptb,
+    /// Any other reference clock code
+    Other([u8; 4]),
+}
+
+impl From<[u8; 4]> for ReferenceClock {
+    fn from(code: [u8; 4]) -> Self {
+        match &code {
+            b"GOES" => ReferenceClock::Goes,
+            b"GPS\0" => ReferenceClock::Gps,
+            b"GAL\0" => ReferenceClock::Gal,
+            b"PPS\0" => ReferenceClock::Pps,
+            b"IRIG" => ReferenceClock::Irig,
+            b"WWVB" => ReferenceClock::Wwvb,
+            b"DCF\0" => ReferenceClock::Dcf,
+            b"HBG\0" => ReferenceClock::Hbg,
+            b"MSF\0" => ReferenceClock::Msf,
+            b"JJY\0" => ReferenceClock::Jjy,
+            b"LORC" => ReferenceClock::Lorc,
+            b"TDF\0" => ReferenceClock::Tdf,
+            b"CHU\0" => ReferenceClock::Chu,
+            b"WWV\0" => ReferenceClock::Wwv,
+            b"WWVH" => ReferenceClock::Wwvh,
+            b"NIST" => ReferenceClock::Nist,
+            b"ACTS" => ReferenceClock::Acts,
+            b"USNO" => ReferenceClock::Usno,
+            b"PTB\0" => ReferenceClock::Ptb,
+            _ => ReferenceClock::Other(code),
+        }
+    }
+}
+
+/// Reference identifier of an NTP server
+///
+/// Meaning of the identifier depends on the stratum of the server, see [`crate::parse_ref_id`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum RefId {
+    /// Four-character ASCII string, left justified and zero padded. Used by stratum 1
+    /// servers to identify the reference clock (e.g. `GPS`, `PPS`, `DCF`) and by
+    /// Kiss-o'-Death packets to carry the kiss code
+    Ascii([u8; 4]),
+    /// IPv4 address of the upstream server for stratum 2-15 servers
+    Ipv4(core::net::Ipv4Addr),
+    /// First four bytes of the MD5 digest of the upstream server IPv6 address for
+    /// stratum 2-15 servers that are reached over IPv6
+    Ipv6Hash([u8; 4]),
+    /// `127.127.t.u` pseudo address of a local reference clock driver of type `t` and unit
+    /// `u`. Servers that report it are usually synchronized to an undisciplined local clock
+    LocalClock(core::net::Ipv4Addr),
+}
+
+impl RefId {
+    /// Returns raw reference identifier bytes in network order
+    #[must_use]
+    pub fn octets(&self) -> [u8; 4] {
+        match self {
+            RefId::Ascii(bytes) | RefId::Ipv6Hash(bytes) => *bytes,
+            RefId::Ipv4(addr) | RefId::LocalClock(addr) => addr.octets(),
+        }
+    }
+
+    /// Returns reference identifier as a string for [`RefId::Ascii`] identifiers with
+    /// trailing zero padding removed
+    #[must_use]
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            RefId::Ascii(bytes) => {
+                let len =
+                    bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+
+                core::str::from_utf8(&bytes[..len]).ok()
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the reference clock for [`RefId::Ascii`] identifiers
+    ///
+    /// The identifier names a reference clock for stratum 1 servers only, the ones of
+    /// Kiss-o'-Death packets are kiss codes and decoded as [`ReferenceClock::Other`].
+    #[must_use]
+    pub fn reference_clock(&self) -> Option<ReferenceClock> {
+        match self {
+            RefId::Ascii(bytes) => Some(ReferenceClock::from(*bytes)),
+            _ => None,
+        }
+    }
+}
+
+impl Default for RefId {
+    fn default() -> Self {
+        RefId::Ascii([0u8; 4])
+    }
 }
 
 /// SNTP request result representation
 #[derive(Debug, Copy, Clone)]
-#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NtpResult {
     /// NTP server seconds value
     pub seconds: u32,
@@ -201,6 +713,128 @@ pub struct NtpResult {
     pub stratum: u8,
     /// Precision of NTP server as log2(seconds) - this should usually be negative
     pub precision: i8,
+    /// Total roundtrip delay to the reference clock of NTP server in microseconds
+    pub root_delay: u64,
+    /// Maximum error relative to the reference clock of NTP server in microseconds
+    pub root_dispersion: u64,
+    /// Poll interval suggested by NTP server as log2(seconds)
+    pub poll: i8,
+    /// Reference identifier of NTP server
+    pub ref_id: RefId,
+    /// Leap second warning reported by NTP server
+    pub leap_indicator: LeapIndicator,
+    /// Units of the values returned by [`NtpResult::roundtrip`] and [`NtpResult::offset`]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub units: Units,
+    /// Address the response has actually been received from, which may differ from the
+    /// queried address for anycast servers or behind NAT
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub source: Option<SocketAddr>,
+    /// Local time the request has been sent at (T1) in the NTP timestamp format, zero if
+    /// unknown
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub originate_timestamp: u64,
+    /// Local time the response has been received at (T4) in the NTP timestamp format, zero
+    /// if unknown
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub destination_timestamp: u64,
+}
+
+/// Validated server response along with the result computed from it, see [`crate::get_time_raw`]
+///
+/// The fields are copied from the response header as is, so custom filtering algorithms have
+/// access to the values [`NtpResult`] converts or drops. Timestamps are in the NTP timestamp
+/// format: seconds since the NTP epoch in the upper 32 bits and the fraction of a second in the
+/// lower 32 bits.
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NtpResponse {
+    /// Result computed from the response
+    pub result: NtpResult,
+    /// Leap second warning reported by NTP server
+    pub leap_indicator: LeapIndicator,
+    /// Protocol version of the response
+    pub version: u8,
+    /// Association mode of the response, 4 for server and 5 for broadcast responses
+    pub mode: u8,
+    /// Clock stratum of NTP server
+    pub stratum: u8,
+    /// Poll interval suggested by NTP server as log2(seconds)
+    pub poll: i8,
+    /// Precision of NTP server as log2(seconds)
+    pub precision: i8,
+    /// Total roundtrip delay to the reference clock in the NTP short format
+    pub root_delay: u32,
+    /// Maximum error relative to the reference clock in the NTP short format
+    pub root_dispersion: u32,
+    /// Raw reference identifier, see [`NtpResult::ref_id`] for the parsed one
+    pub ref_id: u32,
+    /// Time the server clock has been last set or corrected at
+    pub ref_timestamp: u64,
+    /// Time the request has been sent at by the client (T1)
+    pub origin_timestamp: u64,
+    /// Time the request has been received at by the server (T2)
+    pub recv_timestamp: u64,
+    /// Time the response has been sent at by the server (T3)
+    pub tx_timestamp: u64,
+    /// Time the response has been received at by the client (T4)
+    pub destination_timestamp: u64,
+}
+
+impl NtpResponse {
+    pub(crate) fn new(packet: &NtpPacket, result: NtpResult) -> Self {
+        NtpResponse {
+            leap_indicator: result.leap_indicator,
+            version: (packet.li_vn_mode & VERSION_MASK) >> VERSION_SHIFT,
+            mode: (packet.li_vn_mode & MODE_MASK) >> MODE_SHIFT,
+            stratum: packet.stratum,
+            poll: packet.poll,
+            precision: packet.precision,
+            root_delay: packet.root_delay,
+            root_dispersion: packet.root_dispersion,
+            ref_id: packet.ref_id,
+            ref_timestamp: packet.ref_timestamp,
+            origin_timestamp: packet.origin_timestamp,
+            recv_timestamp: packet.recv_timestamp,
+            tx_timestamp: packet.tx_timestamp,
+            destination_timestamp: result.destination_timestamp,
+            result,
+        }
+    }
+}
+
+/// Summary of [`NtpResult`] values for telemetry, see [`NtpResult::metrics`]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct NtpMetrics {
+    /// Estimated difference between the NTP reference and the system time in microseconds
+    pub offset_us: i64,
+    /// Request roundtrip time in microseconds
+    pub roundtrip_us: u64,
+    /// Clock stratum of NTP server
+    pub stratum: u8,
+    /// Precision of NTP server as log2(seconds)
+    pub precision: i8,
+    /// One-way delay to NTP server in microseconds, estimated as half of the roundtrip time
+    pub one_way_us: u64,
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for NtpResult {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "NtpResult {{ sec: {=u32}, frac: {=u32}, offset: {=i64}{=str}, roundtrip: {=u64}{=str}, stratum: {=u8} }}",
+            self.seconds,
+            self.seconds_fraction,
+            self.offset(),
+            self.units.symbol(),
+            self.roundtrip(),
+            self.units.symbol(),
+            self.stratum
+        );
+    }
 }
 
 impl NtpResult {
@@ -231,6 +865,15 @@ impl NtpResult {
             offset,
             stratum,
             precision,
+            root_delay: 0,
+            root_dispersion: 0,
+            poll: 0,
+            ref_id: RefId::default(),
+            leap_indicator: LeapIndicator::default(),
+            units: Units::Microseconds,
+            source: None,
+            originate_timestamp: 0,
+            destination_timestamp: 0,
         }
     }
     /// Returns number of seconds reported by an NTP server
@@ -245,16 +888,91 @@ impl NtpResult {
         self.seconds_fraction
     }
 
-    /// Returns request's roundtrip time (client -> server -> client) in microseconds
+    /// Returns request's roundtrip time (client -> server -> client) in [`NtpResult::units`]
     #[must_use]
     pub fn roundtrip(&self) -> u64 {
-        self.roundtrip
+        self.roundtrip / u64::from(self.units.micros())
     }
 
-    /// Returns system clock offset value in microseconds
+    /// Returns system clock offset value in [`NtpResult::units`]
     #[must_use]
     pub fn offset(&self) -> i64 {
-        self.offset
+        self.offset / i64::from(self.units.micros())
+    }
+
+    /// Returns units of the roundtrip time and offset values, microseconds unless configured
+    /// otherwise with [`NtpContext::with_units`]
+    #[must_use]
+    pub fn units(&self) -> Units {
+        self.units
+    }
+
+    /// Returns absolute system clock offset value as [`core::time::Duration`] along with
+    /// its sign: `true` if the offset is positive or zero and `false` if it is negative
+    #[must_use]
+    pub fn offset_duration(&self) -> (core::time::Duration, bool) {
+        (
+            core::time::Duration::from_micros(self.offset.unsigned_abs()),
+            self.offset >= 0,
+        )
+    }
+
+    /// Returns request's roundtrip time as [`core::time::Duration`]
+    #[must_use]
+    pub fn roundtrip_duration(&self) -> core::time::Duration {
+        core::time::Duration::from_micros(self.roundtrip)
+    }
+
+    /// Returns system clock offset value in seconds as a floating point number
+    #[cfg(feature = "std")]
+    #[must_use]
+    pub fn offset_secs_f64(&self) -> f64 {
+        let (offset, positive) = self.offset_duration();
+
+        if positive {
+            offset.as_secs_f64()
+        } else {
+            -offset.as_secs_f64()
+        }
+    }
+
+    /// Returns request's roundtrip time in seconds as a floating point number
+    #[cfg(feature = "std")]
+    #[must_use]
+    pub fn roundtrip_secs_f64(&self) -> f64 {
+        self.roundtrip_duration().as_secs_f64()
+    }
+
+    /// Returns the local time in UNIX seconds the exchange is centered at, i.e. the midpoint of
+    /// the request transmit (T1) and the response receive (T4) timestamps
+    ///
+    /// The offset is estimated for that instant rather than for the moment the response
+    /// has been received, so the local reference instant plus the offset is a more accurate
+    /// estimation of the server time than T4 plus the offset when the roundtrip is long.
+    ///
+    /// Returns zero if the timestamps are unknown, e.g. for results created with
+    /// [`NtpResult::new`].
+    #[must_use]
+    pub fn local_reference_instant_unix(&self) -> u64 {
+        let t1 = self.originate_timestamp;
+        let t4 = self.destination_timestamp;
+        let midpoint = t1.wrapping_add(t4.wrapping_sub(t1) / 2);
+
+        (midpoint >> 32)
+            .saturating_sub(u64::from(NtpPacket::NTP_TIMESTAMP_DELTA))
+    }
+
+    /// Returns the offset, roundtrip and server clock quality values at once, e.g. to report
+    /// them to telemetry. Time values are in microseconds regardless of [`NtpResult::units`]
+    #[must_use]
+    pub fn metrics(&self) -> NtpMetrics {
+        NtpMetrics {
+            offset_us: self.offset,
+            roundtrip_us: self.roundtrip,
+            stratum: self.stratum,
+            precision: self.precision,
+            one_way_us: self.roundtrip / 2,
+        }
     }
 
     /// Returns reported stratum value (level of server's hierarchy to stratum 0 - "reference clock")
@@ -268,23 +986,250 @@ impl NtpResult {
     pub fn precision(&self) -> i8 {
         self.precision
     }
+
+    /// Returns reported precision of server's system clock as [`core::time::Duration`],
+    /// e.g. about 1 microsecond for a typical precision of `-20`.
+    ///
+    /// Precisions finer than a nanosecond are reported as [`Duration::ZERO`] and the ones
+    /// that exceed the [`Duration`] range are saturated to `u64::MAX` seconds
+    #[must_use]
+    pub fn precision_duration(&self) -> core::time::Duration {
+        let shift = u32::from(self.precision.unsigned_abs());
+
+        if self.precision >= 0 {
+            Duration::from_secs(1u64.checked_shl(shift).unwrap_or(u64::MAX))
+        } else {
+            Duration::from_nanos(
+                u64::from(NSEC_IN_SEC).checked_shr(shift).unwrap_or(0),
+            )
+        }
+    }
+
+    /// Returns the reported time advanced by half of the roundtrip as a pair of seconds and
+    /// seconds fraction, i.e. the best estimation of the server time at the moment the response
+    /// has been received
+    ///
+    /// The compensation is meant for the server transmit timestamp reported with the default
+    /// [`TimeSource::ServerTx`]. The time reported with [`TimeSource::LocalPlusOffset`] is
+    /// already compensated, so it should be used as is.
+    #[must_use]
+    pub fn corrected_time(&self) -> (u32, u32) {
+        let corrected = *self + self.roundtrip_duration() / 2;
+
+        (corrected.seconds, corrected.seconds_fraction)
+    }
+
+    /// Returns reported root delay (total roundtrip delay to the reference clock) in microseconds
+    #[must_use]
+    pub fn root_delay(&self) -> u64 {
+        self.root_delay
+    }
+
+    /// Returns reported root dispersion (maximum error relative to the reference clock)
+    /// in microseconds
+    #[must_use]
+    pub fn root_dispersion(&self) -> u64 {
+        self.root_dispersion
+    }
+
+    /// Returns reported poll value (an exponent of two, which results in the minimum
+    /// polling interval suggested by the server in seconds)
+    #[must_use]
+    pub fn poll(&self) -> i8 {
+        self.poll
+    }
+
+    /// Returns reported reference identifier of the server
+    #[must_use]
+    pub fn ref_id(&self) -> RefId {
+        self.ref_id
+    }
+
+    /// Returns reported leap second warning. [`LeapIndicator::Unsynchronized`] means the
+    /// server clock is not synchronized and the result should not be used for clock adjustment
+    #[must_use]
+    pub fn leap_indicator(&self) -> LeapIndicator {
+        self.leap_indicator
+    }
+
+    /// Returns the address the response has been received from. `None` for results that have
+    /// not been received by the library, e.g. created with [`NtpResult::new`]
+    #[must_use]
+    pub fn source(&self) -> Option<SocketAddr> {
+        self.source
+    }
+
+    /// Returns the minimum polling interval suggested by the server in seconds.
+    ///
+    /// Intervals shorter than a second (negative poll exponent) are reported as `0`
+    #[must_use]
+    pub fn poll_interval(&self) -> u64 {
+        u32::try_from(self.poll)
+            .map_or(0, |shift| 1u64.checked_shl(shift).unwrap_or(u64::MAX))
+    }
+
+    /// Returns `true` if the reported time falls within a reasonable window between
+    /// 2020-01-01 and 2100-01-01
+    ///
+    /// Devices that boot with an unset clock may use the check to find out whether the time
+    /// has been set properly, as well as to guard against garbage in malformed responses.
+    #[must_use]
+    pub fn is_plausible(&self) -> bool {
+        /// 2020-01-01T00:00:00Z in seconds since the UNIX epoch
+        const MIN_PLAUSIBLE_SEC: u32 = 1_577_836_800;
+        /// 2100-01-01T00:00:00Z in seconds since the UNIX epoch
+        const MAX_PLAUSIBLE_SEC: u32 = 4_102_444_800;
+
+        (MIN_PLAUSIBLE_SEC..MAX_PLAUSIBLE_SEC).contains(&self.seconds)
+    }
+
+    /// Returns the reported time as [`chrono::DateTime<Utc>`](chrono::DateTime)
+    ///
+    /// # Panics
+    ///
+    /// Never panics in practice: any `u32` number of seconds since the UNIX epoch along with
+    /// a sub-second fraction is a valid UTC date and time.
+    #[cfg(feature = "chrono")]
+    #[must_use]
+    pub fn to_datetime_utc(&self) -> chrono::DateTime<chrono::Utc> {
+        use chrono::TimeZone;
+
+        chrono::Utc
+            .timestamp_opt(
+                i64::from(self.seconds),
+                crate::fraction_to_nanoseconds(self.seconds_fraction),
+            )
+            .single()
+            .expect("NTP result time is out of the chrono::DateTime range")
+    }
+
+    /// Returns the reported time as [`time::OffsetDateTime`] in UTC
+    ///
+    /// # Errors
+    ///
+    /// Returns [`time::error::ComponentRange`] if the time is out of the range supported by
+    /// [`time::OffsetDateTime`].
+    #[cfg(feature = "time")]
+    pub fn to_offset_date_time(
+        &self,
+    ) -> core::result::Result<time::OffsetDateTime, time::error::ComponentRange>
+    {
+        let unix_nanos = i128::from(self.seconds) * i128::from(NSEC_IN_SEC)
+            + i128::from(crate::fraction_to_nanoseconds(self.seconds_fraction));
+
+        time::OffsetDateTime::from_unix_timestamp_nanos(unix_nanos)
+    }
+}
+
+#[cfg(feature = "time")]
+impl TryFrom<NtpResult> for time::OffsetDateTime {
+    type Error = time::error::ComponentRange;
+
+    fn try_from(result: NtpResult) -> core::result::Result<Self, Self::Error> {
+        result.to_offset_date_time()
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<NtpResult> for chrono::DateTime<chrono::Utc> {
+    fn from(result: NtpResult) -> Self {
+        result.to_datetime_utc()
+    }
+}
+
+impl Display for NtpResult {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "NTP time: {}.{:03}s (roundtrip: {}{}, offset: {}{}, stratum: {})",
+            self.seconds,
+            fraction_to_milliseconds(self.seconds_fraction),
+            self.roundtrip(),
+            self.units.symbol(),
+            self.offset(),
+            self.units.symbol(),
+            self.stratum
+        )
+    }
+}
+
+/// Convert nanoseconds of a second to second fraction value
+#[allow(clippy::cast_possible_truncation)]
+fn nanoseconds_to_fraction(nanos: u32) -> u32 {
+    (u64::from(nanos) * u64::from(u32::MAX) / u64::from(NSEC_IN_SEC)) as u32
+}
+
+/// Shifts the reported time forward by the duration, e.g. to apply a clock offset.
+/// Seconds wrap around on overflow the same way NTP timestamps do.
+impl Add<Duration> for NtpResult {
+    type Output = NtpResult;
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn add(mut self, rhs: Duration) -> Self::Output {
+        let fraction = u64::from(self.seconds_fraction)
+            + u64::from(nanoseconds_to_fraction(rhs.subsec_nanos()));
+        let carry = u32::from(fraction >= u64::from(u32::MAX));
+
+        self.seconds_fraction = (fraction % u64::from(u32::MAX)) as u32;
+        self.seconds = self
+            .seconds
+            .wrapping_add(rhs.as_secs() as u32)
+            .wrapping_add(carry);
+        self
+    }
+}
+
+/// Shifts the reported time backward by the duration.
+/// Seconds wrap around on underflow the same way NTP timestamps do.
+impl Sub<Duration> for NtpResult {
+    type Output = NtpResult;
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn sub(mut self, rhs: Duration) -> Self::Output {
+        let fraction = nanoseconds_to_fraction(rhs.subsec_nanos());
+        let borrow = u32::from(self.seconds_fraction < fraction);
+
+        self.seconds_fraction = if borrow == 0 {
+            self.seconds_fraction - fraction
+        } else {
+            u32::MAX - (fraction - self.seconds_fraction)
+        };
+        self.seconds = self
+            .seconds
+            .wrapping_sub(rhs.as_secs() as u32)
+            .wrapping_sub(borrow);
+        self
+    }
+}
+
+/// Returns the absolute difference between the reported times of two results
+impl Sub<NtpResult> for NtpResult {
+    type Output = Duration;
+
+    fn sub(self, rhs: NtpResult) -> Self::Output {
+        ntp_result_to_duration(&self).abs_diff(ntp_result_to_duration(&rhs))
+    }
 }
 
 impl NtpPacket {
     // First day UNIX era offset https://www.rfc-editor.org/rfc/rfc5905
     pub(crate) const NTP_TIMESTAMP_DELTA: u32 = 2_208_988_800u32;
-    const SNTP_CLIENT_MODE: u8 = 3;
-    const SNTP_VERSION: u8 = 4 << 3;
+    pub(crate) const SNTP_CLIENT_MODE: u8 = 3;
+    pub(crate) const SNTP_VERSION: u8 = 4;
 
-    pub fn new<T: NtpTimestampGenerator>(mut timestamp_gen: T) -> Self {
+    pub fn new<T: NtpTimestampGenerator>(
+        mut timestamp_gen: T,
+        version: u8,
+    ) -> Self {
         timestamp_gen.init();
-        let tx_timestamp = get_ntp_timestamp(&timestamp_gen);
+        let tx_timestamp = ntp_timestamp_from_gen(&timestamp_gen);
 
         #[cfg(any(feature = "log", feature = "defmt"))]
         debug!("NtpPacket::new(tx_timestamp: {})", tx_timestamp);
 
         NtpPacket {
-            li_vn_mode: NtpPacket::SNTP_CLIENT_MODE | NtpPacket::SNTP_VERSION,
+            li_vn_mode: NtpPacket::SNTP_CLIENT_MODE
+                | ((version << VERSION_SHIFT) & VERSION_MASK),
             stratum: 0,
             poll: 0,
             precision: 0,
@@ -332,10 +1277,75 @@ pub trait NtpTimestampGenerator {
     fn timestamp_subsec_micros(&self) -> u32;
 }
 
-#[cfg(feature = "std")]
-/// Supplementary module to implement some `sntpc` boilerplate that environments with
-/// `std` enable have to re-implement.
-mod sup {
+/// Timestamp generator that reads the time from a user-supplied source, e.g. an RTC peripheral
+///
+/// The source is a closure returning the time since UNIX EPOCH as a pair of whole seconds and
+/// the microseconds within the current second. It is called once per
+/// [`NtpTimestampGenerator::init`] call, so both parts of a timestamp come from the same
+/// reading. The generator is `Copy` as long as the closure is, so it can be used with
+/// [`crate::get_time`] directly:
+///
+/// ```rust
+/// use sntpc::{NtpContext, NtpTimestampGenerator, RtcTimestampGen};
+///
+/// # struct Rtc;
+/// # impl Rtc {
+/// #     fn unix_time(&self) -> (u64, u32) {
+/// #         (1_700_000_000, 250_000)
+/// #     }
+/// # }
+/// let rtc = Rtc;
+/// let mut timestamp_gen = RtcTimestampGen::new(|| rtc.unix_time());
+///
+/// timestamp_gen.init();
+/// assert_eq!(1_700_000_000, timestamp_gen.timestamp_sec());
+/// assert_eq!(250_000, timestamp_gen.timestamp_subsec_micros());
+///
+/// let context = NtpContext::new(timestamp_gen);
+/// ```
+#[derive(Copy, Clone)]
+pub struct RtcTimestampGen<F> {
+    read: F,
+    seconds: u64,
+    micros: u32,
+}
+
+impl<F> RtcTimestampGen<F>
+where
+    F: Fn() -> (u64, u32),
+{
+    /// Create a generator reading the time since UNIX EPOCH from `read`
+    #[must_use]
+    pub const fn new(read: F) -> Self {
+        Self {
+            read,
+            seconds: 0,
+            micros: 0,
+        }
+    }
+}
+
+impl<F> NtpTimestampGenerator for RtcTimestampGen<F>
+where
+    F: Fn() -> (u64, u32),
+{
+    fn init(&mut self) {
+        (self.seconds, self.micros) = (self.read)();
+    }
+
+    fn timestamp_sec(&self) -> u64 {
+        self.seconds
+    }
+
+    fn timestamp_subsec_micros(&self) -> u32 {
+        self.micros
+    }
+}
+
+#[cfg(feature = "std")]
+/// Supplementary module to implement some `sntpc` boilerplate that environments with
+/// `std` enable have to re-implement.
+mod sup {
     use std::time::{Duration, SystemTime};
 
     use crate::NtpTimestampGenerator;
@@ -396,36 +1406,917 @@ pub trait NtpUdpSocket {
         &self,
         buf: &mut [u8],
     ) -> impl Future<Output = Result<(usize, SocketAddr)>>;
+
+    /// Receives a single datagram message on the socket waiting no longer than `timeout`.
+    ///
+    /// Used instead of [`NtpUdpSocket::recv_from`] if the context has a receive timeout set,
+    /// see [`NtpContext::with_recv_timeout`]. The default implementation ignores the timeout
+    /// and relies on the timeout configured for the socket itself, if any. Implementations for
+    /// async runtimes override it to bound the receive with the runtime timers.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err(Error::Timeout)` if no datagram has been received in time or `Err` if
+    /// an underlying UDP receive fails
+    fn recv_from_timeout(
+        &self,
+        buf: &mut [u8],
+        timeout: Duration,
+    ) -> impl Future<Output = Result<(usize, SocketAddr)>> {
+        let _ = timeout;
+
+        self.recv_from(buf)
+    }
+}
+
+impl<T: NtpUdpSocket + ?Sized> NtpUdpSocket for &T {
+    fn send_to(
+        &self,
+        buf: &[u8],
+        addr: SocketAddr,
+    ) -> impl Future<Output = Result<usize>> {
+        (**self).send_to(buf, addr)
+    }
+
+    fn recv_from(
+        &self,
+        buf: &mut [u8],
+    ) -> impl Future<Output = Result<(usize, SocketAddr)>> {
+        (**self).recv_from(buf)
+    }
+
+    fn recv_from_timeout(
+        &self,
+        buf: &mut [u8],
+        timeout: Duration,
+    ) -> impl Future<Output = Result<(usize, SocketAddr)>> {
+        (**self).recv_from_timeout(buf, timeout)
+    }
+}
+
+/// Boxed future returned by [`DynNtpUdpSocket`] methods
+#[cfg(feature = "std")]
+pub type BoxFuture<'a, T> = core::pin::Pin<Box<dyn Future<Output = T> + 'a>>;
+
+/// Object-safe variant of [`NtpUdpSocket`]
+///
+/// [`NtpUdpSocket`] methods return `impl Future`, so the trait can not be used as a trait
+/// object. The trait boxes the returned futures instead, which allows to choose the transport
+/// at runtime. It is implemented for all [`NtpUdpSocket`] implementations, and
+/// `dyn DynNtpUdpSocket` implements [`NtpUdpSocket`], so it can be passed to [`crate::get_time`]
+/// and other functions:
+///
+/// ```rust,no_run
+/// # #[cfg(feature = "std-socket")]
+/// # fn main() {
+/// use sntpc::{get_time, DynNtpUdpSocket, NtpContext, StdTimestampGen};
+/// use std::net::UdpSocket;
+/// # use miniloop::executor::Executor;
+///
+/// let socket: Box<dyn DynNtpUdpSocket> = Box::new(UdpSocket::bind("0.0.0.0:0").unwrap());
+/// let context = NtpContext::new(StdTimestampGen::default());
+/// let addr = "216.239.35.0:123".parse().unwrap();
+/// # let mut executor = Executor::new();
+///
+/// let result = executor.block_on(get_time(addr, socket.as_ref(), context));
+/// # }
+/// # #[cfg(not(feature = "std-socket"))]
+/// # fn main() {}
+/// ```
+#[cfg(feature = "std")]
+pub trait DynNtpUdpSocket {
+    /// Send the given buffer to an address provided, see [`NtpUdpSocket::send_to`]
+    fn send_to<'a>(
+        &'a self,
+        buf: &'a [u8],
+        addr: SocketAddr,
+    ) -> BoxFuture<'a, Result<usize>>;
+
+    /// Receives a single datagram message on the socket, see [`NtpUdpSocket::recv_from`]
+    fn recv_from<'a>(
+        &'a self,
+        buf: &'a mut [u8],
+    ) -> BoxFuture<'a, Result<(usize, SocketAddr)>>;
+
+    /// Receives a single datagram message on the socket waiting no longer than `timeout`, see
+    /// [`NtpUdpSocket::recv_from_timeout`]
+    fn recv_from_timeout<'a>(
+        &'a self,
+        buf: &'a mut [u8],
+        timeout: Duration,
+    ) -> BoxFuture<'a, Result<(usize, SocketAddr)>>;
+}
+
+#[cfg(feature = "std")]
+impl<T: NtpUdpSocket> DynNtpUdpSocket for T {
+    fn send_to<'a>(
+        &'a self,
+        buf: &'a [u8],
+        addr: SocketAddr,
+    ) -> BoxFuture<'a, Result<usize>> {
+        Box::pin(NtpUdpSocket::send_to(self, buf, addr))
+    }
+
+    fn recv_from<'a>(
+        &'a self,
+        buf: &'a mut [u8],
+    ) -> BoxFuture<'a, Result<(usize, SocketAddr)>> {
+        Box::pin(NtpUdpSocket::recv_from(self, buf))
+    }
+
+    fn recv_from_timeout<'a>(
+        &'a self,
+        buf: &'a mut [u8],
+        timeout: Duration,
+    ) -> BoxFuture<'a, Result<(usize, SocketAddr)>> {
+        Box::pin(NtpUdpSocket::recv_from_timeout(self, buf, timeout))
+    }
+}
+
+#[cfg(feature = "std")]
+impl NtpUdpSocket for dyn DynNtpUdpSocket + '_ {
+    async fn send_to(&self, buf: &[u8], addr: SocketAddr) -> Result<usize> {
+        DynNtpUdpSocket::send_to(self, buf, addr).await
+    }
+
+    async fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddr)> {
+        DynNtpUdpSocket::recv_from(self, buf).await
+    }
+
+    async fn recv_from_timeout(
+        &self,
+        buf: &mut [u8],
+        timeout: Duration,
+    ) -> Result<(usize, SocketAddr)> {
+        DynNtpUdpSocket::recv_from_timeout(self, buf, timeout).await
+    }
+}
+
+/// Sending half of the UDP socket interface
+///
+/// [`crate::sntp_send_request`] only needs to send data, so it accepts any object implementing
+/// the trait. The trait is implemented for all [`NtpUdpSocket`] implementations, so it only has
+/// to be implemented manually for send-only objects, e.g. halves of a split socket.
+pub trait NtpSendSocket {
+    /// Send the given buffer to an address provided, see [`NtpUdpSocket::send_to`]
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an underlying UDP send fails
+    fn send_to(
+        &self,
+        buf: &[u8],
+        addr: SocketAddr,
+    ) -> impl Future<Output = Result<usize>>;
+}
+
+/// Receiving half of the UDP socket interface
+///
+/// [`crate::sntp_process_response`] and the other functions that only receive data accept any
+/// object implementing the trait. The trait is implemented for all [`NtpUdpSocket`]
+/// implementations, so it only has to be implemented manually for receive-only objects.
+pub trait NtpRecvSocket {
+    /// Receives a single datagram message on the socket, see [`NtpUdpSocket::recv_from`]
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an underlying UDP receive fails
+    fn recv_from(
+        &self,
+        buf: &mut [u8],
+    ) -> impl Future<Output = Result<(usize, SocketAddr)>>;
+
+    /// Receives a single datagram message on the socket waiting no longer than `timeout`, see
+    /// [`NtpUdpSocket::recv_from_timeout`]
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err(Error::Timeout)` if no datagram has been received in time or `Err` if
+    /// an underlying UDP receive fails
+    fn recv_from_timeout(
+        &self,
+        buf: &mut [u8],
+        timeout: Duration,
+    ) -> impl Future<Output = Result<(usize, SocketAddr)>> {
+        let _ = timeout;
+
+        self.recv_from(buf)
+    }
+}
+
+impl<T: NtpUdpSocket + ?Sized> NtpSendSocket for T {
+    fn send_to(
+        &self,
+        buf: &[u8],
+        addr: SocketAddr,
+    ) -> impl Future<Output = Result<usize>> {
+        NtpUdpSocket::send_to(self, buf, addr)
+    }
 }
+
+impl<T: NtpUdpSocket + ?Sized> NtpRecvSocket for T {
+    fn recv_from(
+        &self,
+        buf: &mut [u8],
+    ) -> impl Future<Output = Result<(usize, SocketAddr)>> {
+        NtpUdpSocket::recv_from(self, buf)
+    }
+
+    fn recv_from_timeout(
+        &self,
+        buf: &mut [u8],
+        timeout: Duration,
+    ) -> impl Future<Output = Result<(usize, SocketAddr)>> {
+        NtpUdpSocket::recv_from_timeout(self, buf, timeout)
+    }
+}
+
+/// Allows sharing a single socket between several tasks or threads, e.g. with the
+/// `tokio-socket` feature enabled:
+///
+/// ```rust,no_run
+/// # #[cfg(feature = "tokio-socket")]
+/// # async fn share() {
+/// use sntpc::{get_time, NtpContext, StdTimestampGen};
+/// use std::sync::Arc;
+/// use tokio::net::UdpSocket;
+///
+/// let socket = Arc::new(UdpSocket::bind("0.0.0.0:0").await.unwrap());
+/// let context = NtpContext::new(StdTimestampGen::default());
+/// let addr = "216.239.35.0:123".parse().unwrap();
+///
+/// // the clone may be moved into another task
+/// let shared = Arc::clone(&socket);
+/// let result = get_time(addr, &shared, context).await;
+/// # }
+/// ```
+#[cfg(feature = "std")]
+impl<T: NtpUdpSocket + ?Sized> NtpUdpSocket for std::sync::Arc<T> {
+    fn send_to(
+        &self,
+        buf: &[u8],
+        addr: SocketAddr,
+    ) -> impl Future<Output = Result<usize>> {
+        (**self).send_to(buf, addr)
+    }
+
+    fn recv_from(
+        &self,
+        buf: &mut [u8],
+    ) -> impl Future<Output = Result<(usize, SocketAddr)>> {
+        (**self).recv_from(buf)
+    }
+
+    fn recv_from_timeout(
+        &self,
+        buf: &mut [u8],
+        timeout: Duration,
+    ) -> impl Future<Output = Result<(usize, SocketAddr)>> {
+        (**self).recv_from_timeout(buf, timeout)
+    }
+}
+
+/// A trait encapsulating UDP socket interface for sockets that require mutable access
+///
+/// Some network stacks, e.g. `smoltcp`, need `&mut` access to a socket to send and receive
+/// data. Such sockets may implement the trait and be passed to [`crate::get_time_mut`] and
+/// friends directly, without wrapping them into a type with interior mutability.
+///
+/// The semantics of the methods are the same as of [`NtpUdpSocket`] methods.
+pub trait NtpUdpSocketMut {
+    /// Send the given buffer to an address provided. On success, returns the number
+    /// of bytes written.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an underlying UDP send fails
+    fn send_to(
+        &mut self,
+        buf: &[u8],
+        addr: SocketAddr,
+    ) -> impl Future<Output = Result<usize>>;
+
+    /// Receives a single datagram message on the socket. On success, returns the number
+    /// of bytes read and the origin.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an underlying UDP receive fails
+    fn recv_from(
+        &mut self,
+        buf: &mut [u8],
+    ) -> impl Future<Output = Result<(usize, SocketAddr)>>;
+}
+
+impl<T: NtpUdpSocketMut + ?Sized> NtpUdpSocketMut for &mut T {
+    fn send_to(
+        &mut self,
+        buf: &[u8],
+        addr: SocketAddr,
+    ) -> impl Future<Output = Result<usize>> {
+        (**self).send_to(buf, addr)
+    }
+
+    fn recv_from(
+        &mut self,
+        buf: &mut [u8],
+    ) -> impl Future<Output = Result<(usize, SocketAddr)>> {
+        (**self).recv_from(buf)
+    }
+}
+
+/// Adapter that lets the [`NtpUdpSocket`] based code path drive an [`NtpUdpSocketMut`] socket
+///
+/// The client never sends and receives concurrently, so the socket is borrowed by one
+/// operation at a time.
+pub(crate) struct MutSocket<'a, S: ?Sized>(RefCell<&'a mut S>);
+
+impl<'a, S: NtpUdpSocketMut + ?Sized> MutSocket<'a, S> {
+    pub(crate) fn new(socket: &'a mut S) -> Self {
+        MutSocket(RefCell::new(socket))
+    }
+}
+
+impl<S: NtpUdpSocketMut + ?Sized> NtpUdpSocket for MutSocket<'_, S> {
+    #[allow(clippy::await_holding_refcell_ref)]
+    async fn send_to(&self, buf: &[u8], addr: SocketAddr) -> Result<usize> {
+        self.0.borrow_mut().send_to(buf, addr).await
+    }
+
+    #[allow(clippy::await_holding_refcell_ref)]
+    async fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddr)> {
+        self.0.borrow_mut().recv_from(buf).await
+    }
+}
+
+/// NTP protocol version used in requests, see [`NtpContext::with_version`]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(u8)]
+pub enum NtpVersion {
+    /// `NTPv3`, RFC 1305
+    V3 = 3,
+    /// `NTPv4`, RFC 5905
+    #[default]
+    V4 = 4,
+}
+
+impl From<NtpVersion> for u8 {
+    fn from(version: NtpVersion) -> Self {
+        version as u8
+    }
+}
+
+/// Source of the time reported in [`NtpResult`], see [`NtpContext::with_time_source`]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TimeSource {
+    /// Transmit timestamp of the server response
+    #[default]
+    ServerTx,
+    /// Local receive timestamp corrected by the measured clock offset
+    LocalPlusOffset,
+}
+
+/// Direction of a packet passed to a [`PacketInspector`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PacketDirection {
+    /// Request sent to the server
+    Sent,
+    /// Datagram received from the server before any validation
+    Received,
+}
+
+/// Callback that observes raw packets exchanged with NTP servers, see
+/// [`NtpContext::with_inspector`]
+///
+/// The arguments are the direction of the packet, its bytes and the address of the server the
+/// request is sent to or the datagram is received from.
+pub type PacketInspector = fn(PacketDirection, &[u8], SocketAddr);
+
+/// Set of NTP protocol versions
+///
+/// Used to specify which protocol versions are acceptable in NTP responses
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct VersionMask(u8);
+
+impl VersionMask {
+    /// `NTPv3` protocol version
+    pub const V3: VersionMask = VersionMask(1 << 3);
+    /// `NTPv4` protocol version
+    pub const V4: VersionMask = VersionMask(1 << 4);
+
+    /// Returns the set that contains the given protocol version only
+    ///
+    /// Only the lower 3 bits of the version fit into the NTP header, the rest are discarded.
+    #[must_use]
+    pub const fn from_version(version: u8) -> Self {
+        VersionMask(1 << (version & 0x7))
+    }
+
+    /// Returns `true` if the given protocol version number belongs to the set
+    #[must_use]
+    pub const fn contains(self, version: u8) -> bool {
+        version < 8 && self.0 & (1 << version) != 0
+    }
+}
+
+impl BitOr for VersionMask {
+    type Output = VersionMask;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        VersionMask(self.0 | rhs.0)
+    }
+}
+
+impl Default for VersionMask {
+    fn default() -> Self {
+        VersionMask::V4
+    }
+}
+
+/// Optional checks of NTP responses, see [`NtpContext::with_client_config`]
+///
+/// All checks are disabled by default, [`NtpClientConfig::recommended`] enables the ones
+/// recommended by [RFC 4330](https://www.rfc-editor.org/rfc/rfc4330).
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct NtpClientConfig {
+    /// Maximum root dispersion accepted in NTP responses in the NTP short format, i.e.
+    /// 16-bit seconds and 16-bit fraction. Responses with a higher root dispersion are
+    /// rejected with [`Error::RootDispersionExceeded`]
+    pub max_root_dispersion: Option<u32>,
+}
+
+impl NtpClientConfig {
+    /// Root dispersion limit of 1 second in the NTP short format
+    pub const RECOMMENDED_MAX_ROOT_DISPERSION: u32 = 0x0001_0000;
+
+    /// Create a configuration with the checks recommended by RFC 4330, i.e. servers with a
+    /// root dispersion above 1 second are rejected
+    #[must_use]
+    pub const fn recommended() -> Self {
+        NtpClientConfig {
+            max_root_dispersion: Some(Self::RECOMMENDED_MAX_ROOT_DISPERSION),
+        }
+    }
+}
+
 /// SNTP client context that contains of objects that may be required for client's
 /// operation
 #[derive(Copy, Clone)]
 pub struct NtpContext<T: NtpTimestampGenerator> {
     pub timestamp_gen: T,
+    pub(crate) accepted_versions: VersionMask,
+    pub(crate) version: u8,
+    pub(crate) ref_id: u32,
+    pub(crate) time_source: TimeSource,
+    pub(crate) max_stratum: u8,
+    pub(crate) verify_address: bool,
+    pub(crate) units: Units,
+    pub(crate) recv_timeout: Option<Duration>,
+    pub(crate) client_config: NtpClientConfig,
+    pub(crate) max_request_age: Duration,
+    pub(crate) inspector: Option<PacketInspector>,
 }
 
 impl<T: NtpTimestampGenerator + Copy> NtpContext<T> {
+    /// Default maximum age of a request whose response is still processed, see
+    /// [`NtpContext::with_max_request_age`]
+    pub const DEFAULT_MAX_REQUEST_AGE: Duration = Duration::from_secs(5);
+
+    /// Default maximum stratum accepted in NTP responses, see [`NtpContext::with_max_stratum`].
+    /// Stratum `16` means an unsynchronized server and higher values are reserved
+    pub const DEFAULT_MAX_STRATUM: u8 = 15;
+
     /// Create SNTP client context with the given timestamp generator
     pub fn new(timestamp_gen: T) -> Self {
-        NtpContext { timestamp_gen }
+        NtpContext {
+            timestamp_gen,
+            accepted_versions: VersionMask::default(),
+            version: NtpPacket::SNTP_VERSION,
+            ref_id: 0,
+            time_source: TimeSource::default(),
+            max_stratum: Self::DEFAULT_MAX_STRATUM,
+            verify_address: true,
+            units: Units::default(),
+            recv_timeout: None,
+            client_config: NtpClientConfig::default(),
+            max_request_age: Self::DEFAULT_MAX_REQUEST_AGE,
+            inspector: None,
+        }
+    }
+
+    /// Create a builder of SNTP client context with the given timestamp generator
+    ///
+    /// See [`NtpContextBuilder`] for the available settings.
+    pub fn builder(timestamp_gen: T) -> NtpContextBuilder<T> {
+        NtpContextBuilder::new(timestamp_gen)
+    }
+
+    /// Set protocol versions accepted in NTP responses in addition to the version
+    /// of the request. By default only `NTPv4` responses are accepted.
+    ///
+    /// `NTPv3` and `NTPv4` packet layouts are identical for SNTP client needs, so
+    /// [`VersionMask::V3`] can be added to talk to servers that still reply in `NTPv3`:
+    ///
+    /// ```rust
+    /// use sntpc::{NtpContext, NtpTimestampGenerator, VersionMask};
+    /// # #[derive(Copy, Clone, Default)]
+    /// # struct Timestamp;
+    /// # impl NtpTimestampGenerator for Timestamp {
+    /// #     fn init(&mut self) {}
+    /// #     fn timestamp_sec(&self) -> u64 { 0 }
+    /// #     fn timestamp_subsec_micros(&self) -> u32 { 0 }
+    /// # }
+    ///
+    /// let context = NtpContext::new(Timestamp::default())
+    ///     .with_accepted_versions(VersionMask::V3 | VersionMask::V4);
+    /// ```
+    #[must_use]
+    pub fn with_accepted_versions(mut self, versions: VersionMask) -> Self {
+        self.accepted_versions = versions;
+        self
+    }
+
+    /// Set protocol version used in NTP requests. By default requests are sent as `NTPv4`.
+    ///
+    /// Responses are expected to have the same version as the request, so querying a legacy
+    /// server that only understands `NTPv3` requires the context to be configured like:
+    ///
+    /// ```rust
+    /// use sntpc::{NtpContext, NtpTimestampGenerator, NtpVersion};
+    /// # #[derive(Copy, Clone, Default)]
+    /// # struct Timestamp;
+    /// # impl NtpTimestampGenerator for Timestamp {
+    /// #     fn init(&mut self) {}
+    /// #     fn timestamp_sec(&self) -> u64 { 0 }
+    /// #     fn timestamp_subsec_micros(&self) -> u32 { 0 }
+    /// # }
+    ///
+    /// let context = NtpContext::new(Timestamp::default()).with_version(NtpVersion::V3);
+    /// ```
+    ///
+    /// A raw version number is accepted as well. Only the lower 3 bits of it fit into the NTP
+    /// header, the rest are discarded.
+    #[must_use]
+    pub fn with_version<V: Into<u8>>(mut self, version: V) -> Self {
+        self.version = version.into();
+        self
+    }
+
+    /// Set reference identifier sent in NTP requests. By default it is zero, as required for
+    /// the client mode.
+    ///
+    /// Servers ignore the field in client requests, so it is mostly useful for testing and
+    /// symmetric mode scenarios, e.g. to mark requests with a custom ASCII identifier:
+    ///
+    /// ```rust
+    /// use sntpc::{NtpContext, NtpTimestampGenerator, RefId};
+    /// # #[derive(Copy, Clone, Default)]
+    /// # struct Timestamp;
+    /// # impl NtpTimestampGenerator for Timestamp {
+    /// #     fn init(&mut self) {}
+    /// #     fn timestamp_sec(&self) -> u64 { 0 }
+    /// #     fn timestamp_subsec_micros(&self) -> u32 { 0 }
+    /// # }
+    ///
+    /// let context = NtpContext::new(Timestamp::default()).with_ref_id(RefId::Ascii(*b"TEST"));
+    /// ```
+    #[must_use]
+    pub fn with_ref_id(mut self, ref_id: RefId) -> Self {
+        self.ref_id = u32::from_be_bytes(ref_id.octets());
+        self
+    }
+
+    /// Set the source of the time reported in [`NtpResult`]. By default it is the server
+    /// transmit timestamp.
+    ///
+    /// [`TimeSource::LocalPlusOffset`] reports the local receive time plus the measured offset
+    /// instead, which is the time a client should set its clock to. It may be more stable than
+    /// the server transmit timestamp if the server clock has a low resolution, since the offset
+    /// averages the server receive and transmit timestamps:
+    ///
+    /// ```rust
+    /// use sntpc::{NtpContext, NtpTimestampGenerator, TimeSource};
+    /// # #[derive(Copy, Clone, Default)]
+    /// # struct Timestamp;
+    /// # impl NtpTimestampGenerator for Timestamp {
+    /// #     fn init(&mut self) {}
+    /// #     fn timestamp_sec(&self) -> u64 { 0 }
+    /// #     fn timestamp_subsec_micros(&self) -> u32 { 0 }
+    /// # }
+    ///
+    /// let context = NtpContext::new(Timestamp::default())
+    ///     .with_time_source(TimeSource::LocalPlusOffset);
+    /// ```
+    #[must_use]
+    pub fn with_time_source(mut self, time_source: TimeSource) -> Self {
+        self.time_source = time_source;
+        self
+    }
+
+    /// Set the maximum stratum accepted in NTP responses. By default it is
+    /// [`NtpContext::DEFAULT_MAX_STRATUM`], which rejects unsynchronized servers reporting
+    /// stratum `16` and the reserved values above it.
+    ///
+    /// Responses with a higher stratum are rejected with [`Error::IncorrectStratumHeaders`].
+    /// `u8::MAX` disables the check.
+    #[must_use]
+    pub fn with_max_stratum(mut self, max_stratum: u8) -> Self {
+        self.max_stratum = max_stratum;
+        self
+    }
+
+    /// Set whether the source address of NTP responses has to match the address of the server
+    /// the request has been sent to. Enabled by default.
+    ///
+    /// Disabling the check may be required to query anycast or load balanced servers that reply
+    /// from an address other than the one queried. The origin timestamp is still verified.
+    #[must_use]
+    pub fn with_address_verification(mut self, verify_address: bool) -> Self {
+        self.verify_address = verify_address;
+        self
+    }
+
+    /// Set units of the roundtrip delay and clock offset returned by [`NtpResult::roundtrip`]
+    /// and [`NtpResult::offset`]. By default they are reported in microseconds. The
+    /// [`NtpResult`] fields, durations and metrics are always in microseconds.
+    ///
+    /// Milliseconds are handy on targets that display or log the values directly:
+    ///
+    /// ```rust
+    /// use sntpc::{NtpContext, NtpTimestampGenerator, Units};
+    /// # #[derive(Copy, Clone, Default)]
+    /// # struct Timestamp;
+    /// # impl NtpTimestampGenerator for Timestamp {
+    /// #     fn init(&mut self) {}
+    /// #     fn timestamp_sec(&self) -> u64 { 0 }
+    /// #     fn timestamp_subsec_micros(&self) -> u32 { 0 }
+    /// # }
+    ///
+    /// let context = NtpContext::new(Timestamp::default()).with_units(Units::Milliseconds);
+    /// ```
+    ///
+    /// Note that [`RoundtripHistogram`](crate::RoundtripHistogram) buckets are defined in
+    /// microseconds.
+    #[must_use]
+    pub fn with_units(mut self, units: Units) -> Self {
+        self.units = units;
+        self
+    }
+
+    /// Set the maximum time to wait for an NTP response. Not set by default, so the wait is
+    /// only bounded by the socket itself.
+    ///
+    /// The timeout is passed to [`NtpUdpSocket::recv_from_timeout`], so it takes effect for
+    /// socket implementations that support it, e.g. `tokio` and `embassy` sockets with the
+    /// respective features enabled. A `std` socket is only bounded by its own read timeout,
+    /// see `UdpSocket::set_read_timeout`. Datagrams that do not answer the request are dropped
+    /// while waiting, the timeout bounds the whole wait rather than a single receive.
+    #[must_use]
+    pub fn with_recv_timeout(mut self, timeout: Duration) -> Self {
+        self.recv_timeout = Some(timeout);
+        self
+    }
+
+    /// Set optional checks of NTP responses. By default all of them are disabled.
+    ///
+    /// E.g. [`NtpClientConfig::recommended`] rejects servers whose root dispersion exceeds
+    /// 1 second with [`Error::RootDispersionExceeded`].
+    #[must_use]
+    pub fn with_client_config(
+        mut self,
+        client_config: NtpClientConfig,
+    ) -> Self {
+        self.client_config = client_config;
+        self
+    }
+
+    /// Set the maximum time between sending a request and receiving the response. By default
+    /// it is [`NtpContext::DEFAULT_MAX_REQUEST_AGE`].
+    ///
+    /// A response to an older request, e.g. one held while the event loop has been stalled,
+    /// is rejected with [`Error::StaleRequest`], since the calculated offset would include the
+    /// stall. The age is measured with the timestamp generator of the context.
+    #[must_use]
+    pub fn with_max_request_age(mut self, max_request_age: Duration) -> Self {
+        self.max_request_age = max_request_age;
+        self
+    }
+
+    /// Set a callback invoked with the raw bytes of every request right after it is sent and
+    /// of every datagram right after it is received, before the response is validated. Not set
+    /// by default. The transmit and receive timestamps are taken before the callback is
+    /// invoked, so its run time does not affect the roundtrip and offset.
+    ///
+    /// The callback allows to dump the exchange to debug failures without enabling the `log`
+    /// or `defmt` features:
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "std")]
+    /// # fn main() {
+    /// use sntpc::{NtpContext, PacketDirection, StdTimestampGen};
+    /// use std::net::SocketAddr;
+    ///
+    /// fn dump(direction: PacketDirection, packet: &[u8], addr: SocketAddr) {
+    ///     eprintln!("{direction:?} {addr}: {packet:02x?}");
+    /// }
+    ///
+    /// let context = NtpContext::new(StdTimestampGen::default()).with_inspector(dump);
+    /// # }
+    /// # #[cfg(not(feature = "std"))]
+    /// # fn main() {}
+    /// ```
+    #[must_use]
+    pub fn with_inspector(mut self, inspector: PacketInspector) -> Self {
+        self.inspector = Some(inspector);
+        self
+    }
+}
+
+/// Builder of [`NtpContext`] with optional settings
+///
+/// Settings that are not set keep the defaults of [`NtpContext::new`].
+///
+/// ```rust
+/// use sntpc::{NtpContext, NtpTimestampGenerator, NtpVersion};
+/// # #[derive(Copy, Clone, Default)]
+/// # struct Timestamp;
+/// # impl NtpTimestampGenerator for Timestamp {
+/// #     fn init(&mut self) {}
+/// #     fn timestamp_sec(&self) -> u64 { 0 }
+/// #     fn timestamp_subsec_micros(&self) -> u32 { 0 }
+/// # }
+///
+/// let context = NtpContext::builder(Timestamp::default())
+///     .accept_version(NtpVersion::V3)
+///     .max_stratum(15)
+///     .verify_address(false)
+///     .build();
+/// ```
+#[derive(Copy, Clone)]
+pub struct NtpContextBuilder<T: NtpTimestampGenerator> {
+    context: NtpContext<T>,
+}
+
+impl<T: NtpTimestampGenerator + Copy> NtpContextBuilder<T> {
+    /// Create a builder with the given timestamp generator
+    pub fn new(timestamp_gen: T) -> Self {
+        NtpContextBuilder {
+            context: NtpContext::new(timestamp_gen),
+        }
+    }
+
+    /// Accept the given protocol version in NTP responses in addition to the version of the
+    /// request, see [`NtpContext::with_accepted_versions`]
+    #[must_use]
+    pub fn accept_version<V: Into<u8>>(mut self, version: V) -> Self {
+        self.context.accepted_versions = self.context.accepted_versions
+            | VersionMask::from_version(version.into());
+        self
+    }
+
+    /// Set protocol version used in NTP requests, see [`NtpContext::with_version`]
+    #[must_use]
+    pub fn version<V: Into<u8>>(mut self, version: V) -> Self {
+        self.context = self.context.with_version(version);
+        self
+    }
+
+    /// Set reference identifier sent in NTP requests, see [`NtpContext::with_ref_id`]
+    #[must_use]
+    pub fn ref_id(mut self, ref_id: RefId) -> Self {
+        self.context = self.context.with_ref_id(ref_id);
+        self
+    }
+
+    /// Set the source of the reported time, see [`NtpContext::with_time_source`]
+    #[must_use]
+    pub fn time_source(mut self, time_source: TimeSource) -> Self {
+        self.context = self.context.with_time_source(time_source);
+        self
+    }
+
+    /// Set the maximum stratum accepted in NTP responses, see
+    /// [`NtpContext::with_max_stratum`]
+    #[must_use]
+    pub fn max_stratum(mut self, max_stratum: u8) -> Self {
+        self.context = self.context.with_max_stratum(max_stratum);
+        self
+    }
+
+    /// Set whether the source address of NTP responses is verified, see
+    /// [`NtpContext::with_address_verification`]
+    #[must_use]
+    pub fn verify_address(mut self, verify_address: bool) -> Self {
+        self.context = self.context.with_address_verification(verify_address);
+        self
+    }
+
+    /// Set units of the reported roundtrip delay and clock offset, see
+    /// [`NtpContext::with_units`]
+    #[must_use]
+    pub fn units(mut self, units: Units) -> Self {
+        self.context = self.context.with_units(units);
+        self
+    }
+
+    /// Set the maximum time to wait for an NTP response, see
+    /// [`NtpContext::with_recv_timeout`]
+    #[must_use]
+    pub fn recv_timeout(mut self, timeout: Duration) -> Self {
+        self.context = self.context.with_recv_timeout(timeout);
+        self
+    }
+
+    /// Set optional checks of NTP responses, see [`NtpContext::with_client_config`]
+    #[must_use]
+    pub fn client_config(mut self, client_config: NtpClientConfig) -> Self {
+        self.context = self.context.with_client_config(client_config);
+        self
+    }
+
+    /// Set the maximum time between sending a request and receiving the response, see
+    /// [`NtpContext::with_max_request_age`]
+    #[must_use]
+    pub fn max_request_age(mut self, max_request_age: Duration) -> Self {
+        self.context = self.context.with_max_request_age(max_request_age);
+        self
+    }
+
+    /// Set a callback observing raw packets, see [`NtpContext::with_inspector`]
+    #[must_use]
+    pub fn inspector(mut self, inspector: PacketInspector) -> Self {
+        self.context = self.context.with_inspector(inspector);
+        self
+    }
+
+    /// Build the context
+    #[must_use]
+    pub fn build(self) -> NtpContext<T> {
+        self.context
     }
 }
 
 /// Preserve SNTP request sending operation result required during receiving and processing
 /// state
-#[derive(Copy, Clone, Debug)]
+///
+/// The state may be logged, stored along with other application state or, with the `serde`
+/// feature enabled, serialized while waiting for the response.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SendRequestResult {
+    /// Local time the request has been sent at, which the server echoes back as the origin
+    /// timestamp
     pub(crate) originate_timestamp: u64,
+    /// Protocol version of the request
     pub(crate) version: u8,
 }
 
-impl From<NtpPacket> for SendRequestResult {
-    fn from(ntp_packet: NtpPacket) -> Self {
+impl SendRequestResult {
+    /// Restore the request state from the originate timestamp in the NTP timestamp format and
+    /// the protocol version of a request sent by other means, e.g. a custom transport
+    ///
+    /// Responses are only accepted if they echo `originate_timestamp` back, so it has to be
+    /// the exact transmit timestamp of the sent request.
+    #[cfg(feature = "request-parts")]
+    #[must_use]
+    pub const fn from_parts(originate_timestamp: u64, version: u8) -> Self {
+        Self::new(originate_timestamp, version)
+    }
+
+    pub(crate) const fn new(originate_timestamp: u64, version: u8) -> Self {
         SendRequestResult {
-            originate_timestamp: ntp_packet.tx_timestamp,
-            version: ntp_packet.li_vn_mode,
+            originate_timestamp,
+            version: version & (VERSION_MASK >> VERSION_SHIFT),
         }
     }
+
+    /// Returns the transmit timestamp of the request in the NTP timestamp format, i.e.
+    /// seconds since 1900 in the upper 32 bits and the seconds fraction in the lower 32 bits
+    #[must_use]
+    pub fn originate_timestamp(&self) -> u64 {
+        self.originate_timestamp
+    }
+
+    /// Returns the protocol version of the request
+    #[must_use]
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+}
+
+impl From<NtpPacket> for SendRequestResult {
+    fn from(ntp_packet: NtpPacket) -> Self {
+        SendRequestResult::new(
+            ntp_packet.tx_timestamp,
+            (ntp_packet.li_vn_mode & VERSION_MASK) >> VERSION_SHIFT,
+        )
+    }
 }
 
 pub(crate) trait NtpNum {
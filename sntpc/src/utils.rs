@@ -1,47 +1,86 @@
 //! Helper utils to synchronize time of a system
 //!
-//! Currently, Unix and Windows based systems are supported
+//! Currently, Unix and Windows based systems are supported. On other systems
+//! [`update_system_time`] returns [`UtilsError::Unsupported`]
 #[cfg(any(feature = "log", feature = "defmt"))]
 use crate::log::debug;
 #[cfg(any(feature = "log", feature = "defmt"))]
 use chrono::Timelike;
 use chrono::{Local, TimeZone, Utc};
 
+use core::fmt::{Display, Formatter};
+
 #[cfg(unix)]
 use unix::sync_time;
+#[cfg(not(any(unix, windows)))]
+use unsupported::sync_time;
 #[cfg(windows)]
 use windows::sync_time;
 
 #[cfg(unix)]
 mod unix;
+#[cfg(any(not(any(unix, windows)), test))]
+pub(crate) mod unsupported;
 #[cfg(windows)]
 mod windows;
 
+/// Errors of the system time synchronization
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum UtilsError {
+    /// The given seconds and nanoseconds do not represent a valid point in time
+    InvalidTime,
+    /// Setting the system time is not supported on the target OS
+    Unsupported,
+    /// The platform command that sets the system time could not be run or has failed,
+    /// e.g. because of insufficient privileges
+    CommandFailed,
+}
+
+impl Display for UtilsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            UtilsError::InvalidTime => write!(f, "invalid time"),
+            UtilsError::Unsupported => {
+                write!(f, "system time update is not supported on this OS")
+            }
+            UtilsError::CommandFailed => {
+                write!(f, "system time update command failed")
+            }
+        }
+    }
+}
+
 /// Set up system time based on the given parameters
 /// Args:
 /// * `sec` - Seconds since UNIX epoch start
 /// * `nsec` - Fraction of seconds from an NTP response
-pub fn update_system_time(sec: u32, nsec: u32) {
-    let time = Utc.timestamp_opt(i64::from(sec), nsec);
+///
+/// # Errors
+///
+/// Will return `Err` if the time is invalid, the target OS is neither Unix nor Windows
+/// based or the system time could not be set
+pub fn update_system_time(sec: u32, nsec: u32) -> Result<(), UtilsError> {
+    let time = Utc
+        .timestamp_opt(i64::from(sec), nsec)
+        .single()
+        .ok_or(UtilsError::InvalidTime)?;
+    let local_time = time.with_timezone(&Local);
+    #[cfg(any(feature = "log", feature = "defmt"))]
+    debug!(
+        "UTC time: {:02}:{:02}:{:02}",
+        time.hour(),
+        time.minute(),
+        time.second()
+    );
+    #[cfg(any(feature = "log", feature = "defmt"))]
+    debug!(
+        "{} time: {:02}:{:02}:{:02}",
+        local_time.offset().to_string().as_str(),
+        local_time.hour(),
+        local_time.minute(),
+        local_time.second()
+    );
 
-    if let Some(time) = time.single() {
-        let local_time = time.with_timezone(&Local);
-        #[cfg(any(feature = "log", feature = "defmt"))]
-        debug!(
-            "UTC time: {:02}:{:02}:{:02}",
-            time.hour(),
-            time.minute(),
-            time.second()
-        );
-        #[cfg(any(feature = "log", feature = "defmt"))]
-        debug!(
-            "{} time: {:02}:{:02}:{:02}",
-            local_time.offset(),
-            local_time.hour(),
-            local_time.minute(),
-            local_time.second()
-        );
-
-        sync_time(local_time);
-    }
+    sync_time(local_time)
 }
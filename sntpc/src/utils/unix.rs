@@ -1,10 +1,12 @@
+use super::UtilsError;
+
 use std::process::Command;
 
 use chrono::{DateTime, Datelike, Local, Timelike};
 
 /// Synchronize system time with the platform specific
 /// command line tool
-pub(super) fn sync_time(time: DateTime<Local>) {
+pub(super) fn sync_time(time: DateTime<Local>) -> Result<(), UtilsError> {
     let time_str = format!(
         "{}/{}/{} {:02}:{:02}:{:02}",
         time.month(),
@@ -17,12 +19,11 @@ pub(super) fn sync_time(time: DateTime<Local>) {
     let sync_cmd_status = Command::new("date")
         .args(["-s", time_str.as_str()])
         .status()
-        .expect("Unable to execute date command");
+        .map_err(|_| UtilsError::CommandFailed)?;
 
-    if !sync_cmd_status.success() {
-        eprintln!(
-            "Date command exit status {}",
-            sync_cmd_status.code().unwrap()
-        );
+    if sync_cmd_status.success() {
+        Ok(())
+    } else {
+        Err(UtilsError::CommandFailed)
     }
 }
@@ -0,0 +1,8 @@
+use super::UtilsError;
+
+use chrono::{DateTime, Local};
+
+/// Fallback for systems without a known way to set the time
+pub(crate) fn sync_time(_time: DateTime<Local>) -> Result<(), UtilsError> {
+    Err(UtilsError::Unsupported)
+}
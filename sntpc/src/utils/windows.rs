@@ -1,11 +1,13 @@
+use super::UtilsError;
+
 use std::process::Command;
 
 use chrono::{DateTime, Datelike, Local, Timelike};
 
 /// Synchronize system time with the platform specific
 /// command line tool
-pub(super) fn sync_time(time: DateTime<Local>) {
-    let cmd = Command::new("cmd")
+pub(super) fn sync_time(time: DateTime<Local>) -> Result<(), UtilsError> {
+    let status = Command::new("cmd")
         .args([
             "/C",
             format!(
@@ -19,16 +21,12 @@ pub(super) fn sync_time(time: DateTime<Local>) {
             )
             .as_str(),
         ])
-        .spawn();
+        .status()
+        .map_err(|_| UtilsError::CommandFailed)?;
 
-    match cmd {
-        Ok(mut child) => {
-            child
-                .wait()
-                .expect("Time synchronization finished incorrectly");
-        }
-        Err(e) => {
-            eprintln!("Error occurred: {e}");
-        }
-    };
+    if status.success() {
+        Ok(())
+    } else {
+        Err(UtilsError::CommandFailed)
+    }
 }